@@ -0,0 +1,212 @@
+//! Integration with the `calloop` event loop.
+//!
+//! Instead of running a background thread, [`HotkeySource`] registers the
+//! keyboard file descriptors with a calloop loop and dispatches hotkey events
+//! from it, which suits Wayland tools that are already built around calloop.
+
+use crate::event::HotkeyEvent;
+use crate::hotkey::Hotkey;
+use crate::linux::{find_selected_keyboards, set_nonblocking, with_sandbox_hint, EventProcessor};
+use crate::listener::ListenerConfig;
+use ::calloop::{EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory};
+use anyhow::Result;
+use evdev::Device;
+use std::io::ErrorKind;
+use std::os::fd::{AsRawFd, BorrowedFd};
+
+/// A non-blocking source of evdev events backed by a file descriptor.
+pub(crate) trait KeyEventReader: AsRawFd {
+    /// Read the events that are currently available.
+    fn read_events(&mut self) -> std::io::Result<Vec<evdev::InputEvent>>;
+}
+
+impl KeyEventReader for Device {
+    fn read_events(&mut self) -> std::io::Result<Vec<evdev::InputEvent>> {
+        self.fetch_events().map(|events| events.collect())
+    }
+}
+
+/// A calloop event source emitting [`HotkeyEvent`]s for the keyboards.
+///
+/// Created with [`HotkeyListenerBuilder::build_calloop_source`] and inserted
+/// with `LoopHandle::insert_source`. Keyboards that fail are dropped and not
+/// reconnected; the source removes itself once none are left. Options that
+/// rely on the listener thread polling, like `max_hold` and `long_press`, have no effect.
+///
+/// [`HotkeyListenerBuilder::build_calloop_source`]: crate::HotkeyListenerBuilder::build_calloop_source
+pub struct HotkeySource {
+    readers: Vec<Box<dyn KeyEventReader>>,
+    processor: EventProcessor,
+}
+
+impl HotkeySource {
+    /// Open the keyboards the listener would use.
+    pub(crate) fn discover(hotkeys: Vec<Hotkey>, config: ListenerConfig) -> Result<Self> {
+        let keyboards = find_selected_keyboards(
+            config.device_paths.as_deref(),
+            config.pinned_keyboard.as_deref(),
+            config.keyboard_group,
+            config.device_filter.as_ref(),
+            !config.keep_duplicate_devices,
+        )
+        .map_err(with_sandbox_hint)?;
+        set_nonblocking(&keyboards)?;
+        let readers = keyboards
+            .into_iter()
+            .map(|device| Box::new(device) as Box<dyn KeyEventReader>)
+            .collect();
+        Ok(Self::new(readers, hotkeys, config))
+    }
+
+    pub(crate) fn new(
+        readers: Vec<Box<dyn KeyEventReader>>,
+        hotkeys: Vec<Hotkey>,
+        config: ListenerConfig,
+    ) -> Self {
+        Self {
+            readers,
+            processor: EventProcessor::new(hotkeys, config),
+        }
+    }
+}
+
+impl EventSource for HotkeySource {
+    type Event = HotkeyEvent;
+    type Metadata = ();
+    type Ret = ();
+    type Error = std::io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        _readiness: Readiness,
+        _token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, Self::Error>
+    where
+        F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
+    {
+        // Reads are non-blocking, so polling every reader is cheap and keeps
+        // the token bookkeeping out of the way
+        let processor = &mut self.processor;
+        self.readers
+            .retain_mut(|reader| match reader.read_events() {
+                Ok(events) => {
+                    for hotkey_event in processor.process_batch(events) {
+                        callback(hotkey_event, &mut ());
+                    }
+                    true
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => true,
+                Err(e) => {
+                    // Dropping the reader closes its fd, which removes it from the poll
+                    log::warn!("Dropping keyboard after read error: {}", e);
+                    false
+                }
+            });
+
+        if self.readers.is_empty() {
+            log::warn!("No keyboards left, removing hotkey source");
+            return Ok(PostAction::Remove);
+        }
+        Ok(PostAction::Continue)
+    }
+
+    fn register(
+        &mut self,
+        poll: &mut Poll,
+        token_factory: &mut TokenFactory,
+    ) -> ::calloop::Result<()> {
+        for reader in &self.readers {
+            // SAFETY: the fd is owned by the reader, which stays in this source
+            // until it is unregistered or the reader is dropped, closing the fd.
+            unsafe {
+                let fd = BorrowedFd::borrow_raw(reader.as_raw_fd());
+                poll.register(fd, Interest::READ, Mode::Level, token_factory.token())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn reregister(
+        &mut self,
+        poll: &mut Poll,
+        token_factory: &mut TokenFactory,
+    ) -> ::calloop::Result<()> {
+        for reader in &self.readers {
+            // SAFETY: see `register`.
+            let fd = unsafe { BorrowedFd::borrow_raw(reader.as_raw_fd()) };
+            poll.reregister(fd, Interest::READ, Mode::Level, token_factory.token())?;
+        }
+        Ok(())
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> ::calloop::Result<()> {
+        for reader in &self.readers {
+            // SAFETY: see `register`.
+            let fd = unsafe { BorrowedFd::borrow_raw(reader.as_raw_fd()) };
+            poll.unregister(fd)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::parse_hotkey;
+    use ::calloop::EventLoop;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    /// Reads `[code, value]` byte pairs written to a socket as key events.
+    struct MockReader(UnixStream);
+
+    impl AsRawFd for MockReader {
+        fn as_raw_fd(&self) -> std::os::fd::RawFd {
+            self.0.as_raw_fd()
+        }
+    }
+
+    impl KeyEventReader for MockReader {
+        fn read_events(&mut self) -> std::io::Result<Vec<evdev::InputEvent>> {
+            let mut buf = [0u8; 64];
+            let n = self.0.read(&mut buf)?;
+            Ok(buf[..n]
+                .chunks_exact(2)
+                .map(|pair| {
+                    evdev::InputEvent::new(evdev::EventType::KEY, pair[0].into(), pair[1].into())
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_source_dispatches_hotkey_events() {
+        let (mut writer, reader) = UnixStream::pair().unwrap();
+        reader.set_nonblocking(true).unwrap();
+        let source = HotkeySource::new(
+            vec![Box::new(MockReader(reader))],
+            vec![parse_hotkey("F8").unwrap()],
+            ListenerConfig::default(),
+        );
+
+        let mut event_loop: EventLoop<Vec<HotkeyEvent>> = EventLoop::try_new().unwrap();
+        event_loop
+            .handle()
+            .insert_source(source, |event, _, events| events.push(event))
+            .unwrap();
+
+        let f8 = evdev::Key::KEY_F8.code() as u8;
+        writer.write_all(&[f8, 1, f8, 0]).unwrap();
+
+        let mut events = Vec::new();
+        event_loop
+            .dispatch(Duration::from_millis(500), &mut events)
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![HotkeyEvent::Pressed(0), HotkeyEvent::Released(0)]
+        );
+    }
+}
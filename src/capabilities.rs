@@ -0,0 +1,90 @@
+//! Runtime report of compiled-in features and platform support.
+
+/// What this build of the crate can do.
+///
+/// Returned by [`capabilities`] so consumers can adapt their behavior or
+/// show diagnostics without repeating the crate's `cfg` logic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// Name of the platform backend: `"evdev"` on Linux, `"rdev"` on macOS,
+    /// or `"none"` on unsupported platforms.
+    pub backend: &'static str,
+    /// The `crossbeam` feature: the event channel is a crossbeam channel.
+    pub crossbeam: bool,
+    /// The `raw-evdev` feature: raw evdev events can be observed.
+    pub raw_evdev: bool,
+    /// The `libinput` feature: libinput is used when `/dev/input` can't be read.
+    pub libinput: bool,
+    /// The `x11` feature: hotkeys can be grabbed through X11 when `/dev/input`
+    /// can't be read.
+    pub x11: bool,
+    /// The `calloop` feature: hotkeys can be read from a calloop event source.
+    pub calloop: bool,
+    /// The `tokio` feature: listener status can be watched and events
+    /// received through a tokio channel.
+    pub tokio: bool,
+    /// The `futures` feature: events can be received as a `futures::Stream`
+    /// on any executor.
+    pub futures: bool,
+    /// The `serde` feature: keys, modifiers and hotkeys can be serialized
+    /// and deserialized.
+    pub serde: bool,
+    /// The `test-util` feature: listeners can read a `MockKeyboard` through
+    /// `build_mock` instead of real devices.
+    pub test_util: bool,
+    /// Whether hotkeys can be grabbed so other applications don't see them.
+    /// True on Linux, where `grab_keys` grabs whole keyboards; macOS only
+    /// observes keys.
+    pub can_grab: bool,
+    /// Whether events can identify the keyboard they came from, through
+    /// `report_devices` and `subscribe_with_device`. True on Linux; macOS
+    /// doesn't tell keyboards apart.
+    pub can_report_device_id: bool,
+}
+
+/// Report the features and platform support compiled into this build.
+pub fn capabilities() -> Capabilities {
+    let backend = if cfg!(target_os = "linux") {
+        "evdev"
+    } else if cfg!(target_os = "macos") {
+        "rdev"
+    } else {
+        "none"
+    };
+    Capabilities {
+        backend,
+        crossbeam: cfg!(feature = "crossbeam"),
+        raw_evdev: cfg!(all(target_os = "linux", feature = "raw-evdev")),
+        libinput: cfg!(all(target_os = "linux", feature = "libinput")),
+        x11: cfg!(all(target_os = "linux", feature = "x11")),
+        calloop: cfg!(all(target_os = "linux", feature = "calloop")),
+        tokio: cfg!(feature = "tokio"),
+        futures: cfg!(feature = "futures"),
+        serde: cfg!(feature = "serde"),
+        test_util: cfg!(feature = "test-util"),
+        can_grab: cfg!(target_os = "linux"),
+        can_report_device_id: cfg!(target_os = "linux"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_matches_target() {
+        let capabilities = capabilities();
+        #[cfg(target_os = "linux")]
+        assert_eq!(capabilities.backend, "evdev");
+        #[cfg(target_os = "macos")]
+        assert_eq!(capabilities.backend, "rdev");
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        assert_eq!(capabilities.backend, "none");
+        assert_eq!(capabilities.tokio, cfg!(feature = "tokio"));
+        assert_eq!(capabilities.futures, cfg!(feature = "futures"));
+        assert_eq!(capabilities.serde, cfg!(feature = "serde"));
+        assert_eq!(capabilities.test_util, cfg!(feature = "test-util"));
+        assert_eq!(capabilities.can_report_device_id, cfg!(target_os = "linux"));
+    }
+}
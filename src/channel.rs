@@ -0,0 +1,39 @@
+//! Event channel used between the listener thread and the handle.
+//!
+//! Defaults to `std::sync::mpsc`. With the `crossbeam` feature the channel is
+//! backed by `crossbeam_channel` instead, so consumers can `select!` on it.
+
+#[cfg(not(feature = "crossbeam"))]
+pub(crate) use std::sync::mpsc::{
+    channel, sync_channel as bounded, Receiver, Sender, SyncSender as BoundedSender,
+};
+
+#[cfg(feature = "crossbeam")]
+pub(crate) use crossbeam_channel::{
+    bounded, unbounded as channel, Receiver, Sender, Sender as BoundedSender,
+};
+
+/// Why [`try_send`] didn't send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TrySendError {
+    Full,
+    Disconnected,
+}
+
+/// Send `value` on a bounded channel without blocking.
+#[cfg(not(feature = "crossbeam"))]
+pub(crate) fn try_send<T>(tx: &BoundedSender<T>, value: T) -> Result<(), TrySendError> {
+    tx.try_send(value).map_err(|e| match e {
+        std::sync::mpsc::TrySendError::Full(_) => TrySendError::Full,
+        std::sync::mpsc::TrySendError::Disconnected(_) => TrySendError::Disconnected,
+    })
+}
+
+/// Send `value` on a bounded channel without blocking.
+#[cfg(feature = "crossbeam")]
+pub(crate) fn try_send<T>(tx: &BoundedSender<T>, value: T) -> Result<(), TrySendError> {
+    tx.try_send(value).map_err(|e| match e {
+        crossbeam_channel::TrySendError::Full(_) => TrySendError::Full,
+        crossbeam_channel::TrySendError::Disconnected(_) => TrySendError::Disconnected,
+    })
+}
@@ -0,0 +1,52 @@
+//! Typed errors for failures callers may want to handle.
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// Errors callers can match on.
+///
+/// [`validate_hotkey`](crate::validate_hotkey) returns it directly. Where a
+/// function returns `anyhow::Error`, like `start()`, recover it with
+/// `error.downcast_ref::<HotkeyError>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HotkeyError {
+    /// The string isn't a valid hotkey, with the reason `parse_hotkey` gives.
+    InvalidHotkey(String),
+    /// macOS refused to create the keyboard event tap. This almost always
+    /// means the process hasn't been granted Accessibility (or Input
+    /// Monitoring) permission in System Settings.
+    EventTap,
+    /// The macOS run loop source for the event tap couldn't be created.
+    LoopSource,
+    /// Linux `/dev/input` event nodes that can't be opened for reading, with
+    /// a hint on how to fix it, see [`check_permissions`](crate::check_permissions).
+    PermissionDenied { paths: Vec<PathBuf>, hint: String },
+    /// Any other failure reported by the platform while starting to listen.
+    Platform(String),
+    /// The listener was built with no hotkeys or chords although
+    /// [`require_hotkeys`](crate::HotkeyListenerBuilder::require_hotkeys)
+    /// was set.
+    NoHotkeys,
+}
+
+impl fmt::Display for HotkeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HotkeyError::InvalidHotkey(reason) => write!(f, "Invalid hotkey: {}", reason),
+            HotkeyError::EventTap => write!(
+                f,
+                "Failed to create the keyboard event tap; grant Accessibility permission to this app"
+            ),
+            HotkeyError::LoopSource => write!(f, "Failed to add the event tap to the run loop"),
+            HotkeyError::PermissionDenied { paths, hint } => {
+                let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                write!(f, "Permission denied reading {}; {}", paths.join(", "), hint)
+            }
+            HotkeyError::Platform(cause) => write!(f, "Failed to listen for keyboard events: {}", cause),
+            HotkeyError::NoHotkeys => write!(f, "No hotkeys were added to the listener"),
+        }
+    }
+}
+
+impl std::error::Error for HotkeyError {}
@@ -0,0 +1,283 @@
+//! Replay of `evemu-record` captures through the hotkey matcher.
+//!
+//! Captures are plain text. Only the `E:` event lines are interpreted; device
+//! description lines and comments are skipped:
+//!
+//! ```text
+//! E: 0.000001 0001 0042 0001    # EV_KEY / KEY_F8                 1
+//! ```
+//!
+//! Events keep their recorded timestamps and are replayed one `SYN_REPORT`
+//! at a time, the way the kernel delivers them. An event recorded at exactly
+//! `0.000000` has no usable time and is stamped when its hotkey fires.
+
+use crate::channel::Sender;
+use crate::event::HotkeyEvent;
+use crate::hotkey::Hotkey;
+use crate::linux::EventProcessor;
+use crate::listener::ListenerConfig;
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A single event line from an evemu capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct EvemuEvent {
+    /// Timestamp of the event as recorded by the kernel.
+    pub time: Duration,
+    pub type_: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+impl EvemuEvent {
+    fn to_input_event(self) -> evdev::InputEvent {
+        evdev::InputEvent::from(libc::input_event {
+            time: libc::timeval {
+                tv_sec: self.time.as_secs() as libc::time_t,
+                tv_usec: self.time.subsec_micros() as libc::suseconds_t,
+            },
+            type_: self.type_,
+            code: self.code,
+            value: self.value,
+        })
+    }
+
+    fn is_syn_report(self) -> bool {
+        self.type_ == evdev::EventType::SYNCHRONIZATION.0
+            && self.code == evdev::Synchronization::SYN_REPORT.0
+    }
+}
+
+/// Split events into the batches a reader would see, each ending with its
+/// `SYN_REPORT`. Events after the last report form a final batch.
+fn split_reports(events: Vec<EvemuEvent>) -> Vec<Vec<EvemuEvent>> {
+    let mut batches = Vec::new();
+    let mut batch = Vec::new();
+    for event in events {
+        batch.push(event);
+        if event.is_syn_report() {
+            batches.push(std::mem::take(&mut batch));
+        }
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+/// Read and parse an evemu capture file.
+pub(crate) fn read_evemu(path: &Path) -> Result<Vec<EvemuEvent>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read evemu capture {:?}", path))?;
+    parse_evemu(&contents)
+}
+
+/// Parse the event lines of an evemu capture.
+pub(crate) fn parse_evemu(contents: &str) -> Result<Vec<EvemuEvent>> {
+    let mut events = Vec::new();
+
+    for (lineno, line) in contents.lines().enumerate() {
+        let Some(rest) = line.strip_prefix("E:") else {
+            continue;
+        };
+        // Drop the trailing human-readable comment
+        let rest = rest.split('#').next().unwrap_or_default();
+        events.push(
+            parse_event_line(rest)
+                .with_context(|| format!("Invalid evemu event on line {}", lineno + 1))?,
+        );
+    }
+
+    Ok(events)
+}
+
+fn parse_event_line(line: &str) -> Result<EvemuEvent> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [time, type_, code, value] = fields[..] else {
+        return Err(anyhow!("Expected 4 fields, found {}", fields.len()));
+    };
+
+    let (secs, micros) = time
+        .split_once('.')
+        .ok_or_else(|| anyhow!("Invalid timestamp: {}", time))?;
+    let time = Duration::from_secs(secs.parse()?) + Duration::from_micros(micros.parse()?);
+
+    Ok(EvemuEvent {
+        time,
+        type_: u16::from_str_radix(type_, 16)?,
+        code: u16::from_str_radix(code, 16)?,
+        value: value.parse()?,
+    })
+}
+
+/// Replay captured events in a background thread, preserving their original timing.
+pub(crate) fn start_replay(
+    events: Vec<EvemuEvent>,
+    hotkeys: Vec<Hotkey>,
+    config: ListenerConfig,
+    running: Arc<AtomicBool>,
+    tx: Sender<HotkeyEvent>,
+) {
+    let listener_thread = config.shared.thread.clone();
+    let status = config.shared.status.clone();
+    let mut processor = EventProcessor::new(hotkeys, config);
+
+    listener_thread.set(thread::spawn(move || {
+        let _stopped = status.stop_on_drop();
+        let Some(first) = events.first().map(|e| e.time) else {
+            return;
+        };
+        let started = Instant::now();
+
+        for batch in split_reports(events) {
+            // The batch is delivered once its last event has been reported
+            let last = batch.last().map_or(first, |e| e.time);
+            let due = started + last.saturating_sub(first);
+            while running.load(Ordering::Relaxed) && Instant::now() < due {
+                thread::sleep((due - Instant::now()).min(Duration::from_millis(10)));
+            }
+            if !running.load(Ordering::Relaxed) {
+                return;
+            }
+
+            let batch = batch.into_iter().map(EvemuEvent::to_input_event).collect();
+            for hotkey_event in processor.process_batch(batch) {
+                let _ = tx.send(hotkey_event);
+            }
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::parse_hotkey;
+    use crate::listener::HotkeyListener;
+    use std::time::UNIX_EPOCH;
+
+    const CAPTURE: &str = "\
+# EVEMU 1.3
+# Input device name: \"AT Translated Set 2 keyboard\"
+N: AT Translated Set 2 keyboard
+I: 0011 0001 0001 ab41
+E: 0.000001 0004 0004 0066	# EV_MSC / MSC_SCAN             102
+E: 0.000001 0001 0042 0001	# EV_KEY / KEY_F8                 1
+E: 0.000001 0000 0000 0000	# ------------ SYN_REPORT (0) ---------- +0ms
+E: 0.080012 0001 0042 0000	# EV_KEY / KEY_F8                 0
+E: 0.080012 0000 0000 0000	# ------------ SYN_REPORT (0) ---------- +80ms
+E: 0.200000 0001 002a 0001	# EV_KEY / KEY_LEFTSHIFT          1
+E: 0.200000 0000 0000 0000	# ------------ SYN_REPORT (0) ---------- +120ms
+E: 0.210000 0001 0042 0001	# EV_KEY / KEY_F8                 1
+E: 0.210000 0000 0000 0000	# ------------ SYN_REPORT (0) ---------- +10ms
+E: 0.210000 0001 0042 0002	# EV_KEY / KEY_F8                 2
+E: 0.210000 0000 0000 0000	# ------------ SYN_REPORT (0) ---------- +0ms
+E: 0.250000 0001 0042 0000	# EV_KEY / KEY_F8                 0
+E: 0.250000 0000 0000 0000	# ------------ SYN_REPORT (0) ---------- +40ms
+E: 0.260000 0001 002a 0000	# EV_KEY / KEY_LEFTSHIFT          0
+";
+
+    #[test]
+    fn test_parse_event_lines() {
+        let events = parse_evemu(CAPTURE).unwrap();
+        assert_eq!(events.len(), 14);
+        assert_eq!(
+            events[3],
+            EvemuEvent {
+                time: Duration::from_micros(80_012),
+                type_: 1,
+                code: 0x42,
+                value: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_split_reports_ends_batches_at_syn_report() {
+        let batches = split_reports(parse_evemu(CAPTURE).unwrap());
+        let sizes: Vec<usize> = batches.iter().map(Vec::len).collect();
+        // The trailing Shift release has no report but is still replayed
+        assert_eq!(sizes, vec![3, 2, 2, 2, 2, 2, 1]);
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_line() {
+        assert!(parse_evemu("E: 0.000001 0001 0042").is_err());
+    }
+
+    #[test]
+    fn test_replay_capture() {
+        let path = std::env::temp_dir().join(format!("hotkey-evemu-{}.txt", std::process::id()));
+        std::fs::write(&path, CAPTURE).unwrap();
+
+        let hotkeys = vec![
+            parse_hotkey("F8").unwrap(),
+            parse_hotkey("Shift+F8").unwrap(),
+        ];
+        let handle = HotkeyListener::from_evemu(&path, hotkeys)
+            .unwrap()
+            .start()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let events: Vec<HotkeyEvent> = std::iter::from_fn(|| handle.recv().ok()).collect();
+        assert_eq!(
+            events,
+            vec![
+                HotkeyEvent::Pressed(0),
+                HotkeyEvent::Released(0),
+                HotkeyEvent::Pressed(1),
+                HotkeyEvent::Released(1),
+            ]
+        );
+        // The replay ended, so the listener no longer reports running
+        #[cfg(feature = "tokio")]
+        assert!(!handle.status().borrow().running);
+    }
+
+    fn replay(capture: &str, hotkeys: Vec<Hotkey>, config: ListenerConfig) -> Vec<HotkeyEvent> {
+        let running = Arc::new(AtomicBool::new(true));
+        let (tx, rx) = crate::channel::channel();
+        let thread = config.shared.thread.clone();
+        start_replay(parse_evemu(capture).unwrap(), hotkeys, config, running, tx);
+        assert!(thread.join(Some(Duration::from_secs(2))));
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    }
+
+    #[test]
+    fn test_replay_keeps_recorded_timestamps() {
+        let config = ListenerConfig::default();
+        let timed = config.shared.subscribers.subscribe_timed();
+        replay(CAPTURE, vec![parse_hotkey("F8").unwrap()], config);
+
+        let timestamps: Vec<_> = std::iter::from_fn(|| timed.try_recv().ok())
+            .map(|timed| timed.timestamp)
+            .collect();
+        assert_eq!(
+            timestamps,
+            vec![
+                UNIX_EPOCH + Duration::from_micros(1),
+                UNIX_EPOCH + Duration::from_micros(80_012),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replay_processes_each_report_as_a_batch() {
+        // The keyboard reported Shift after F8 within the same report
+        const LATE_SHIFT: &str = "\
+E: 0.100000 0001 0042 0001	# EV_KEY / KEY_F8                 1
+E: 0.100002 0001 002a 0001	# EV_KEY / KEY_LEFTSHIFT          1
+E: 0.100002 0000 0000 0000	# ------------ SYN_REPORT (0) ---------- +0ms
+";
+        let config = ListenerConfig {
+            modifier_lookahead: Some(Duration::from_millis(5)),
+            ..ListenerConfig::default()
+        };
+        let events = replay(LATE_SHIFT, vec![parse_hotkey("Shift+F8").unwrap()], config);
+        assert_eq!(events, vec![HotkeyEvent::Pressed(0)]);
+    }
+}
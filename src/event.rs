@@ -1,12 +1,89 @@
 //! Events emitted by the hotkey listener.
 
+use std::time::SystemTime;
+
 /// Events emitted when a registered hotkey is pressed or released.
+///
+/// New kinds of events may be added in minor releases, so matches on it need
+/// a wildcard arm.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum HotkeyEvent {
     /// A hotkey was pressed. The index corresponds to the order in which
-    /// hotkeys were added to the listener builder.
+    /// hotkeys were added to the listener builder, or is the id given to
+    /// `add_hotkey_with_id`.
     Pressed(usize),
     /// A hotkey was released. The index corresponds to the order in which
-    /// hotkeys were added to the listener builder.
+    /// hotkeys were added to the listener builder, or is the id given to
+    /// `add_hotkey_with_id`.
     Released(usize),
+    /// A held hotkey's key auto-repeated. Only emitted when enabled with
+    /// `HotkeyListenerBuilder::emit_repeats`, between the hotkey's `Pressed`
+    /// and `Released`.
+    Repeat(usize),
+    /// A hotkey was pressed twice within its double-tap window. Only emitted
+    /// for hotkeys configured with `HotkeyListenerBuilder::double_tap`, right
+    /// after the second press's `Pressed`.
+    DoubleTapped(usize),
+    /// A hotkey has been held down for its long-press threshold. Only
+    /// emitted for hotkeys configured with
+    /// `HotkeyListenerBuilder::long_press`, once per press, between its
+    /// `Pressed` and `Released`.
+    LongPressed(usize),
+    /// The last step of a chord was pressed. The index is the chord's
+    /// position among those added with `HotkeyListenerBuilder::add_chord`,
+    /// numbered separately from hotkeys.
+    ChordCompleted(usize),
+    /// The number of keyboards being read changed: they reconnected after
+    /// an error, one was plugged in, or reading them failed, reported as 0
+    /// connected until they are found again. Only emitted when enabled with
+    /// `HotkeyListenerBuilder::emit_device_changes`, by the Linux evdev
+    /// backend.
+    DevicesChanged { connected: usize },
+    /// A hotkey was pressed and released. Emitted on the release in place
+    /// of `Pressed` and `Released` when enabled with
+    /// `HotkeyListenerBuilder::tap_mode`, except for presses held until
+    /// their `LongPressed`.
+    Tapped(usize),
+}
+
+/// A [`HotkeyEvent`] with the time its key transition happened.
+///
+/// Received from
+/// [`HotkeyListenerHandle::subscribe_timed`](crate::HotkeyListenerHandle::subscribe_timed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedHotkeyEvent {
+    pub event: HotkeyEvent,
+    /// When the key was pressed or released. On Linux this is the kernel's
+    /// timestamp for the input event and on macOS the time rdev reports.
+    /// Injected keys, stuck-key releases and replayed captures without
+    /// timestamps carry the time the listener handled them.
+    pub timestamp: SystemTime,
+    /// The character the hotkey's key types with the Shift state held when
+    /// the event happened, assuming a US layout, so `A` gives 'A' with
+    /// Shift held and 'a' without. A display hint only, see
+    /// [`Key::to_char`](crate::Key::to_char). `None` for keys without a
+    /// textual representation such as function keys, for modifier-only
+    /// hotkeys, chords and device changes, and on the X11 backend.
+    pub char_hint: Option<char>,
+}
+
+/// A [`HotkeyEvent`] with the keyboard it came from.
+///
+/// Received from
+/// [`HotkeyListenerHandle::subscribe_with_device`](crate::HotkeyListenerHandle::subscribe_with_device).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceHotkeyEvent {
+    pub event: HotkeyEvent,
+    /// The physical path of the keyboard that produced the event, as in
+    /// [`KeyboardInfo::physical_path`](crate::KeyboardInfo::physical_path).
+    /// `None` unless enabled with
+    /// `HotkeyListenerBuilder::report_devices`, for keyboards that report no
+    /// physical path, for events not caused by a single keyboard (injected
+    /// keys, stuck-key releases, long presses), and on backends other than
+    /// Linux evdev.
+    pub device: Option<String>,
+    /// The character the hotkey's key typed, as in
+    /// [`TimedHotkeyEvent::char_hint`].
+    pub char_hint: Option<char>,
 }
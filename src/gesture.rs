@@ -0,0 +1,317 @@
+//! Recognition of taps, double-taps and long presses from hotkey events.
+//!
+//! [`GestureListener`] wraps a [`HotkeyListenerHandle`] for apps that care
+//! about what the user did with a hotkey rather than when it went down and
+//! up. The recognition itself lives in [`GestureRecognizer`], which can be
+//! fed events from any source.
+
+use crate::event::HotkeyEvent;
+use crate::listener::HotkeyListenerHandle;
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+
+/// A gesture performed with a hotkey, carrying the hotkey's index or id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    /// Pressed and released once, with no second press following.
+    Tap(usize),
+    /// Pressed and released twice in quick succession.
+    DoubleTap(usize),
+    /// Held for at least the long-press threshold. Reported while the hotkey
+    /// is still held.
+    LongPress(usize),
+}
+
+/// Timing thresholds for gesture recognition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GestureConfig {
+    /// Holding a hotkey at least this long is a long press.
+    pub long_press: Duration,
+    /// A second press within this long of a release makes a double-tap. A tap
+    /// is only reported once this has passed without a second press.
+    pub double_tap_window: Duration,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            long_press: Duration::from_millis(500),
+            double_tap_window: Duration::from_millis(300),
+        }
+    }
+}
+
+/// Per-hotkey recognition state.
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Down {
+        since: Instant,
+        /// Whether this press follows a tap, making it a double-tap candidate.
+        after_tap: bool,
+        long_press_sent: bool,
+    },
+    TapPending {
+        released_at: Instant,
+    },
+}
+
+/// Turns `Pressed`/`Released` events into [`Gesture`]s.
+///
+/// Feed events with [`on_event`](Self::on_event) and call
+/// [`poll`](Self::poll) regularly, at the latest by
+/// [`next_deadline`](Self::next_deadline), since taps and long presses are
+/// only recognized once time has passed.
+#[derive(Debug, Default)]
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    states: HashMap<usize, State>,
+}
+
+impl GestureRecognizer {
+    /// Create a recognizer with the given thresholds.
+    pub fn new(config: GestureConfig) -> Self {
+        Self {
+            config,
+            states: HashMap::new(),
+        }
+    }
+
+    /// Process an event received at `at`, returning any completed gestures.
+    pub fn on_event(&mut self, event: HotkeyEvent, at: Instant) -> Vec<Gesture> {
+        let mut gestures = self.poll(at);
+        match event {
+            HotkeyEvent::Pressed(idx) => {
+                let after_tap = matches!(self.states.get(&idx), Some(State::TapPending { .. }));
+                self.states.insert(
+                    idx,
+                    State::Down {
+                        since: at,
+                        after_tap,
+                        long_press_sent: false,
+                    },
+                );
+            }
+            HotkeyEvent::Released(idx) => match self.states.remove(&idx) {
+                Some(State::Down {
+                    long_press_sent: true,
+                    ..
+                }) => {}
+                Some(State::Down {
+                    after_tap: true, ..
+                }) => gestures.push(Gesture::DoubleTap(idx)),
+                Some(State::Down { .. }) => {
+                    self.states
+                        .insert(idx, State::TapPending { released_at: at });
+                }
+                // A release without a press, e.g. one that was pending
+                // when the recognizer was created
+                Some(State::TapPending { .. }) | None => {}
+            },
+            HotkeyEvent::Repeat(_)
+            | HotkeyEvent::DoubleTapped(_)
+            | HotkeyEvent::LongPressed(_)
+            | HotkeyEvent::ChordCompleted(_)
+            | HotkeyEvent::DevicesChanged { .. }
+            | HotkeyEvent::Tapped(_) => {}
+        }
+        gestures
+    }
+
+    /// Report the gestures completed by the passage of time up to `now`.
+    pub fn poll(&mut self, now: Instant) -> Vec<Gesture> {
+        let config = self.config;
+        let mut gestures = Vec::new();
+        let mut idxs: Vec<usize> = self.states.keys().copied().collect();
+        idxs.sort_unstable();
+        for idx in idxs {
+            match self.states[&idx] {
+                State::Down {
+                    since,
+                    after_tap,
+                    long_press_sent: false,
+                } if now.saturating_duration_since(since) >= config.long_press => {
+                    // The tap before a long press stands on its own
+                    if after_tap {
+                        gestures.push(Gesture::Tap(idx));
+                    }
+                    gestures.push(Gesture::LongPress(idx));
+                    self.states.insert(
+                        idx,
+                        State::Down {
+                            since,
+                            after_tap: false,
+                            long_press_sent: true,
+                        },
+                    );
+                }
+                State::TapPending { released_at }
+                    if now.saturating_duration_since(released_at) >= config.double_tap_window =>
+                {
+                    gestures.push(Gesture::Tap(idx));
+                    self.states.remove(&idx);
+                }
+                _ => {}
+            }
+        }
+        gestures
+    }
+
+    /// The earliest time at which [`poll`](Self::poll) may report a gesture.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.states
+            .values()
+            .filter_map(|state| match *state {
+                State::Down {
+                    since,
+                    long_press_sent: false,
+                    ..
+                } => Some(since + self.config.long_press),
+                State::TapPending { released_at } => {
+                    Some(released_at + self.config.double_tap_window)
+                }
+                State::Down { .. } => None,
+            })
+            .min()
+    }
+}
+
+/// A [`HotkeyListenerHandle`] that reports gestures instead of raw events.
+pub struct GestureListener {
+    handle: HotkeyListenerHandle,
+    recognizer: GestureRecognizer,
+    pending: VecDeque<Gesture>,
+}
+
+impl GestureListener {
+    /// Wrap `handle`, recognizing gestures with the given thresholds.
+    pub fn new(handle: HotkeyListenerHandle, config: GestureConfig) -> Self {
+        Self {
+            handle,
+            recognizer: GestureRecognizer::new(config),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Wait up to `timeout` for the next gesture.
+    ///
+    /// Returns `None` on timeout, or once the listener has stopped and no
+    /// gestures are left.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Gesture> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(gesture) = self.pending.pop_front() {
+                return Some(gesture);
+            }
+            let now = Instant::now();
+            self.pending.extend(self.recognizer.poll(now));
+            if !self.pending.is_empty() {
+                continue;
+            }
+            if now >= deadline {
+                return None;
+            }
+            let wake = self
+                .recognizer
+                .next_deadline()
+                .map_or(deadline, |next| next.min(deadline));
+            match self
+                .handle
+                .recv_timeout(wake.saturating_duration_since(now))
+            {
+                Ok(event) => {
+                    let gestures = self.recognizer.on_event(event, Instant::now());
+                    self.pending.extend(gestures);
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.pending.extend(self.recognizer.poll(Instant::now()));
+                    return self.pending.pop_front();
+                }
+            }
+        }
+    }
+
+    /// The wrapped handle.
+    pub fn handle(&self) -> &HotkeyListenerHandle {
+        &self.handle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_tap_is_reported_after_double_tap_window() {
+        let mut recognizer = GestureRecognizer::default();
+        let start = Instant::now();
+
+        assert!(recognizer
+            .on_event(HotkeyEvent::Pressed(0), start)
+            .is_empty());
+        assert!(recognizer
+            .on_event(HotkeyEvent::Released(0), start + ms(50))
+            .is_empty());
+        assert!(recognizer.poll(start + ms(200)).is_empty());
+        assert_eq!(recognizer.next_deadline(), Some(start + ms(350)));
+        assert_eq!(recognizer.poll(start + ms(350)), vec![Gesture::Tap(0)]);
+        assert!(recognizer.poll(start + ms(1000)).is_empty());
+    }
+
+    #[test]
+    fn test_double_tap() {
+        let mut recognizer = GestureRecognizer::default();
+        let start = Instant::now();
+
+        recognizer.on_event(HotkeyEvent::Pressed(1), start);
+        recognizer.on_event(HotkeyEvent::Released(1), start + ms(50));
+        assert!(recognizer
+            .on_event(HotkeyEvent::Pressed(1), start + ms(150))
+            .is_empty());
+        assert_eq!(
+            recognizer.on_event(HotkeyEvent::Released(1), start + ms(200)),
+            vec![Gesture::DoubleTap(1)]
+        );
+        assert!(recognizer.poll(start + ms(1000)).is_empty());
+    }
+
+    #[test]
+    fn test_long_press_is_reported_while_held() {
+        let mut recognizer = GestureRecognizer::new(GestureConfig {
+            long_press: ms(400),
+            ..GestureConfig::default()
+        });
+        let start = Instant::now();
+
+        recognizer.on_event(HotkeyEvent::Pressed(0), start);
+        assert!(recognizer.poll(start + ms(300)).is_empty());
+        assert_eq!(
+            recognizer.poll(start + ms(400)),
+            vec![Gesture::LongPress(0)]
+        );
+        assert!(recognizer
+            .on_event(HotkeyEvent::Released(0), start + ms(900))
+            .is_empty());
+        assert!(recognizer.poll(start + ms(2000)).is_empty());
+    }
+
+    #[test]
+    fn test_slow_second_press_is_two_taps() {
+        let mut recognizer = GestureRecognizer::default();
+        let start = Instant::now();
+
+        recognizer.on_event(HotkeyEvent::Pressed(0), start);
+        recognizer.on_event(HotkeyEvent::Released(0), start + ms(50));
+        assert_eq!(
+            recognizer.on_event(HotkeyEvent::Pressed(0), start + ms(600)),
+            vec![Gesture::Tap(0)]
+        );
+        recognizer.on_event(HotkeyEvent::Released(0), start + ms(650));
+        assert_eq!(recognizer.poll(start + ms(950)), vec![Gesture::Tap(0)]);
+    }
+}
@@ -1,119 +1,656 @@
 //! Hotkey definition with optional modifiers.
 
+use crate::error::HotkeyError;
 use crate::key::Key;
 use anyhow::{anyhow, Result};
+use std::time::Duration;
 
 /// Modifier keys that can be combined with a hotkey.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+///
+/// By default either side of a modifier satisfies it. Setting a `*_side`
+/// field alongside its flag requires that side, e.g. `RAlt` for AltGr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct Modifiers {
     pub shift: bool,
     pub ctrl: bool,
     pub alt: bool,
+    /// Super on Linux, the Windows key, or Command on macOS.
+    pub meta: bool,
+    /// The side of Shift required, or `None` for either.
+    pub shift_side: Option<ModifierSide>,
+    /// The side of Ctrl required, or `None` for either.
+    pub ctrl_side: Option<ModifierSide>,
+    /// The side of Alt required, or `None` for either.
+    pub alt_side: Option<ModifierSide>,
+    /// The side of Super required, or `None` for either.
+    pub meta_side: Option<ModifierSide>,
 }
 
-/// A hotkey consisting of a key and optional modifiers.
+/// Which of a pair of modifier keys, e.g. left or right Alt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ModifierSide {
+    Left,
+    Right,
+}
+
+impl Modifiers {
+    /// The same modifiers with any side requirements dropped.
+    pub(crate) fn any_side(self) -> Self {
+        Self {
+            shift: self.shift,
+            ctrl: self.ctrl,
+            alt: self.alt,
+            meta: self.meta,
+            ..Self::default()
+        }
+    }
+
+    /// Whether every modifier in `self` is also in `other`, whatever side.
+    pub(crate) fn is_subset_of(self, other: Self) -> bool {
+        (!self.shift || other.shift)
+            && (!self.ctrl || other.ctrl)
+            && (!self.alt || other.alt)
+            && (!self.meta || other.meta)
+    }
+
+    /// The modifiers as display names, in display order, with sides like `LAlt`.
+    fn names(&self) -> Vec<String> {
+        [
+            (self.ctrl, self.ctrl_side, "Ctrl"),
+            (self.alt, self.alt_side, "Alt"),
+            (self.shift, self.shift_side, "Shift"),
+            (self.meta, self.meta_side, "Super"),
+        ]
+        .into_iter()
+        .filter(|(held, _, _)| *held)
+        .map(|(_, side, name)| match side {
+            Some(ModifierSide::Left) => format!("L{}", name),
+            Some(ModifierSide::Right) => format!("R{}", name),
+            None => name.to_string(),
+        })
+        .collect()
+    }
+}
+
+/// Tap pattern that triggers a modifier-only hotkey.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ModifierTaps {
+    /// Number of consecutive taps required.
+    pub count: u32,
+    /// Maximum time from the first to the last tap.
+    pub window: Duration,
+}
+
+/// Repeated presses of the same key that trigger a sequence hotkey.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeySequence {
+    /// Number of presses in a row required.
+    pub count: u32,
+    /// Maximum time from the first to the last press.
+    pub window: Duration,
+}
+
+/// Hotkeys pressed one after another that trigger together, e.g. `Ctrl+F1`
+/// then `F2`.
+///
+/// Registered with
+/// [`HotkeyListenerBuilder::add_chord`](crate::HotkeyListenerBuilder::add_chord),
+/// which emits [`HotkeyEvent::ChordCompleted`](crate::HotkeyEvent::ChordCompleted)
+/// once every step has been pressed in order.
 #[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chord {
+    /// The hotkeys to press, in order. Only steps with a main key match;
+    /// modifier-only and sequence hotkeys never do.
+    pub steps: Vec<Hotkey>,
+    /// Maximum time between two consecutive steps.
+    pub timeout: Duration,
+}
+
+impl Chord {
+    /// Create a chord of `steps`, each pressed within `timeout` of the previous one.
+    pub fn new(steps: impl IntoIterator<Item = Hotkey>, timeout: Duration) -> Self {
+        Self {
+            steps: steps.into_iter().collect(),
+            timeout,
+        }
+    }
+}
+
+impl std::fmt::Display for Chord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let steps: Vec<String> = self.steps.iter().map(ToString::to_string).collect();
+        write!(f, "{}", steps.join(" then "))
+    }
+}
+
+/// How to render a hotkey for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayStyle {
+    /// Modifier names joined with `+`, e.g. `Ctrl+Shift+F8`. Same as [`Display`](std::fmt::Display).
+    Text,
+    /// macOS modifier glyphs in the order the menus use, e.g. `⌃⇧F8`.
+    Symbols,
+}
+
+/// A hotkey consisting of a key and optional modifiers.
+///
+/// Hotkeys can be map keys, and sort by key first, then by modifiers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Hotkey {
-    pub key: Key,
+    /// The main key, or `None` for modifier-only hotkeys.
+    pub key: Option<Key>,
     pub modifiers: Modifiers,
+    /// Set for modifier-only hotkeys, which trigger on tapping `modifiers` alone.
+    pub modifier_taps: Option<ModifierTaps>,
+    /// Set for sequence hotkeys, which trigger on pressing `key` repeatedly.
+    pub sequence: Option<KeySequence>,
+    /// Only match while NumLock is on (`Some(true)`) or off (`Some(false)`).
+    pub num_lock: Option<bool>,
+    /// Only match while CapsLock is on (`Some(true)`) or off (`Some(false)`).
+    pub caps_lock: Option<bool>,
 }
 
 impl Hotkey {
     /// Create a new hotkey with no modifiers.
     pub fn new(key: Key) -> Self {
         Self {
-            key,
+            key: Some(key),
             modifiers: Modifiers::default(),
+            modifier_taps: None,
+            sequence: None,
+            num_lock: None,
+            caps_lock: None,
         }
     }
 
     /// Create a new hotkey with the given modifiers.
     pub fn with_modifiers(key: Key, modifiers: Modifiers) -> Self {
-        Self { key, modifiers }
+        Self {
+            key: Some(key),
+            modifiers,
+            modifier_taps: None,
+            sequence: None,
+            num_lock: None,
+            caps_lock: None,
+        }
+    }
+
+    /// Create a hotkey triggered by tapping modifiers on their own.
+    ///
+    /// Fires when the modifier combination is pressed and released `count`
+    /// times within `window`, with no other key pressed in between, e.g.
+    /// double-tapping Shift:
+    ///
+    /// ```
+    /// use hotkey_listener::{Hotkey, Modifiers};
+    /// use std::time::Duration;
+    ///
+    /// let shift = Modifiers { shift: true, ..Modifiers::default() };
+    /// let hotkey = Hotkey::modifier_only(shift, 2, Duration::from_millis(400));
+    /// ```
+    ///
+    /// `Pressed` is emitted on the final tap and `Released` when it is released.
+    pub fn modifier_only(modifiers: Modifiers, count: u32, window: Duration) -> Self {
+        Self {
+            key: None,
+            modifiers,
+            modifier_taps: Some(ModifierTaps { count, window }),
+            sequence: None,
+            num_lock: None,
+            caps_lock: None,
+        }
+    }
+
+    /// Create a hotkey triggered by pressing `key` `count` times in a row within `window`.
+    ///
+    /// Useful as a deliberate safety gesture, e.g. triple-tapping F12 to quit.
+    /// `Pressed` is emitted once on the final press and `Released` when that
+    /// press is released. Pressing any other key restarts the count.
+    pub fn sequence(key: Key, count: u32, window: Duration) -> Self {
+        Self {
+            sequence: Some(KeySequence { count, window }),
+            ..Self::new(key)
+        }
+    }
+
+    /// Only match this hotkey while NumLock is `on`, or while it's off.
+    ///
+    /// Lets the same numpad key be bound twice, e.g. `Numpad1` with NumLock
+    /// on and off meaning different things. NumLock is read from the
+    /// keyboard LEDs, so this is Linux only; until the state is known, e.g.
+    /// on a keyboard without a NumLock LED, hotkeys with a condition don't
+    /// match.
+    pub fn when_num_lock(self, on: bool) -> Self {
+        Self {
+            num_lock: Some(on),
+            ..self
+        }
+    }
+
+    /// Only match this hotkey while CapsLock is `on`, or while it's off.
+    ///
+    /// Like [`when_num_lock`](Self::when_num_lock), the state is read from
+    /// the keyboard LEDs when the listener starts and tracked from then on,
+    /// so this is Linux only and a hotkey with a condition doesn't match
+    /// until the state is known.
+    pub fn when_caps_lock(self, on: bool) -> Self {
+        Self {
+            caps_lock: Some(on),
+            ..self
+        }
+    }
+
+    /// Whether this hotkey triggers on modifier taps alone.
+    pub fn is_modifier_only(&self) -> bool {
+        self.modifier_taps.is_some()
+    }
+
+    /// Render this hotkey in the given style.
+    pub fn display_with(&self, style: DisplayStyle) -> String {
+        match style {
+            DisplayStyle::Text => self.to_string(),
+            DisplayStyle::Symbols => {
+                let mut out = String::new();
+                if self.modifiers.ctrl {
+                    out.push('⌃');
+                }
+                if self.modifiers.alt {
+                    out.push('⌥');
+                }
+                if self.modifiers.shift {
+                    out.push('⇧');
+                }
+                if self.modifiers.meta {
+                    out.push('⌘');
+                }
+                if let Some(taps) = self.modifier_taps {
+                    return format!("{} x{}", out, taps.count);
+                }
+                if let Some(key) = self.key {
+                    out.push_str(&key.to_string());
+                }
+                if let Some(sequence) = self.sequence {
+                    out.push_str(&format!(" x{}", sequence.count));
+                }
+                out
+            }
+        }
+    }
+
+    /// Render this hotkey the way the current platform shows shortcuts.
+    ///
+    /// Uses [`DisplayStyle::Symbols`] on macOS and [`DisplayStyle::Text`]
+    /// elsewhere. The [`Display`](std::fmt::Display) impl stays the portable
+    /// form accepted by [`parse_hotkey`].
+    pub fn display_for_platform(&self) -> String {
+        if cfg!(target_os = "macos") {
+            self.display_with(DisplayStyle::Symbols)
+        } else {
+            self.display_with(DisplayStyle::Text)
+        }
+    }
+
+    /// The canonical string form of this hotkey, e.g. `"Ctrl+Shift+F8"`.
+    ///
+    /// Same as the [`Display`](std::fmt::Display) impl: modifiers always come
+    /// in the order Ctrl, Alt, Shift, Super, so hotkeys parsed from strings
+    /// that list the same modifiers in a different order compare equal here.
+    pub fn canonical_string(&self) -> String {
+        self.to_string()
     }
 
     /// Return a copy of this hotkey with the shift modifier added.
     pub fn with_shift(&self) -> Self {
+        self.replace_modifiers(Modifiers {
+            shift: true,
+            ..self.modifiers
+        })
+    }
+
+    /// Return a copy of this hotkey with the ctrl modifier added.
+    pub fn with_ctrl(&self) -> Self {
+        self.replace_modifiers(Modifiers {
+            ctrl: true,
+            ..self.modifiers
+        })
+    }
+
+    /// Return a copy of this hotkey with the alt modifier added.
+    pub fn with_alt(&self) -> Self {
+        self.replace_modifiers(Modifiers {
+            alt: true,
+            ..self.modifiers
+        })
+    }
+
+    /// Return a copy of this hotkey with the meta (Super) modifier added.
+    pub fn with_meta(&self) -> Self {
+        self.replace_modifiers(Modifiers {
+            meta: true,
+            ..self.modifiers
+        })
+    }
+
+    /// Return a copy of this hotkey with no modifiers.
+    pub fn without_modifiers(&self) -> Self {
+        self.replace_modifiers(Modifiers::default())
+    }
+
+    /// A copy of this hotkey with `modifiers` in place of its own.
+    fn replace_modifiers(&self, modifiers: Modifiers) -> Self {
         Self {
-            key: self.key,
-            modifiers: Modifiers {
-                shift: true,
-                ctrl: self.modifiers.ctrl,
-                alt: self.modifiers.alt,
-            },
+            modifiers,
+            ..self.clone()
         }
     }
 }
 
+/// Formats the canonical string form, modifiers ordered Ctrl, Alt, Shift,
+/// Super whatever order they were parsed in.
 impl std::fmt::Display for Hotkey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut parts = Vec::new();
-        if self.modifiers.ctrl {
-            parts.push("Ctrl".to_string());
+        let mut parts = self.modifiers.names();
+        if let Some(taps) = self.modifier_taps {
+            return write!(f, "{} x{}", parts.join("+"), taps.count);
         }
-        if self.modifiers.alt {
-            parts.push("Alt".to_string());
+        parts.extend(self.key.map(|key| key.to_string()));
+        if let Some(sequence) = self.sequence {
+            return write!(f, "{} x{}", parts.join("+"), sequence.count);
         }
-        if self.modifiers.shift {
-            parts.push("Shift".to_string());
+        write!(f, "{}", parts.join("+"))?;
+        for (name, state) in [("NumLock", self.num_lock), ("CapsLock", self.caps_lock)] {
+            match state {
+                Some(true) => write!(f, " ({} on)", name)?,
+                Some(false) => write!(f, " ({} off)", name)?,
+                None => {}
+            }
         }
-        parts.push(self.key.to_string());
-        write!(f, "{}", parts.join("+"))
+        Ok(())
+    }
+}
+
+/// Names of the modifiers the platform backend tracks, in display order.
+///
+/// Shift, Ctrl, Alt and Super can be part of a hotkey on every platform,
+/// either side or a specific one (e.g. `LAlt`). Both Alt keys, including
+/// AltGr and the right Option key, count as Alt, and Command is Super on
+/// macOS. Empty on unsupported platforms.
+pub fn supported_modifiers() -> Vec<&'static str> {
+    if cfg!(any(target_os = "linux", target_os = "macos")) {
+        vec!["Shift", "Ctrl", "Alt", "Super"]
+    } else {
+        Vec::new()
     }
 }
 
 /// Parse a hotkey string like "Shift+F8" or "F10" into a Hotkey.
+///
+/// Modifiers may come in any order but each only once, so "Shift+Shift+F8"
+/// and "Shift+LShift+F8" are rejected. NumLock and CapsLock conditions
+/// follow in parentheses the way [`Display`](std::fmt::Display) writes
+/// them, e.g. "Numpad1 (NumLock off)" or "Shift+F8 (CapsLock on)". Equivalent to `s.parse::<Hotkey>()`.
 pub fn parse_hotkey(s: &str) -> Result<Hotkey> {
-    let parts: Vec<&str> = s.split('+').collect();
-    let mut modifiers = Modifiers::default();
+    s.parse()
+}
+
+impl std::str::FromStr for Hotkey {
+    type Err = anyhow::Error;
+
+    /// Parse a hotkey string like "Shift+F8" or "F10".
+    fn from_str(s: &str) -> Result<Self> {
+        let (s, num_lock, caps_lock) = split_lock_conditions(s)?;
+        let parts: Vec<&str> = s.split('+').collect();
+        let mut modifiers = Modifiers::default();
+
+        if parts.is_empty() {
+            return Err(anyhow!("Empty hotkey string"));
+        }
+
+        // Parse modifiers (all parts except the last one)
+        for part in &parts[..parts.len() - 1] {
+            let upper = part.to_uppercase();
+            // "LAlt"/"RAlt" style tokens require a side; AltGr is right Alt
+            let (name, side) = match upper.as_str() {
+                "ALTGR" => ("ALT", Some(ModifierSide::Right)),
+                name => match (name.strip_prefix('L'), name.strip_prefix('R')) {
+                    (Some(rest), _) if is_modifier_name(rest) => (rest, Some(ModifierSide::Left)),
+                    (_, Some(rest)) if is_modifier_name(rest) => (rest, Some(ModifierSide::Right)),
+                    _ => (name, None),
+                },
+            };
+            let (held, held_side) = match name {
+                "SHIFT" => (&mut modifiers.shift, &mut modifiers.shift_side),
+                "CTRL" | "CONTROL" => (&mut modifiers.ctrl, &mut modifiers.ctrl_side),
+                "ALT" => (&mut modifiers.alt, &mut modifiers.alt_side),
+                "SUPER" | "META" | "WIN" | "CMD" => (&mut modifiers.meta, &mut modifiers.meta_side),
+                _ => return Err(anyhow!("Unknown modifier: {}", part)),
+            };
+            if *held {
+                return Err(anyhow!("Duplicate modifier: {}", part));
+            }
+            (*held, *held_side) = (true, side);
+        }
+
+        // Parse the key (last part)
+        let key_str = parts[parts.len() - 1];
+        let key = Key::parse(key_str)?;
+
+        Ok(Hotkey {
+            num_lock,
+            caps_lock,
+            ..Hotkey::with_modifiers(key, modifiers)
+        })
+    }
+}
 
-    if parts.is_empty() {
-        return Err(anyhow!("Empty hotkey string"));
+/// Split the lock conditions `Display` appends, like " (NumLock on)", off
+/// the end of `s`, returning the rest and the NumLock and CapsLock states
+/// required.
+fn split_lock_conditions(s: &str) -> Result<(&str, Option<bool>, Option<bool>)> {
+    let mut rest = s;
+    let mut num_lock = None;
+    let mut caps_lock = None;
+    while let Some(inner) = rest.strip_suffix(')') {
+        let (head, condition) = inner
+            .rsplit_once(" (")
+            .ok_or_else(|| anyhow!("Unmatched ')' in hotkey: {}", s))?;
+        let (name, state) = condition
+            .split_once(' ')
+            .ok_or_else(|| anyhow!("Invalid lock condition: {}", condition))?;
+        let on = match state.to_uppercase().as_str() {
+            "ON" => true,
+            "OFF" => false,
+            _ => return Err(anyhow!("Invalid lock condition: {}", condition)),
+        };
+        let slot = match name.to_uppercase().as_str() {
+            "NUMLOCK" => &mut num_lock,
+            "CAPSLOCK" => &mut caps_lock,
+            _ => return Err(anyhow!("Unknown lock in condition: {}", condition)),
+        };
+        if slot.is_some() {
+            return Err(anyhow!("Duplicate lock condition: {}", condition));
+        }
+        *slot = Some(on);
+        rest = head;
     }
+    Ok((rest, num_lock, caps_lock))
+}
 
-    // Parse modifiers (all parts except the last one)
-    for part in &parts[..parts.len() - 1] {
-        match part.to_uppercase().as_str() {
-            "SHIFT" => modifiers.shift = true,
-            "CTRL" | "CONTROL" => modifiers.ctrl = true,
-            "ALT" => modifiers.alt = true,
-            _ => return Err(anyhow!("Unknown modifier: {}", part)),
+/// Serializes to the string form, e.g. `"Shift+F8"`.
+///
+/// Modifier-only and sequence hotkeys have no string form [`parse_hotkey`]
+/// accepts, so serializing them fails.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Hotkey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_modifier_only() || self.sequence.is_some() {
+            return Err(serde::ser::Error::custom(format!(
+                "hotkey {} has no string form",
+                self
+            )));
         }
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserializes from the string form with [`parse_hotkey`].
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Hotkey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        parse_hotkey(&s).map_err(serde::de::Error::custom)
     }
+}
 
-    // Parse the key (last part)
-    let key_str = parts[parts.len() - 1];
-    let key = Key::parse(key_str)?;
+/// Whether `name`, already uppercased, is a modifier `parse_hotkey` accepts.
+fn is_modifier_name(name: &str) -> bool {
+    matches!(
+        name,
+        "SHIFT" | "CTRL" | "CONTROL" | "ALT" | "SUPER" | "META" | "WIN" | "CMD"
+    )
+}
 
-    Ok(Hotkey { key, modifiers })
+/// Check whether `s` is a valid hotkey, e.g. while the user types one.
+///
+/// Accepts exactly the strings [`parse_hotkey`] accepts.
+pub fn is_valid_hotkey(s: &str) -> bool {
+    validate_hotkey(s).is_ok()
+}
+
+/// Check that `s` is a valid hotkey, returning why it isn't otherwise.
+///
+/// Accepts exactly the strings [`parse_hotkey`] accepts, and the error
+/// carries the same message.
+pub fn validate_hotkey(s: &str) -> std::result::Result<(), HotkeyError> {
+    parse_hotkey(s)
+        .map(drop)
+        .map_err(|e| HotkeyError::InvalidHotkey(e.to_string()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_validate_hotkey() {
+        assert!(is_valid_hotkey("F8"));
+        assert!(is_valid_hotkey("ctrl+shift+insert"));
+        assert_eq!(validate_hotkey("Alt+F1"), Ok(()));
+
+        // Empty, missing key, unknown key and unknown modifier
+        for invalid in ["", "Shift+", "F13", "Hyper+F8"] {
+            assert!(!is_valid_hotkey(invalid), "{:?}", invalid);
+            let reason = parse_hotkey(invalid).unwrap_err().to_string();
+            assert_eq!(
+                validate_hotkey(invalid),
+                Err(HotkeyError::InvalidHotkey(reason))
+            );
+        }
+    }
+
+    #[test]
+    fn test_supported_modifiers() {
+        let modifiers = supported_modifiers();
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        assert_eq!(modifiers, vec!["Shift", "Ctrl", "Alt", "Super"]);
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        assert!(modifiers.is_empty());
+    }
+
     #[test]
     fn test_parse_simple_key() {
         let hotkey = parse_hotkey("F8").unwrap();
-        assert_eq!(hotkey.key, Key::F8);
+        assert_eq!(hotkey.key, Some(Key::F8));
         assert!(!hotkey.modifiers.shift);
         assert!(!hotkey.modifiers.ctrl);
         assert!(!hotkey.modifiers.alt);
     }
 
+    #[test]
+    fn test_modifier_helpers_keep_other_modifiers() {
+        let hotkey = parse_hotkey("LShift+F8").unwrap().when_caps_lock(false);
+        assert_eq!(
+            hotkey.with_ctrl().to_string(),
+            "Ctrl+LShift+F8 (CapsLock off)"
+        );
+        assert_eq!(
+            hotkey.with_alt().to_string(),
+            "Alt+LShift+F8 (CapsLock off)"
+        );
+        assert_eq!(
+            hotkey.with_meta().to_string(),
+            "LShift+Super+F8 (CapsLock off)"
+        );
+        assert_eq!(hotkey.with_shift(), hotkey);
+        assert_eq!(
+            hotkey.with_ctrl().with_alt().with_meta(),
+            parse_hotkey("Ctrl+Alt+LShift+Super+F8")
+                .unwrap()
+                .when_caps_lock(false)
+        );
+        assert_eq!(
+            hotkey.with_ctrl().without_modifiers(),
+            Hotkey::new(Key::F8).when_caps_lock(false)
+        );
+    }
+
+    #[test]
+    fn test_hotkeys_work_as_map_keys() {
+        use std::collections::{BTreeSet, HashSet};
+
+        let hotkeys: Vec<Hotkey> = ["Shift+F8", "F8", "Ctrl+F1", "F8", "RAlt+F1"]
+            .iter()
+            .map(|s| parse_hotkey(s).unwrap())
+            .collect();
+        let hashed: HashSet<Hotkey> = hotkeys.iter().cloned().collect();
+        assert_eq!(hashed.len(), 4);
+        assert!(hashed.contains(&parse_hotkey("shift+f8").unwrap()));
+
+        let sorted: Vec<String> = hotkeys
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+            .iter()
+            .map(Hotkey::to_string)
+            .collect();
+        assert_eq!(sorted, ["RAlt+F1", "Ctrl+F1", "F8", "Shift+F8"]);
+    }
+
     #[test]
     fn test_parse_with_shift() {
         let hotkey = parse_hotkey("Shift+F8").unwrap();
-        assert_eq!(hotkey.key, Key::F8);
+        assert_eq!(hotkey.key, Some(Key::F8));
         assert!(hotkey.modifiers.shift);
         assert!(!hotkey.modifiers.ctrl);
         assert!(!hotkey.modifiers.alt);
     }
 
+    #[test]
+    fn test_modifier_only_hotkey_has_no_key() {
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::default()
+        };
+        let hotkey = Hotkey::modifier_only(shift, 2, Duration::from_millis(400));
+        assert_eq!(hotkey.key, None);
+        assert_eq!(hotkey.to_string(), "Shift x2");
+        let f1 = Hotkey {
+            modifier_taps: hotkey.modifier_taps,
+            ..Hotkey::with_modifiers(Key::F1, shift)
+        };
+        assert_ne!(hotkey, f1);
+        assert_ne!(hotkey.cmp(&f1), std::cmp::Ordering::Equal);
+    }
+
     #[test]
     fn test_parse_with_multiple_modifiers() {
         let hotkey = parse_hotkey("Ctrl+Alt+F1").unwrap();
-        assert_eq!(hotkey.key, Key::F1);
+        assert_eq!(hotkey.key, Some(Key::F1));
         assert!(!hotkey.modifiers.shift);
         assert!(hotkey.modifiers.ctrl);
         assert!(hotkey.modifiers.alt);
@@ -122,10 +659,86 @@ mod tests {
     #[test]
     fn test_parse_case_insensitive() {
         let hotkey = parse_hotkey("SHIFT+f8").unwrap();
-        assert_eq!(hotkey.key, Key::F8);
+        assert_eq!(hotkey.key, Some(Key::F8));
         assert!(hotkey.modifiers.shift);
     }
 
+    #[test]
+    fn test_from_str() -> Result<()> {
+        let hotkey: Hotkey = "Ctrl+F1".parse()?;
+        assert_eq!(hotkey, parse_hotkey("Ctrl+F1")?);
+        assert!(hotkey.modifiers.ctrl);
+
+        assert!("Ctrl+".parse::<Hotkey>().is_err());
+        assert!("Hyper+F1".parse::<Hotkey>().is_err());
+        assert!("F13".parse::<Hotkey>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_num_lock_condition_round_trips() {
+        for on in [true, false] {
+            let hotkey = parse_hotkey("Ctrl+Numpad1").unwrap().when_num_lock(on);
+            assert_eq!(parse_hotkey(&hotkey.to_string()).unwrap(), hotkey);
+        }
+        assert_eq!(
+            parse_hotkey("numpad1 (numlock OFF)").unwrap(),
+            parse_hotkey("Numpad1").unwrap().when_num_lock(false)
+        );
+        for invalid in [
+            "Numpad1 (NumLock)",
+            "Numpad1 (NumLock maybe)",
+            "Numpad1 (ScrollLock on)",
+            "Numpad1 (NumLock on) (NumLock off)",
+            "Numpad1)",
+        ] {
+            assert!(parse_hotkey(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_caps_lock_condition_round_trips() {
+        let hotkey = parse_hotkey("LShift+F8").unwrap().when_caps_lock(true);
+        assert_eq!(parse_hotkey(&hotkey.to_string()).unwrap(), hotkey);
+        let both = parse_hotkey("Numpad1")
+            .unwrap()
+            .when_num_lock(false)
+            .when_caps_lock(false);
+        assert_eq!(both.to_string(), "Numpad1 (NumLock off) (CapsLock off)");
+        assert_eq!(parse_hotkey(&both.to_string()).unwrap(), both);
+        assert!(parse_hotkey("F8 (CapsLock on) (CapsLock on)").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let hotkey = parse_hotkey("Ctrl+RAlt+F8").unwrap();
+        let json = serde_json::to_string(&hotkey).unwrap();
+        assert_eq!(json, "\"Ctrl+RAlt+F8\"");
+        assert_eq!(serde_json::from_str::<Hotkey>(&json).unwrap(), hotkey);
+        assert_eq!(
+            serde_json::from_str::<Hotkey>("\"shift+f8\"").unwrap(),
+            parse_hotkey("Shift+F8").unwrap()
+        );
+        assert!(serde_json::from_str::<Hotkey>("\"Hyper+F8\"").is_err());
+        let conditioned = hotkey.clone().when_caps_lock(true);
+        let json = serde_json::to_string(&conditioned).unwrap();
+        assert_eq!(serde_json::from_str::<Hotkey>(&json).unwrap(), conditioned);
+        assert!(
+            serde_json::to_string(&Hotkey::sequence(Key::F12, 3, Duration::from_secs(1))).is_err()
+        );
+
+        let key: Key = serde_json::from_str(&serde_json::to_string(&Key::PageUp).unwrap()).unwrap();
+        assert_eq!(key, Key::PageUp);
+        let modifiers: Modifiers = serde_json::from_str(r#"{"ctrl": true}"#).unwrap();
+        assert_eq!(modifiers, parse_hotkey("Ctrl+F1").unwrap().modifiers);
+        assert_eq!(
+            serde_json::from_str::<Modifiers>(&serde_json::to_string(&hotkey.modifiers).unwrap())
+                .unwrap(),
+            hotkey.modifiers
+        );
+    }
+
     #[test]
     fn test_parse_unknown_key() {
         assert!(parse_hotkey("Unknown").is_err());
@@ -133,12 +746,99 @@ mod tests {
 
     #[test]
     fn test_parse_unknown_modifier() {
-        assert!(parse_hotkey("Meta+F8").is_err());
+        assert!(parse_hotkey("Hyper+F8").is_err());
+    }
+
+    #[test]
+    fn test_parse_meta_aliases() {
+        for alias in ["Super", "META", "win", "Cmd"] {
+            let hotkey = parse_hotkey(&format!("{}+F1", alias)).unwrap();
+            assert_eq!(hotkey.key, Some(Key::F1));
+            assert!(hotkey.modifiers.meta, "{}", alias);
+            assert!(!hotkey.modifiers.shift);
+        }
+    }
+
+    #[test]
+    fn test_parse_sided_modifiers() {
+        let hotkey = parse_hotkey("RAlt+F1").unwrap();
+        assert!(hotkey.modifiers.alt);
+        assert_eq!(hotkey.modifiers.alt_side, Some(ModifierSide::Right));
+        assert_eq!(parse_hotkey("AltGr+F1").unwrap(), hotkey);
+
+        let hotkey = parse_hotkey("lctrl+Shift+F1").unwrap();
+        assert_eq!(hotkey.modifiers.ctrl_side, Some(ModifierSide::Left));
+        assert_eq!(hotkey.modifiers.shift_side, None);
+        assert_eq!(hotkey.to_string(), "LCtrl+Shift+F1");
+        assert_eq!(parse_hotkey(&hotkey.to_string()).unwrap(), hotkey);
+
+        assert_eq!(parse_hotkey("Alt+F1").unwrap().modifiers.alt_side, None);
+        assert!(parse_hotkey("LFoo+F1").is_err());
+    }
+
+    #[test]
+    fn test_meta_display_round_trips() {
+        let hotkey = parse_hotkey("Cmd+Shift+F1").unwrap();
+        assert_eq!(hotkey.to_string(), "Shift+Super+F1");
+        assert_eq!(parse_hotkey(&hotkey.to_string()).unwrap(), hotkey);
+        assert_eq!(hotkey.display_with(DisplayStyle::Symbols), "⇧⌘F1");
+    }
+
+    #[test]
+    fn test_canonical_modifier_order() {
+        let hotkey = parse_hotkey("Super+Shift+Alt+Ctrl+F8").unwrap();
+        assert_eq!(hotkey.canonical_string(), "Ctrl+Alt+Shift+Super+F8");
+        assert_eq!(
+            parse_hotkey("Shift+Ctrl+F8").unwrap().canonical_string(),
+            parse_hotkey("ctrl+shift+F8").unwrap().canonical_string()
+        );
+        assert_eq!(
+            parse_hotkey("AltGr+LShift+F1").unwrap().canonical_string(),
+            "RAlt+LShift+F1"
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_duplicate_modifiers() {
+        for s in [
+            "Shift+Shift+F8",
+            "Ctrl+Control+F8",
+            "Alt+AltGr+F8",
+            "Cmd+Win+F8",
+        ] {
+            let err = parse_hotkey(s).unwrap_err();
+            assert!(err.to_string().starts_with("Duplicate modifier"), "{}", s);
+        }
     }
 
     #[test]
     fn test_display() {
         let hotkey = parse_hotkey("Shift+F8").unwrap();
         assert_eq!(hotkey.to_string(), "Shift+F8");
+        assert_eq!(
+            hotkey.when_caps_lock(false).to_string(),
+            "Shift+F8 (CapsLock off)"
+        );
+    }
+
+    #[test]
+    fn test_navigation_hotkey_round_trips() {
+        let hotkey = parse_hotkey("Ctrl+PageDown").unwrap();
+        assert_eq!(hotkey.key, Some(Key::PageDown));
+        assert_eq!(hotkey.to_string(), "Ctrl+PageDown");
+        assert_eq!(
+            parse_hotkey("ctrl+pgdn").unwrap().to_string(),
+            "Ctrl+PageDown"
+        );
+    }
+
+    #[test]
+    fn test_display_styles() {
+        let hotkey = parse_hotkey("Shift+Ctrl+F8").unwrap();
+        assert_eq!(hotkey.display_with(DisplayStyle::Text), "Ctrl+Shift+F8");
+        assert_eq!(hotkey.display_with(DisplayStyle::Symbols), "⌃⇧F8");
+
+        let hotkey = parse_hotkey("Alt+Shift+F1").unwrap();
+        assert_eq!(hotkey.display_with(DisplayStyle::Symbols), "⌥⇧F1");
     }
 }
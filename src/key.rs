@@ -3,7 +3,10 @@
 use anyhow::{anyhow, Result};
 
 /// Platform-agnostic key representation.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// Keys sort in declaration order, with raw keycodes last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Key {
     F1,
     F2,
@@ -17,15 +20,342 @@ pub enum Key {
     F10,
     F11,
     F12,
+    Escape,
+    PrintScreen,
     ScrollLock,
     Pause,
     Insert,
+    /// The application (context menu) key.
+    Menu,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Delete,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadEnter,
+    NumpadPlus,
+    NumpadMinus,
+    NumpadMultiply,
+    NumpadDivide,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Digit0,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    /// A key given by its Linux evdev keycode, for keys without a variant
+    /// of their own, such as vendor-specific keys. Written `raw:<code>`,
+    /// e.g. `raw:190`. On Linux, parsing the code of a key that has a
+    /// variant gives that variant, and the codes of modifier keys are
+    /// rejected. Linux only; building a listener with one fails on macOS.
+    Raw(u16),
 }
 
 impl Key {
-    /// Parse a key from a string like "F8" or "ScrollLock".
+    /// Alternative names [`Key::parse`] accepts, uppercased, alongside each
+    /// key's canonical name. Formatting always uses the canonical name.
+    pub(crate) const ALIASES: &'static [(&'static str, Key)] = &[
+        ("ESC", Key::Escape),
+        ("PRTSC", Key::PrintScreen),
+        ("PRTSCN", Key::PrintScreen),
+        ("PRINT", Key::PrintScreen),
+        ("SYSRQ", Key::PrintScreen),
+        ("SCROLL_LOCK", Key::ScrollLock),
+        ("BREAK", Key::Pause),
+        ("INS", Key::Insert),
+        ("APP", Key::Menu),
+        ("ARROWUP", Key::Up),
+        ("ARROWDOWN", Key::Down),
+        ("ARROWLEFT", Key::Left),
+        ("ARROWRIGHT", Key::Right),
+        ("PAGE_UP", Key::PageUp),
+        ("PGUP", Key::PageUp),
+        ("PAGE_DOWN", Key::PageDown),
+        ("PGDN", Key::PageDown),
+        ("PGDOWN", Key::PageDown),
+        ("DEL", Key::Delete),
+        ("KP0", Key::Numpad0),
+        ("KP1", Key::Numpad1),
+        ("KP2", Key::Numpad2),
+        ("KP3", Key::Numpad3),
+        ("KP4", Key::Numpad4),
+        ("KP5", Key::Numpad5),
+        ("KP6", Key::Numpad6),
+        ("KP7", Key::Numpad7),
+        ("KP8", Key::Numpad8),
+        ("KP9", Key::Numpad9),
+        ("KPENTER", Key::NumpadEnter),
+        ("KPPLUS", Key::NumpadPlus),
+        ("KPMINUS", Key::NumpadMinus),
+        ("KPMULTIPLY", Key::NumpadMultiply),
+        ("KPDIVIDE", Key::NumpadDivide),
+        ("KPASTERISK", Key::NumpadMultiply),
+        ("KPSLASH", Key::NumpadDivide),
+    ];
+
+    /// Evdev codes of the Shift, Ctrl, Alt and Super keys.
+    const MODIFIER_CODES: [u16; 8] = [29, 42, 54, 56, 97, 100, 125, 126];
+
+    /// The key for evdev code `code`.
+    ///
+    /// Listeners always report a code with a variant of its own as that
+    /// variant, and modifier codes as modifiers, so a `Raw` binding of
+    /// either could never fire.
+    fn from_raw(code: u16) -> Result<Self> {
+        if Key::MODIFIER_CODES.contains(&code) {
+            return Err(anyhow!(
+                "raw:{} is a modifier key; use Shift, Ctrl, Alt or Super instead",
+                code
+            ));
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(&key) = Key::ALL
+            .iter()
+            .find(|&&key| crate::linux::to_evdev_key(key).code() == code)
+        {
+            return Ok(key);
+        }
+        Ok(Key::Raw(code))
+    }
+
+    /// Every key variant, used to build reverse lookups from native key codes.
+    pub(crate) const ALL: &'static [Key] = &[
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+        Key::Escape,
+        Key::PrintScreen,
+        Key::ScrollLock,
+        Key::Pause,
+        Key::Insert,
+        Key::Menu,
+        Key::Up,
+        Key::Down,
+        Key::Left,
+        Key::Right,
+        Key::Home,
+        Key::End,
+        Key::PageUp,
+        Key::PageDown,
+        Key::Delete,
+        Key::Numpad0,
+        Key::Numpad1,
+        Key::Numpad2,
+        Key::Numpad3,
+        Key::Numpad4,
+        Key::Numpad5,
+        Key::Numpad6,
+        Key::Numpad7,
+        Key::Numpad8,
+        Key::Numpad9,
+        Key::NumpadEnter,
+        Key::NumpadPlus,
+        Key::NumpadMinus,
+        Key::NumpadMultiply,
+        Key::NumpadDivide,
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+        Key::Digit0,
+        Key::Digit1,
+        Key::Digit2,
+        Key::Digit3,
+        Key::Digit4,
+        Key::Digit5,
+        Key::Digit6,
+        Key::Digit7,
+        Key::Digit8,
+        Key::Digit9,
+    ];
+
+    /// All supported keys, in declaration order. [`Key::Raw`] isn't included.
+    pub fn all() -> &'static [Key] {
+        Self::ALL
+    }
+
+    /// The character this key types under a US layout, if any.
+    ///
+    /// `shift` selects the shifted character, e.g. `A` or `!` for `Digit1`.
+    /// Function and special keys have no textual representation and return
+    /// `None`, as do numpad keys, whose meaning depends on NumLock. The US
+    /// layout is assumed regardless of the layout actually configured, so
+    /// this is only a display hint.
+    pub fn to_char(self, shift: bool) -> Option<char> {
+        let (plain, shifted) = match self {
+            Key::A => ('a', 'A'),
+            Key::B => ('b', 'B'),
+            Key::C => ('c', 'C'),
+            Key::D => ('d', 'D'),
+            Key::E => ('e', 'E'),
+            Key::F => ('f', 'F'),
+            Key::G => ('g', 'G'),
+            Key::H => ('h', 'H'),
+            Key::I => ('i', 'I'),
+            Key::J => ('j', 'J'),
+            Key::K => ('k', 'K'),
+            Key::L => ('l', 'L'),
+            Key::M => ('m', 'M'),
+            Key::N => ('n', 'N'),
+            Key::O => ('o', 'O'),
+            Key::P => ('p', 'P'),
+            Key::Q => ('q', 'Q'),
+            Key::R => ('r', 'R'),
+            Key::S => ('s', 'S'),
+            Key::T => ('t', 'T'),
+            Key::U => ('u', 'U'),
+            Key::V => ('v', 'V'),
+            Key::W => ('w', 'W'),
+            Key::X => ('x', 'X'),
+            Key::Y => ('y', 'Y'),
+            Key::Z => ('z', 'Z'),
+            Key::Digit0 => ('0', ')'),
+            Key::Digit1 => ('1', '!'),
+            Key::Digit2 => ('2', '@'),
+            Key::Digit3 => ('3', '#'),
+            Key::Digit4 => ('4', '$'),
+            Key::Digit5 => ('5', '%'),
+            Key::Digit6 => ('6', '^'),
+            Key::Digit7 => ('7', '&'),
+            Key::Digit8 => ('8', '*'),
+            Key::Digit9 => ('9', '('),
+            Key::F1
+            | Key::F2
+            | Key::F3
+            | Key::F4
+            | Key::F5
+            | Key::F6
+            | Key::F7
+            | Key::F8
+            | Key::F9
+            | Key::F10
+            | Key::F11
+            | Key::F12
+            | Key::Escape
+            | Key::PrintScreen
+            | Key::ScrollLock
+            | Key::Pause
+            | Key::Insert
+            | Key::Menu
+            | Key::Up
+            | Key::Down
+            | Key::Left
+            | Key::Right
+            | Key::Home
+            | Key::End
+            | Key::PageUp
+            | Key::PageDown
+            | Key::Delete
+            | Key::Numpad0
+            | Key::Numpad1
+            | Key::Numpad2
+            | Key::Numpad3
+            | Key::Numpad4
+            | Key::Numpad5
+            | Key::Numpad6
+            | Key::Numpad7
+            | Key::Numpad8
+            | Key::Numpad9
+            | Key::NumpadEnter
+            | Key::NumpadPlus
+            | Key::NumpadMinus
+            | Key::NumpadMultiply
+            | Key::NumpadDivide
+            | Key::Raw(_) => return None,
+        };
+        Some(if shift { shifted } else { plain })
+    }
+
+    /// Parse a key from a string like "F8", "ScrollLock", "A", "7", "PageUp"
+    /// or "raw:190". Case-insensitive. Alternative names such as "PgUp",
+    /// "Esc" or "KP1" are accepted too.
     pub fn parse(s: &str) -> Result<Self> {
-        match s.to_uppercase().as_str() {
+        let upper = s.to_uppercase();
+        if let Some(code) = upper.strip_prefix("RAW:") {
+            let code = code
+                .parse()
+                .map_err(|_| anyhow!("Invalid raw keycode: {}", s))?;
+            return Key::from_raw(code);
+        }
+        if let Some(&(_, key)) = Key::ALIASES.iter().find(|(alias, _)| *alias == upper) {
+            return Ok(key);
+        }
+        match upper.as_str() {
             "F1" => Ok(Key::F1),
             "F2" => Ok(Key::F2),
             "F3" => Ok(Key::F3),
@@ -38,9 +368,72 @@ impl Key {
             "F10" => Ok(Key::F10),
             "F11" => Ok(Key::F11),
             "F12" => Ok(Key::F12),
-            "SCROLLLOCK" | "SCROLL_LOCK" => Ok(Key::ScrollLock),
+            "ESCAPE" => Ok(Key::Escape),
+            "PRINTSCREEN" => Ok(Key::PrintScreen),
+            "SCROLLLOCK" => Ok(Key::ScrollLock),
             "PAUSE" => Ok(Key::Pause),
             "INSERT" => Ok(Key::Insert),
+            "MENU" => Ok(Key::Menu),
+            "UP" => Ok(Key::Up),
+            "DOWN" => Ok(Key::Down),
+            "LEFT" => Ok(Key::Left),
+            "RIGHT" => Ok(Key::Right),
+            "HOME" => Ok(Key::Home),
+            "END" => Ok(Key::End),
+            "PAGEUP" => Ok(Key::PageUp),
+            "PAGEDOWN" => Ok(Key::PageDown),
+            "DELETE" => Ok(Key::Delete),
+            "NUMPAD0" => Ok(Key::Numpad0),
+            "NUMPAD1" => Ok(Key::Numpad1),
+            "NUMPAD2" => Ok(Key::Numpad2),
+            "NUMPAD3" => Ok(Key::Numpad3),
+            "NUMPAD4" => Ok(Key::Numpad4),
+            "NUMPAD5" => Ok(Key::Numpad5),
+            "NUMPAD6" => Ok(Key::Numpad6),
+            "NUMPAD7" => Ok(Key::Numpad7),
+            "NUMPAD8" => Ok(Key::Numpad8),
+            "NUMPAD9" => Ok(Key::Numpad9),
+            "NUMPADENTER" => Ok(Key::NumpadEnter),
+            "NUMPADPLUS" => Ok(Key::NumpadPlus),
+            "NUMPADMINUS" => Ok(Key::NumpadMinus),
+            "NUMPADMULTIPLY" => Ok(Key::NumpadMultiply),
+            "NUMPADDIVIDE" => Ok(Key::NumpadDivide),
+            "A" => Ok(Key::A),
+            "B" => Ok(Key::B),
+            "C" => Ok(Key::C),
+            "D" => Ok(Key::D),
+            "E" => Ok(Key::E),
+            "F" => Ok(Key::F),
+            "G" => Ok(Key::G),
+            "H" => Ok(Key::H),
+            "I" => Ok(Key::I),
+            "J" => Ok(Key::J),
+            "K" => Ok(Key::K),
+            "L" => Ok(Key::L),
+            "M" => Ok(Key::M),
+            "N" => Ok(Key::N),
+            "O" => Ok(Key::O),
+            "P" => Ok(Key::P),
+            "Q" => Ok(Key::Q),
+            "R" => Ok(Key::R),
+            "S" => Ok(Key::S),
+            "T" => Ok(Key::T),
+            "U" => Ok(Key::U),
+            "V" => Ok(Key::V),
+            "W" => Ok(Key::W),
+            "X" => Ok(Key::X),
+            "Y" => Ok(Key::Y),
+            "Z" => Ok(Key::Z),
+            "0" => Ok(Key::Digit0),
+            "1" => Ok(Key::Digit1),
+            "2" => Ok(Key::Digit2),
+            "3" => Ok(Key::Digit3),
+            "4" => Ok(Key::Digit4),
+            "5" => Ok(Key::Digit5),
+            "6" => Ok(Key::Digit6),
+            "7" => Ok(Key::Digit7),
+            "8" => Ok(Key::Digit8),
+            "9" => Ok(Key::Digit9),
             _ => Err(anyhow!("Unknown key: {}", s)),
         }
     }
@@ -61,9 +454,153 @@ impl std::fmt::Display for Key {
             Key::F10 => write!(f, "F10"),
             Key::F11 => write!(f, "F11"),
             Key::F12 => write!(f, "F12"),
+            Key::Escape => write!(f, "Escape"),
+            Key::PrintScreen => write!(f, "PrintScreen"),
             Key::ScrollLock => write!(f, "ScrollLock"),
             Key::Pause => write!(f, "Pause"),
             Key::Insert => write!(f, "Insert"),
+            Key::Menu => write!(f, "Menu"),
+            Key::Up => write!(f, "Up"),
+            Key::Down => write!(f, "Down"),
+            Key::Left => write!(f, "Left"),
+            Key::Right => write!(f, "Right"),
+            Key::Home => write!(f, "Home"),
+            Key::End => write!(f, "End"),
+            Key::PageUp => write!(f, "PageUp"),
+            Key::PageDown => write!(f, "PageDown"),
+            Key::Delete => write!(f, "Delete"),
+            Key::Numpad0 => write!(f, "Numpad0"),
+            Key::Numpad1 => write!(f, "Numpad1"),
+            Key::Numpad2 => write!(f, "Numpad2"),
+            Key::Numpad3 => write!(f, "Numpad3"),
+            Key::Numpad4 => write!(f, "Numpad4"),
+            Key::Numpad5 => write!(f, "Numpad5"),
+            Key::Numpad6 => write!(f, "Numpad6"),
+            Key::Numpad7 => write!(f, "Numpad7"),
+            Key::Numpad8 => write!(f, "Numpad8"),
+            Key::Numpad9 => write!(f, "Numpad9"),
+            Key::NumpadEnter => write!(f, "NumpadEnter"),
+            Key::NumpadPlus => write!(f, "NumpadPlus"),
+            Key::NumpadMinus => write!(f, "NumpadMinus"),
+            Key::NumpadMultiply => write!(f, "NumpadMultiply"),
+            Key::NumpadDivide => write!(f, "NumpadDivide"),
+            Key::A => write!(f, "A"),
+            Key::B => write!(f, "B"),
+            Key::C => write!(f, "C"),
+            Key::D => write!(f, "D"),
+            Key::E => write!(f, "E"),
+            Key::F => write!(f, "F"),
+            Key::G => write!(f, "G"),
+            Key::H => write!(f, "H"),
+            Key::I => write!(f, "I"),
+            Key::J => write!(f, "J"),
+            Key::K => write!(f, "K"),
+            Key::L => write!(f, "L"),
+            Key::M => write!(f, "M"),
+            Key::N => write!(f, "N"),
+            Key::O => write!(f, "O"),
+            Key::P => write!(f, "P"),
+            Key::Q => write!(f, "Q"),
+            Key::R => write!(f, "R"),
+            Key::S => write!(f, "S"),
+            Key::T => write!(f, "T"),
+            Key::U => write!(f, "U"),
+            Key::V => write!(f, "V"),
+            Key::W => write!(f, "W"),
+            Key::X => write!(f, "X"),
+            Key::Y => write!(f, "Y"),
+            Key::Z => write!(f, "Z"),
+            Key::Digit0 => write!(f, "0"),
+            Key::Digit1 => write!(f, "1"),
+            Key::Digit2 => write!(f, "2"),
+            Key::Digit3 => write!(f, "3"),
+            Key::Digit4 => write!(f, "4"),
+            Key::Digit5 => write!(f, "5"),
+            Key::Digit6 => write!(f, "6"),
+            Key::Digit7 => write!(f, "7"),
+            Key::Digit8 => write!(f, "8"),
+            Key::Digit9 => write!(f, "9"),
+            Key::Raw(code) => write!(f, "raw:{}", code),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_key_round_trips_through_display() {
+        for &key in Key::all() {
+            assert_eq!(Key::parse(&key.to_string()).unwrap(), key);
+            assert_eq!(Key::parse(&key.to_string().to_lowercase()).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn test_parse_navigation_aliases() {
+        assert_eq!(Key::parse("PgUp").unwrap(), Key::PageUp);
+        assert_eq!(Key::parse("PAGEUP").unwrap(), Key::PageUp);
+        assert_eq!(Key::parse("page_down").unwrap(), Key::PageDown);
+        assert_eq!(Key::parse("PGDN").unwrap(), Key::PageDown);
+        assert_eq!(Key::parse("del").unwrap(), Key::Delete);
+        assert_eq!(Key::parse("ArrowUp").unwrap(), Key::Up);
+        assert_eq!(Key::parse("pgup").unwrap().to_string(), "PageUp");
+    }
+
+    #[test]
+    fn test_parse_aliases() {
+        assert_eq!(Key::parse("Esc").unwrap(), Key::Escape);
+        assert_eq!(Key::parse("escape").unwrap(), Key::Escape);
+        assert_eq!(Key::parse("Del").unwrap(), Key::Delete);
+        assert_eq!(Key::parse("Ins").unwrap(), Key::Insert);
+        assert_eq!(Key::parse("Break").unwrap(), Key::Pause);
+        assert_eq!(Key::parse("PrtSc").unwrap(), Key::PrintScreen);
+        assert_eq!(Key::parse("prtsc").unwrap().to_string(), "PrintScreen");
+        assert_eq!(Key::parse("esc").unwrap().to_string(), "Escape");
+        assert_eq!(Key::parse("SysRq").unwrap(), Key::PrintScreen);
+        assert_eq!(Key::parse("menu").unwrap(), Key::Menu);
+        assert_eq!(Key::parse("App").unwrap().to_string(), "Menu");
+        assert_eq!(Key::parse("KP5").unwrap(), Key::Numpad5);
+        assert_eq!(Key::parse("kpenter").unwrap(), Key::NumpadEnter);
+        assert_eq!(Key::parse("NumpadPlus").unwrap(), Key::NumpadPlus);
+        for &(alias, key) in Key::ALIASES {
+            assert_eq!(Key::parse(alias).unwrap(), key, "{}", alias);
+            assert_eq!(Key::parse(&alias.to_lowercase()).unwrap(), key, "{}", alias);
+            assert_ne!(key.to_string().to_uppercase(), alias);
+        }
+    }
+
+    #[test]
+    fn test_parse_alphanumeric() {
+        assert_eq!(Key::parse("A").unwrap(), Key::A);
+        assert_eq!(Key::parse("a").unwrap(), Key::A);
+        assert_eq!(Key::parse("z").unwrap(), Key::Z);
+        assert_eq!(Key::parse("7").unwrap(), Key::Digit7);
+        assert_eq!(Key::Digit7.to_string(), "7");
+        assert!(Key::parse("AB").is_err());
+        assert!(Key::parse("10").is_err());
+    }
+
+    #[test]
+    fn test_parse_raw_keycode() {
+        assert_eq!(Key::parse("raw:190").unwrap(), Key::Raw(190));
+        assert_eq!(Key::parse("RAW:190").unwrap(), Key::Raw(190));
+        assert_eq!(Key::Raw(190).to_string(), "raw:190");
+        assert!(Key::parse("raw:").is_err());
+        assert!(Key::parse("raw:-1").is_err());
+        assert!(Key::parse("raw:65536").is_err());
+        // Modifiers can never be bound as raw keys
+        assert!(Key::parse("raw:42").is_err());
+        assert!(Key::parse("raw:126").is_err());
+    }
+
+    #[test]
+    fn test_to_char_respects_shift() {
+        assert_eq!(Key::A.to_char(true), Some('A'));
+        assert_eq!(Key::A.to_char(false), Some('a'));
+        assert_eq!(Key::Digit1.to_char(true), Some('!'));
+        assert_eq!(Key::F8.to_char(false), None);
+        assert_eq!(Key::Insert.to_char(true), None);
+    }
+}
@@ -0,0 +1,182 @@
+//! Information about detected keyboards.
+
+use crate::error::HotkeyError;
+use std::path::PathBuf;
+
+/// The bus a keyboard is connected through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BusType {
+    Usb,
+    Bluetooth,
+    /// The PS/2 controller used by most built-in laptop keyboards.
+    I8042,
+    Virtual,
+    /// Any other bus, with its raw Linux `BUS_*` code.
+    Other(u16),
+}
+
+impl BusType {
+    /// Map a raw Linux `BUS_*` code to a bus type.
+    pub fn from_raw(code: u16) -> Self {
+        match code {
+            0x03 => BusType::Usb,
+            0x05 => BusType::Bluetooth,
+            0x06 => BusType::Virtual,
+            0x11 => BusType::I8042,
+            other => BusType::Other(other),
+        }
+    }
+}
+
+impl std::fmt::Display for BusType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BusType::Usb => write!(f, "USB"),
+            BusType::Bluetooth => write!(f, "Bluetooth"),
+            BusType::I8042 => write!(f, "i8042"),
+            BusType::Virtual => write!(f, "virtual"),
+            BusType::Other(code) => write!(f, "bus 0x{:02x}", code),
+        }
+    }
+}
+
+/// A set of keyboards to listen to, classified by how they're connected.
+///
+/// The classification is a heuristic based on the bus type: built-in laptop
+/// keyboards almost always sit on the i8042 (PS/2) controller or a platform
+/// host bus, while external keyboards connect over USB or Bluetooth. Some
+/// laptops attach their built-in keyboard over internal USB, which counts as
+/// external here; use [`HotkeyListenerBuilder::use_only`] for those.
+/// Virtual and other devices belong to neither group.
+///
+/// [`HotkeyListenerBuilder::use_only`]: crate::HotkeyListenerBuilder::use_only
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyboardGroup {
+    /// Every keyboard.
+    #[default]
+    All,
+    /// Built-in keyboards, on the i8042 controller or host bus.
+    Internal,
+    /// Keyboards connected over USB or Bluetooth.
+    External,
+}
+
+impl KeyboardGroup {
+    /// Whether a keyboard on `bus` belongs to this group.
+    pub fn contains(self, bus: BusType) -> bool {
+        match self {
+            KeyboardGroup::All => true,
+            KeyboardGroup::Internal => matches!(bus, BusType::I8042 | BusType::Other(BUS_HOST)),
+            KeyboardGroup::External => matches!(bus, BusType::Usb | BusType::Bluetooth),
+        }
+    }
+}
+
+/// Linux `BUS_HOST`, used by some built-in keyboards on non-x86 laptops.
+const BUS_HOST: u16 = 0x19;
+
+/// A detected keyboard, described without exposing platform types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyboardInfo {
+    /// Device name reported by the kernel.
+    pub name: String,
+    /// Device node, e.g. `/dev/input/event3`.
+    pub path: PathBuf,
+    /// Physical location reported by the kernel, e.g. `usb-0000:00:14.0-2/input0`.
+    pub physical_path: Option<String>,
+    pub bus_type: BusType,
+    /// USB-style vendor id, 0 if the device doesn't report one.
+    pub vendor: u16,
+    /// USB-style product id, 0 if the device doesn't report one.
+    pub product: u16,
+    /// Number of keys the device reports supporting.
+    pub key_count: usize,
+}
+
+impl std::fmt::Display for KeyboardInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}, {}, {} keys)",
+            self.name,
+            self.path.display(),
+            self.bus_type,
+            self.key_count
+        )
+    }
+}
+
+/// List the keyboards that the listener would use.
+///
+/// Handy for a `--list-keyboards` style CLI flag. On Linux this scans
+/// `/dev/input`, lists one node per physical keyboard as the listener does by
+/// default, and returns an empty list if it can't be read. On macOS,
+/// keyboards aren't enumerated individually, so the list is always empty.
+pub fn list_keyboards() -> Vec<KeyboardInfo> {
+    #[cfg(target_os = "linux")]
+    {
+        crate::linux::list_keyboards()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Vec::new()
+    }
+}
+
+/// Check that the listener will be allowed to read the keyboards.
+///
+/// On Linux, returns [`HotkeyError::PermissionDenied`] naming the
+/// `/dev/input` nodes that can't be opened, with a hint based on the user's
+/// `input` group membership. On macOS, always succeeds; a missing
+/// Accessibility permission is reported by `start` instead.
+pub fn check_permissions() -> Result<(), HotkeyError> {
+    #[cfg(target_os = "linux")]
+    {
+        crate::linux::check_permissions()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bus_type_from_raw() {
+        assert_eq!(BusType::from_raw(0x03), BusType::Usb);
+        assert_eq!(BusType::from_raw(0x05), BusType::Bluetooth);
+        assert_eq!(BusType::from_raw(0x11), BusType::I8042);
+        assert_eq!(BusType::from_raw(0x18), BusType::Other(0x18));
+    }
+
+    #[test]
+    fn test_keyboard_group_contains() {
+        assert!(KeyboardGroup::Internal.contains(BusType::I8042));
+        assert!(!KeyboardGroup::Internal.contains(BusType::Usb));
+        assert!(KeyboardGroup::External.contains(BusType::Usb));
+        assert!(KeyboardGroup::External.contains(BusType::Bluetooth));
+        assert!(!KeyboardGroup::External.contains(BusType::I8042));
+        assert!(!KeyboardGroup::External.contains(BusType::Virtual));
+        assert!(KeyboardGroup::All.contains(BusType::Virtual));
+    }
+
+    #[test]
+    fn test_display() {
+        let info = KeyboardInfo {
+            name: "AT Translated Set 2 keyboard".to_string(),
+            path: PathBuf::from("/dev/input/event3"),
+            physical_path: Some("isa0060/serio0/input0".to_string()),
+            bus_type: BusType::I8042,
+            vendor: 0x0001,
+            product: 0x0001,
+            key_count: 105,
+        };
+        assert_eq!(
+            info.to_string(),
+            "AT Translated Set 2 keyboard (/dev/input/event3, i8042, 105 keys)"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+//! Best-effort detection of the configured keyboard layout.
+
+use crate::hotkey::Hotkey;
+
+/// Files that may hold the system keyboard layout, in order of preference.
+#[cfg(target_os = "linux")]
+const LAYOUT_FILES: &[&str] = &[
+    "/etc/X11/xorg.conf.d/00-keyboard.conf",
+    "/etc/default/keyboard",
+    "/etc/vconsole.conf",
+];
+
+/// Detect the system keyboard layout, e.g. `"us"` or `"de,us"`.
+///
+/// On Linux this reads the XKB configuration written by `localectl` or the
+/// distribution's keyboard setup. The layout of a running desktop session
+/// may differ, so treat the result as a hint. Returns `None` if no layout is
+/// configured, and always on other platforms.
+pub fn detect_layout() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        LAYOUT_FILES.iter().find_map(|path| {
+            let contents = std::fs::read_to_string(path).ok()?;
+            parse_layout(&contents)
+        })
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Extract the layout from an `XKBLAYOUT=` assignment or an xorg.conf `XkbLayout` option.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_layout(contents: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let line = line.trim();
+        let value = if let Some(value) = line.strip_prefix("XKBLAYOUT=") {
+            value
+        } else {
+            let option = line.strip_prefix("Option")?.trim_start();
+            option.strip_prefix("\"XkbLayout\"")?
+        };
+        let value = value.trim().trim_matches('"');
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// Describe why `hotkeys` may not match as expected under `layout`.
+///
+/// Keys that type a character are bound by their US-layout position, so they
+/// can end up on a different key under another primary layout.
+pub(crate) fn layout_mismatch(hotkeys: &[Hotkey], layout: Option<&str>) -> Option<String> {
+    let primary = layout?.split(',').next()?.trim();
+    if primary == "us" {
+        return None;
+    }
+    let symbols: Vec<String> = hotkeys
+        .iter()
+        .filter(|hotkey| hotkey.key.is_some_and(|key| key.to_char(false).is_some()))
+        .map(ToString::to_string)
+        .collect();
+    if symbols.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "Hotkeys {} are bound by US layout key positions, but the keyboard layout is {:?}",
+        symbols.join(", "),
+        primary
+    ))
+}
+
+/// Log a warning if symbol hotkeys are used under a non-US layout.
+pub(crate) fn warn_on_layout_mismatch(hotkeys: &[Hotkey], detect: impl FnOnce() -> Option<String>) {
+    if let Some(warning) = layout_mismatch(hotkeys, detect().as_deref()) {
+        log::warn!("{}", warning);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::parse_hotkey;
+
+    #[test]
+    fn test_parse_layout() {
+        let default_keyboard = "XKBMODEL=\"pc105\"\nXKBLAYOUT=\"de,us\"\nXKBVARIANT=\"\"\n";
+        assert_eq!(parse_layout(default_keyboard).as_deref(), Some("de,us"));
+
+        let xorg = "Section \"InputClass\"\n        Option \"XkbLayout\" \"fr\"\nEndSection\n";
+        assert_eq!(parse_layout(xorg).as_deref(), Some("fr"));
+
+        assert_eq!(parse_layout("KEYMAP=us\n"), None);
+    }
+
+    #[test]
+    fn test_function_keys_never_mismatch() {
+        let hotkeys = vec![parse_hotkey("Shift+F8").unwrap()];
+        assert_eq!(layout_mismatch(&hotkeys, Some("de")), None);
+        assert_eq!(layout_mismatch(&hotkeys, Some("us")), None);
+        assert_eq!(layout_mismatch(&hotkeys, None), None);
+    }
+
+    #[test]
+    fn test_letter_keys_mismatch_under_other_layouts() {
+        let hotkeys = vec![parse_hotkey("Ctrl+Z").unwrap(), parse_hotkey("F8").unwrap()];
+        let warning = layout_mismatch(&hotkeys, Some("de,us")).unwrap();
+        assert!(warning.contains("Ctrl+Z"));
+        assert!(!warning.contains("F8"));
+        assert_eq!(layout_mismatch(&hotkeys, Some("us")), None);
+    }
+}
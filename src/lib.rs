@@ -33,6 +33,7 @@
 //!         match handle.recv_timeout(Duration::from_millis(100)) {
 //!             Ok(HotkeyEvent::Pressed(idx)) => println!("Hotkey {} pressed", idx),
 //!             Ok(HotkeyEvent::Released(idx)) => println!("Hotkey {} released", idx),
+//!             Ok(_) => { /* opt-in events like `Repeat` and `LongPressed` */ }
 //!             Err(_) => { /* timeout, check exit conditions */ }
 //!         }
 //!     }
@@ -46,21 +47,61 @@
 //! On Linux, the user must have permission to read from `/dev/input/event*` devices.
 //! This typically means running as root or being a member of the `input` group.
 
+mod capabilities;
+mod channel;
+mod error;
 mod event;
+mod gesture;
 mod hotkey;
 mod key;
+mod keyboard;
+mod layout;
 mod listener;
+mod matcher;
+mod report;
+mod status;
+#[cfg(feature = "futures")]
+mod stream;
 
+#[cfg(all(target_os = "linux", feature = "calloop"))]
+mod calloop;
+#[cfg(target_os = "linux")]
+mod evemu;
+#[cfg(all(target_os = "linux", feature = "libinput"))]
+mod libinput;
 #[cfg(target_os = "linux")]
 mod linux;
+#[cfg(all(target_os = "linux", feature = "test-util"))]
+mod mock;
+#[cfg(all(target_os = "linux", feature = "x11"))]
+mod x11;
 
 #[cfg(target_os = "macos")]
 mod macos;
 
-pub use event::HotkeyEvent;
-pub use hotkey::{parse_hotkey, Hotkey, Modifiers};
+pub use capabilities::{capabilities, Capabilities};
+pub use error::HotkeyError;
+pub use event::{DeviceHotkeyEvent, HotkeyEvent, TimedHotkeyEvent};
+pub use gesture::{Gesture, GestureConfig, GestureListener, GestureRecognizer};
+pub use hotkey::{
+    is_valid_hotkey, parse_hotkey, supported_modifiers, validate_hotkey, Chord, DisplayStyle,
+    Hotkey, KeySequence, ModifierSide, ModifierTaps, Modifiers,
+};
 pub use key::Key;
-pub use listener::{HotkeyListener, HotkeyListenerBuilder, HotkeyListenerHandle};
+pub use keyboard::{check_permissions, list_keyboards, BusType, KeyboardGroup, KeyboardInfo};
+pub use layout::detect_layout;
+pub use listener::{
+    Backend, HotkeyListener, HotkeyListenerBuilder, HotkeyListenerHandle, ModifierMatch,
+    MultiDevicePress,
+};
+pub use report::StartupReport;
+pub use status::ListenerStatus;
+#[cfg(feature = "futures")]
+pub use stream::HotkeyEventStream;
 
+#[cfg(all(target_os = "linux", feature = "calloop"))]
+pub use crate::calloop::HotkeySource;
 #[cfg(target_os = "linux")]
 pub use linux::find_keyboards;
+#[cfg(all(target_os = "linux", feature = "test-util"))]
+pub use mock::MockKeyboard;
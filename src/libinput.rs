@@ -0,0 +1,247 @@
+//! Fallback Linux backend reading keyboards through libinput.
+//!
+//! Used when `/dev/input` can't be opened directly but the user's session has
+//! access to the seat's devices, which are opened through logind or seatd
+//! using libseat. Key events are translated back into evdev
+//! events so they go through the same matching as the evdev backend.
+
+use crate::channel::Sender;
+use crate::event::HotkeyEvent;
+use crate::hotkey::Hotkey;
+use crate::linux::EventProcessor;
+use crate::listener::ListenerConfig;
+use anyhow::{anyhow, bail, Result};
+use input::event::device::DeviceEvent;
+use input::event::keyboard::{KeyState, KeyboardEvent, KeyboardEventTrait};
+use input::event::{Event, EventTrait};
+use input::{DeviceCapability, Libinput, LibinputInterface};
+use libseat::{Seat, SeatEvent};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the session's seat to become active at startup.
+const SEAT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Opens devices on behalf of libinput through the session's seat.
+///
+/// logind or seatd hand out the seat's devices to the active session, which
+/// is what makes this work without read access to `/dev/input`.
+struct Interface {
+    seat: Rc<RefCell<Seat>>,
+    devices: HashMap<RawFd, libseat::Device>,
+}
+
+impl LibinputInterface for Interface {
+    fn open_restricted(&mut self, path: &Path, _flags: i32) -> Result<OwnedFd, i32> {
+        let device = self.seat.borrow_mut().open_device(&path).map_err(|e| e.0)?;
+        let fd = device.as_fd().as_raw_fd();
+        self.devices.insert(fd, device);
+        // SAFETY: the seat hands the fd over to us; it's closed once libinput
+        // gives it back through close_restricted.
+        Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+
+    fn close_restricted(&mut self, fd: OwnedFd) {
+        if let Some(device) = self.devices.remove(&fd.as_raw_fd()) {
+            if let Err(e) = self.seat.borrow_mut().close_device(device) {
+                log::debug!("Failed to close seat device: {}", e);
+            }
+        }
+    }
+}
+
+/// Open the session's seat through logind or seatd and wait until it's
+/// active, so its devices can be opened.
+fn open_seat() -> Result<Seat> {
+    let active = Rc::new(Cell::new(false));
+    let mut seat = Seat::open({
+        let active = active.clone();
+        move |seat, event| match event {
+            SeatEvent::Enable => active.set(true),
+            SeatEvent::Disable => {
+                active.set(false);
+                let _ = seat.disable();
+            }
+        }
+    })
+    .map_err(|e| anyhow!("Failed to open the seat through logind or seatd: {}", e))?;
+    let deadline = Instant::now() + SEAT_TIMEOUT;
+    while !active.get() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            bail!("The seat didn't become active, is this session in the foreground?");
+        }
+        let timeout = i32::try_from(remaining.as_millis()).unwrap_or(i32::MAX);
+        seat.dispatch(timeout)
+            .map_err(|e| anyhow!("Failed to wait for the seat: {}", e))?;
+    }
+    Ok(seat)
+}
+
+/// Count the keyboards libinput added when it opened the seat's devices,
+/// failing if there are none.
+///
+/// Without this, a seat whose devices all fail to open would start a
+/// listener that never receives a key.
+fn added_keyboards(input: &mut Libinput, seat: &str) -> Result<usize> {
+    input
+        .dispatch()
+        .map_err(|e| anyhow!("libinput dispatch failed: {}", e))?;
+    let keyboards = input
+        .by_ref()
+        .filter(|event| {
+            matches!(
+                event,
+                Event::Device(DeviceEvent::Added(added))
+                    if added.device().has_capability(DeviceCapability::Keyboard)
+            )
+        })
+        .count();
+    if keyboards == 0 {
+        bail!("No keyboards could be opened on seat {}", seat);
+    }
+    Ok(keyboards)
+}
+
+/// Start reading keyboard events from the session's seat in a background thread.
+///
+/// Returns once the seat's keyboards have been opened, or with an error if
+/// the seat couldn't be opened or has no keyboards.
+pub(crate) fn start_libinput_listener(
+    hotkeys: Vec<Hotkey>,
+    config: ListenerConfig,
+    running: Arc<AtomicBool>,
+    tx: Sender<HotkeyEvent>,
+) -> Result<()> {
+    let listener_thread = config.shared.thread.clone();
+    let status = config.shared.status.clone();
+    let mut processor = EventProcessor::new(hotkeys, config);
+    // The libinput context isn't Send, so it's created on the listener thread
+    let (ready_tx, ready_rx) = mpsc::channel();
+
+    listener_thread.set(thread::spawn(move || {
+        let _stopped = status.stop_on_drop();
+        let mut seat = match open_seat() {
+            Ok(seat) => seat,
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+        let seat_name = seat.name().to_string();
+        let seat = Rc::new(RefCell::new(seat));
+        let mut input = Libinput::new_with_udev(Interface {
+            seat: seat.clone(),
+            devices: HashMap::new(),
+        });
+        if input.udev_assign_seat(&seat_name).is_err() {
+            let _ = ready_tx.send(Err(anyhow!("Failed to assign libinput seat {}", seat_name)));
+            return;
+        }
+        match added_keyboards(&mut input, &seat_name) {
+            Ok(keyboards) => {
+                status.connected(keyboards);
+                let _ = ready_tx.send(Ok(()));
+            }
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        }
+
+        while running.load(Ordering::Relaxed) {
+            // Handle the seat being disabled and enabled on session switches
+            if let Err(e) = seat.borrow_mut().dispatch(0) {
+                log::warn!("Seat dispatch failed: {}", e);
+                break;
+            }
+            if let Err(e) = input.dispatch() {
+                log::warn!("libinput dispatch failed: {}", e);
+                break;
+            }
+            for event in &mut input {
+                let Event::Keyboard(KeyboardEvent::Key(key_event)) = event else {
+                    continue;
+                };
+                let Ok(code) = u16::try_from(key_event.key()) else {
+                    continue;
+                };
+                let value = match key_event.key_state() {
+                    KeyState::Pressed => 1,
+                    KeyState::Released => 0,
+                };
+                let event = evdev::InputEvent::new(evdev::EventType::KEY, code, value);
+                for hotkey_event in processor.process(&event) {
+                    let _ = tx.send(hotkey_event);
+                }
+            }
+            for hotkey_event in processor
+                .process_injected()
+                .into_iter()
+                .chain(processor.release_stuck())
+                .chain(processor.poll_long_presses())
+            {
+                let _ = tx.send(hotkey_event);
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }));
+
+    ready_rx
+        .recv()
+        .map_err(|_| anyhow!("libinput listener thread exited during startup"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{File, OpenOptions};
+    use std::os::unix::fs::OpenOptionsExt;
+
+    /// Opens devices directly, for contexts that aren't backed by a seat.
+    struct Direct;
+
+    impl LibinputInterface for Direct {
+        fn open_restricted(&mut self, path: &Path, flags: i32) -> Result<OwnedFd, i32> {
+            OpenOptions::new()
+                .custom_flags(flags)
+                .read(true)
+                .open(path)
+                .map(OwnedFd::from)
+                .map_err(|e| e.raw_os_error().unwrap_or(libc::EIO))
+        }
+
+        fn close_restricted(&mut self, fd: OwnedFd) {
+            drop(File::from(fd));
+        }
+    }
+
+    #[test]
+    fn test_startup_fails_without_keyboards() {
+        // A context with no devices stands in for a seat whose devices
+        // couldn't be opened
+        let mut input = Libinput::new_from_path(Direct);
+        let error = added_keyboards(&mut input, "seat0").unwrap_err();
+        assert!(error.to_string().contains("No keyboards"));
+    }
+
+    #[test]
+    fn test_startup_counts_added_keyboards() {
+        // Needs a readable keyboard node; skipped where there is none
+        let Some(keyboard) = crate::list_keyboards().into_iter().next() else {
+            return;
+        };
+        let mut input = Libinput::new_from_path(Direct);
+        assert!(input
+            .path_add_device(&keyboard.path.to_string_lossy())
+            .is_some());
+        assert_eq!(added_keyboards(&mut input, "seat0").unwrap(), 1);
+    }
+}
@@ -1,22 +1,37 @@
 //! Linux implementation using evdev.
 
+use crate::channel::{self, Receiver, Sender};
+use crate::error::HotkeyError;
+use crate::evemu::{read_evemu, start_replay, EvemuEvent};
 use crate::event::HotkeyEvent;
-use crate::hotkey::{Hotkey, Modifiers};
+use crate::hotkey::{Hotkey, ModifierSide};
 use crate::key::Key;
+use crate::keyboard::{BusType, KeyboardGroup, KeyboardInfo};
+use crate::listener::{
+    Backend, DeviceFilter, ListenerConfig, DEFAULT_RECONNECT_SETTLE, DEFAULT_RESCAN_INTERVAL,
+};
+use crate::matcher::{HotkeyMatcher, Modifier};
 use anyhow::{anyhow, Context, Result};
 use evdev::Device;
+use nix::errno::Errno;
 use nix::fcntl::{fcntl, FcntlArg, OFlag};
-use std::collections::HashSet;
-use std::os::fd::AsRawFd;
-use std::path::PathBuf;
+use nix::sys::epoll::{Epoll, EpollCreateFlags, EpollEvent, EpollFlags, EpollTimeout};
+use std::collections::{HashMap, HashSet};
+use std::os::fd::{AsRawFd, BorrowedFd, RawFd};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
+#[cfg(feature = "raw-evdev")]
+use std::sync::Mutex;
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 /// Convert our platform-agnostic Key to evdev Key.
-fn to_evdev_key(key: Key) -> evdev::Key {
+///
+/// PrintScreen is `KEY_SYSRQ`, which is what PC keyboards send for it;
+/// `KEY_PRINT` is only an alternate code. Likewise Menu is `KEY_COMPOSE`,
+/// with `KEY_MENU` as the alternate.
+pub(crate) fn to_evdev_key(key: Key) -> evdev::Key {
     match key {
         Key::F1 => evdev::Key::KEY_F1,
         Key::F2 => evdev::Key::KEY_F2,
@@ -30,53 +45,660 @@ fn to_evdev_key(key: Key) -> evdev::Key {
         Key::F10 => evdev::Key::KEY_F10,
         Key::F11 => evdev::Key::KEY_F11,
         Key::F12 => evdev::Key::KEY_F12,
+        Key::Escape => evdev::Key::KEY_ESC,
+        Key::PrintScreen => evdev::Key::KEY_SYSRQ,
         Key::ScrollLock => evdev::Key::KEY_SCROLLLOCK,
         Key::Pause => evdev::Key::KEY_PAUSE,
         Key::Insert => evdev::Key::KEY_INSERT,
+        Key::Menu => evdev::Key::KEY_COMPOSE,
+        Key::Up => evdev::Key::KEY_UP,
+        Key::Down => evdev::Key::KEY_DOWN,
+        Key::Left => evdev::Key::KEY_LEFT,
+        Key::Right => evdev::Key::KEY_RIGHT,
+        Key::Home => evdev::Key::KEY_HOME,
+        Key::End => evdev::Key::KEY_END,
+        Key::PageUp => evdev::Key::KEY_PAGEUP,
+        Key::PageDown => evdev::Key::KEY_PAGEDOWN,
+        Key::Delete => evdev::Key::KEY_DELETE,
+        Key::Numpad0 => evdev::Key::KEY_KP0,
+        Key::Numpad1 => evdev::Key::KEY_KP1,
+        Key::Numpad2 => evdev::Key::KEY_KP2,
+        Key::Numpad3 => evdev::Key::KEY_KP3,
+        Key::Numpad4 => evdev::Key::KEY_KP4,
+        Key::Numpad5 => evdev::Key::KEY_KP5,
+        Key::Numpad6 => evdev::Key::KEY_KP6,
+        Key::Numpad7 => evdev::Key::KEY_KP7,
+        Key::Numpad8 => evdev::Key::KEY_KP8,
+        Key::Numpad9 => evdev::Key::KEY_KP9,
+        Key::NumpadEnter => evdev::Key::KEY_KPENTER,
+        Key::NumpadPlus => evdev::Key::KEY_KPPLUS,
+        Key::NumpadMinus => evdev::Key::KEY_KPMINUS,
+        Key::NumpadMultiply => evdev::Key::KEY_KPASTERISK,
+        Key::NumpadDivide => evdev::Key::KEY_KPSLASH,
+        Key::A => evdev::Key::KEY_A,
+        Key::B => evdev::Key::KEY_B,
+        Key::C => evdev::Key::KEY_C,
+        Key::D => evdev::Key::KEY_D,
+        Key::E => evdev::Key::KEY_E,
+        Key::F => evdev::Key::KEY_F,
+        Key::G => evdev::Key::KEY_G,
+        Key::H => evdev::Key::KEY_H,
+        Key::I => evdev::Key::KEY_I,
+        Key::J => evdev::Key::KEY_J,
+        Key::K => evdev::Key::KEY_K,
+        Key::L => evdev::Key::KEY_L,
+        Key::M => evdev::Key::KEY_M,
+        Key::N => evdev::Key::KEY_N,
+        Key::O => evdev::Key::KEY_O,
+        Key::P => evdev::Key::KEY_P,
+        Key::Q => evdev::Key::KEY_Q,
+        Key::R => evdev::Key::KEY_R,
+        Key::S => evdev::Key::KEY_S,
+        Key::T => evdev::Key::KEY_T,
+        Key::U => evdev::Key::KEY_U,
+        Key::V => evdev::Key::KEY_V,
+        Key::W => evdev::Key::KEY_W,
+        Key::X => evdev::Key::KEY_X,
+        Key::Y => evdev::Key::KEY_Y,
+        Key::Z => evdev::Key::KEY_Z,
+        Key::Digit0 => evdev::Key::KEY_0,
+        Key::Digit1 => evdev::Key::KEY_1,
+        Key::Digit2 => evdev::Key::KEY_2,
+        Key::Digit3 => evdev::Key::KEY_3,
+        Key::Digit4 => evdev::Key::KEY_4,
+        Key::Digit5 => evdev::Key::KEY_5,
+        Key::Digit6 => evdev::Key::KEY_6,
+        Key::Digit7 => evdev::Key::KEY_7,
+        Key::Digit8 => evdev::Key::KEY_8,
+        Key::Digit9 => evdev::Key::KEY_9,
+        Key::Raw(code) => evdev::Key::new(code),
+    }
+}
+
+/// Alternate evdev codes that some keyboards report for a key.
+///
+/// Best-effort table of firmware quirks, consulted only when
+/// [`ListenerConfig::alternate_codes`] is enabled.
+fn alternate_evdev_keys(key: Key) -> &'static [evdev::Key] {
+    match key {
+        Key::Pause => &[evdev::Key::KEY_BREAK, evdev::Key::KEY_PAUSECD],
+        Key::PrintScreen => &[evdev::Key::KEY_PRINT],
+        Key::Menu => &[evdev::Key::KEY_MENU],
+        _ => &[],
+    }
+}
+
+/// Convert an evdev key back to our platform-agnostic Key, if it has one.
+fn from_evdev_key(key: evdev::Key, alternate_codes: bool) -> Option<Key> {
+    Key::ALL
+        .iter()
+        .copied()
+        .find(|k| to_evdev_key(*k) == key)
+        .or_else(|| {
+            if !alternate_codes {
+                return None;
+            }
+            Key::ALL
+                .iter()
+                .copied()
+                .find(|k| alternate_evdev_keys(*k).contains(&key))
+        })
+}
+
+/// Convert an evdev modifier key to the modifier it controls and its side.
+fn to_modifier(key: evdev::Key) -> Option<(Modifier, ModifierSide)> {
+    use ModifierSide::{Left, Right};
+    match key {
+        evdev::Key::KEY_LEFTSHIFT => Some((Modifier::Shift, Left)),
+        evdev::Key::KEY_RIGHTSHIFT => Some((Modifier::Shift, Right)),
+        evdev::Key::KEY_LEFTCTRL => Some((Modifier::Ctrl, Left)),
+        evdev::Key::KEY_RIGHTCTRL => Some((Modifier::Ctrl, Right)),
+        evdev::Key::KEY_LEFTALT => Some((Modifier::Alt, Left)),
+        evdev::Key::KEY_RIGHTALT => Some((Modifier::Alt, Right)),
+        evdev::Key::KEY_LEFTMETA => Some((Modifier::Meta, Left)),
+        evdev::Key::KEY_RIGHTMETA => Some((Modifier::Meta, Right)),
+        _ => None,
+    }
+}
+
+/// The kernel's timestamp for `event`, or `None` for synthesized events,
+/// which have it zeroed.
+fn kernel_time(event: &evdev::InputEvent) -> Option<SystemTime> {
+    let time = event.timestamp();
+    (time != SystemTime::UNIX_EPOCH).then_some(time)
+}
+
+/// Move each modifier press ahead of the key presses it follows by at most
+/// `window`, so a combo whose modifier the keyboard reported just after the
+/// key still matches. Releases and other keys' events are never crossed.
+fn apply_late_modifiers_first(events: &mut [evdev::InputEvent], window: Duration) {
+    for i in 0..events.len() {
+        let event = events[i];
+        let is_modifier_press = matches!(event.kind(), evdev::InputEventKind::Key(key)
+            if event.value() == 1 && to_modifier(key).is_some());
+        if !is_modifier_press {
+            continue;
+        }
+        let mut first_key = None;
+        for j in (0..i).rev() {
+            let earlier = events[j];
+            match earlier.kind() {
+                evdev::InputEventKind::Synchronization(_) | evdev::InputEventKind::Misc(_) => {}
+                evdev::InputEventKind::Key(key)
+                    if earlier.value() == 1
+                        && to_modifier(key).is_none()
+                        && event
+                            .timestamp()
+                            .duration_since(earlier.timestamp())
+                            .map_or(true, |gap| gap <= window) =>
+                {
+                    first_key = Some(j);
+                }
+                _ => break,
+            }
+        }
+        if let Some(j) = first_key {
+            events[j..=i].rotate_right(1);
+        }
+    }
+}
+
+#[cfg(feature = "raw-evdev")]
+type RawEvdevFn = dyn Fn(&evdev::InputEvent) + Send;
+
+/// Callback receiving every raw evdev event before matching.
+#[cfg(feature = "raw-evdev")]
+#[derive(Clone)]
+pub(crate) struct RawEvdevCallback(Arc<Mutex<RawEvdevFn>>);
+
+#[cfg(feature = "raw-evdev")]
+impl RawEvdevCallback {
+    pub fn new(callback: impl Fn(&evdev::InputEvent) + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(callback)))
+    }
+
+    fn call(&self, event: &evdev::InputEvent) {
+        if let Ok(callback) = self.0.lock() {
+            callback(event);
+        }
+    }
+}
+
+#[cfg(feature = "raw-evdev")]
+impl std::fmt::Debug for RawEvdevCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RawEvdevCallback(..)")
+    }
+}
+
+/// Translates evdev events and feeds them through the hotkey matcher.
+pub(crate) struct EventProcessor {
+    matcher: HotkeyMatcher,
+    config: ListenerConfig,
+    /// Keys currently held down, for the ghosting guard.
+    held_keys: HashSet<evdev::Key>,
+    /// Held keys whose press was suppressed as likely ghosting.
+    ghosted_keys: HashSet<evdev::Key>,
+}
+
+impl EventProcessor {
+    pub fn new(hotkeys: Vec<Hotkey>, config: ListenerConfig) -> Self {
+        Self {
+            matcher: HotkeyMatcher::new(hotkeys, config.clone()),
+            config,
+            held_keys: HashSet::new(),
+            ghosted_keys: HashSet::new(),
+        }
+    }
+
+    /// Process the events read from a keyboard in one go, returning any
+    /// hotkey events.
+    ///
+    /// With [`modifier_lookahead`](ListenerConfig::modifier_lookahead) set,
+    /// modifier presses trailing a key press in the batch are applied first.
+    pub fn process_batch(&mut self, mut events: Vec<evdev::InputEvent>) -> Vec<HotkeyEvent> {
+        if let Some(window) = self.config.modifier_lookahead {
+            apply_late_modifiers_first(&mut events, window);
+        }
+        events
+            .iter()
+            .flat_map(|event| self.process(event))
+            .collect()
+    }
+
+    /// Process a single evdev event, returning any hotkey events.
+    pub fn process(&mut self, event: &evdev::InputEvent) -> Vec<HotkeyEvent> {
+        self.matcher.set_event_time(kernel_time(event));
+
+        #[cfg(feature = "raw-evdev")]
+        if let Some(callback) = &self.config.raw_callback {
+            callback.call(event);
+        }
+
+        let key = match event.kind() {
+            evdev::InputEventKind::Key(key) => key,
+            evdev::InputEventKind::Led(evdev::LedType::LED_NUML) => {
+                self.matcher.set_num_lock(event.value() != 0);
+                return Vec::new();
+            }
+            evdev::InputEventKind::Led(evdev::LedType::LED_CAPSL) => {
+                self.matcher.set_caps_lock(event.value() != 0);
+                return Vec::new();
+            }
+            _ => return Vec::new(),
+        };
+        let pressed = event.value() == 1;
+        let released = event.value() == 0;
+
+        if self.is_ghosted(key, pressed, released) {
+            return Vec::new();
+        }
+
+        // Auto-repeat (value 2) leaves modifier state untouched
+        if let Some((modifier, side)) = to_modifier(key) {
+            if pressed || released {
+                return self
+                    .matcher
+                    .on_modifier(modifier, side, pressed, Instant::now());
+            }
+            return Vec::new();
+        }
+
+        // Raw keycodes are only matched when bound, so other keys still
+        // interrupt taps and sequences
+        let raw = Key::Raw(key.code());
+        let key = from_evdev_key(key, self.config.alternate_codes)
+            .or_else(|| self.matcher.binds(raw).then_some(raw));
+        match key {
+            Some(key) if pressed => self.matcher.on_key_down(key, Instant::now()),
+            Some(key) if released => self.matcher.on_key_up(key),
+            Some(key) => self.matcher.on_key_repeat(key),
+            None if pressed => {
+                self.matcher.on_other_key();
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Record the NumLock state read from a keyboard's LEDs.
+    pub fn set_num_lock(&mut self, on: bool) {
+        self.matcher.set_num_lock(on);
+    }
+
+    /// Attribute the events processed from now on to the keyboard at `device`.
+    pub fn set_device(&mut self, device: Option<String>) {
+        self.matcher.set_event_device(device);
+    }
+
+    /// Record the CapsLock state read from a keyboard's LEDs.
+    pub fn set_caps_lock(&mut self, on: bool) {
+        self.matcher.set_caps_lock(on);
+    }
+
+    /// Match the key events injected through the handle.
+    pub fn process_injected(&mut self) -> Vec<HotkeyEvent> {
+        self.matcher.set_event_time(None);
+        self.matcher.set_event_device(None);
+        let mut hotkey_events = Vec::new();
+        for (key, pressed) in self.config.shared.injector.drain() {
+            hotkey_events.extend(if pressed {
+                self.matcher.on_key_down(key, Instant::now())
+            } else {
+                self.matcher.on_key_up(key)
+            });
+        }
+        hotkey_events
+    }
+
+    /// Release hotkeys held past the configured maximum hold time.
+    pub fn release_stuck(&mut self) -> Vec<HotkeyEvent> {
+        self.matcher.set_event_time(None);
+        self.matcher.set_event_device(None);
+        self.matcher.release_stuck(Instant::now())
+    }
+
+    /// Emit `LongPressed` for hotkeys held past their long-press threshold.
+    pub fn poll_long_presses(&mut self) -> Vec<HotkeyEvent> {
+        self.matcher.set_event_time(None);
+        self.matcher.set_event_device(None);
+        self.matcher.poll_long_presses(Instant::now())
+    }
+
+    /// When [`poll_long_presses`](Self::poll_long_presses) next has something to emit.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.matcher.next_long_press()
+    }
+
+    /// Forget all held modifiers, e.g. after the keyboards were reconnected.
+    pub fn reset(&mut self) {
+        self.matcher.reset();
+        self.held_keys.clear();
+        self.ghosted_keys.clear();
+    }
+
+    /// Track held keys and decide whether this event looks like a ghosting artifact.
+    ///
+    /// A press that would exceed the configured number of simultaneously held
+    /// keys is treated as a phantom, and so is its matching release.
+    fn is_ghosted(&mut self, key: evdev::Key, pressed: bool, released: bool) -> bool {
+        let Some(max_keys) = self.config.max_simultaneous_keys else {
+            return false;
+        };
+
+        if pressed {
+            if self.held_keys.len() >= max_keys {
+                log::debug!(
+                    "Suppressing {:?}: {} keys already held, likely ghosting",
+                    key,
+                    self.held_keys.len()
+                );
+                self.ghosted_keys.insert(key);
+                return true;
+            }
+            self.held_keys.insert(key);
+        } else if released {
+            self.held_keys.remove(&key);
+            return self.ghosted_keys.remove(&key);
+        }
+        self.ghosted_keys.contains(&key)
     }
 }
 
 /// Find all keyboard devices in /dev/input.
 pub fn find_keyboards() -> Result<Vec<Device>> {
-    let mut keyboards = Vec::new();
+    find_selected_keyboards(None, None, KeyboardGroup::All, None, true)
+}
 
+/// The device nodes to look for keyboards in: `explicit` if the builder was
+/// given a list, otherwise the event nodes in /dev/input.
+fn candidate_paths(explicit: Option<&[PathBuf]>) -> std::io::Result<Vec<PathBuf>> {
+    if let Some(paths) = explicit {
+        return Ok(paths.to_vec());
+    }
+    let mut paths = Vec::new();
     for entry in std::fs::read_dir("/dev/input")? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if !path
+        let path = entry?.path();
+        if path
             .file_name()
             .and_then(|n| n.to_str())
-            .map(|n| n.starts_with("event"))
-            .unwrap_or(false)
+            .is_some_and(|n| n.starts_with("event"))
         {
-            continue;
+            paths.push(path);
         }
+    }
+    Ok(paths)
+}
 
-        if let Ok(device) = Device::open(&path) {
+/// Open the devices at `paths` that have keyboard keys.
+///
+/// A path that can't be opened or isn't a keyboard doesn't stop the others;
+/// it's returned as a message naming the path and the reason instead.
+fn open_keyboards(paths: Vec<PathBuf>) -> (Vec<(PathBuf, Device)>, Vec<String>) {
+    let mut keyboards = Vec::new();
+    let mut failures = Vec::new();
+    for path in paths {
+        match Device::open(&path) {
             // Check if device supports keyboard keys
-            if device
-                .supported_keys()
-                .map(|keys| keys.contains(evdev::Key::KEY_A))
-                .unwrap_or(false)
+            Ok(device)
+                if device
+                    .supported_keys()
+                    .is_some_and(|keys| keys.contains(evdev::Key::KEY_A)) =>
             {
                 log::debug!("Found keyboard: {:?} at {:?}", device.name(), path);
-                keyboards.push(device);
+                keyboards.push((path, device));
             }
+            Ok(_) => failures.push(format!("{}: not a keyboard", path.display())),
+            Err(e) => failures.push(format!("{}: {}", path.display(), e)),
         }
     }
+    (keyboards, failures)
+}
+
+/// Find keyboard devices at `paths`, or in /dev/input if `None`, keeping
+/// only `pinned` if given, those in `group` and those whose name passes
+/// `filter`, and one node per physical keyboard if `dedupe` is set.
+pub(crate) fn find_selected_keyboards(
+    paths: Option<&[PathBuf]>,
+    pinned: Option<&Path>,
+    group: KeyboardGroup,
+    filter: Option<&DeviceFilter>,
+    dedupe: bool,
+) -> Result<Vec<Device>> {
+    let (mut keyboards, failures) = open_keyboards(candidate_paths(paths)?);
+    // Only paths the user listed are worth a warning; /dev/input is full of
+    // mice and other devices that aren't keyboards
+    if paths.is_some() {
+        for failure in &failures {
+            log::warn!("Skipping device {}", failure);
+        }
+    }
+    retain_pinned(&mut keyboards, pinned);
+    retain_group(&mut keyboards, group, device_bus);
+    retain_named(&mut keyboards, filter, device_name);
+    if dedupe {
+        retain_distinct(&mut keyboards, Device::physical_path, device_rank);
+    }
 
     if keyboards.is_empty() {
-        Err(anyhow!(
-            "No keyboards found. Make sure you're in the 'input' group or running as root."
-        ))
+        match pinned {
+            Some(path) => Err(anyhow!(
+                "Keyboard {:?} not found in group {:?}",
+                path,
+                group
+            )),
+            None if group != KeyboardGroup::All => {
+                Err(anyhow!("No keyboards found in group {:?}", group))
+            }
+            None if filter.is_some() => Err(anyhow!("No keyboards match the device filter")),
+            None if paths.is_some() => Err(anyhow!(
+                "No keyboards found at the given device paths: {}",
+                failures.join("; ")
+            )),
+            None => Err(anyhow!(
+                "No keyboards found. Make sure you're in the 'input' group or running as root."
+            )),
+        }
     } else {
-        Ok(keyboards)
+        Ok(keyboards.into_iter().map(|(_, device)| device).collect())
+    }
+}
+
+/// Keep only the device at `pinned`, if the listener was restricted to one.
+fn retain_pinned<D>(devices: &mut Vec<(PathBuf, D)>, pinned: Option<&Path>) {
+    if let Some(pinned) = pinned {
+        devices.retain(|(path, _)| path == pinned);
+    }
+}
+
+/// Keep only the devices in `group`, classified by the bus `bus` reports.
+fn retain_group<D>(
+    devices: &mut Vec<(PathBuf, D)>,
+    group: KeyboardGroup,
+    bus: impl Fn(&D) -> BusType,
+) {
+    devices.retain(|(path, device)| {
+        let keep = group.contains(bus(device));
+        if !keep {
+            log::debug!("Ignoring keyboard at {:?}, not in group {:?}", path, group);
+        }
+        keep
+    });
+}
+
+/// Keep only the devices whose name, as reported by `name`, passes `filter`.
+fn retain_named<D>(
+    devices: &mut Vec<(PathBuf, D)>,
+    filter: Option<&DeviceFilter>,
+    name: impl Fn(&D) -> &str,
+) {
+    let Some(filter) = filter else {
+        return;
+    };
+    devices.retain(|(path, device)| {
+        let keep = filter.matches(name(device));
+        if keep {
+            log::debug!(
+                "Keyboard {:?} at {:?} matches device filter",
+                name(device),
+                path
+            );
+        } else {
+            log::debug!(
+                "Ignoring keyboard {:?} at {:?}, filtered by name",
+                name(device),
+                path
+            );
+        }
+        keep
+    });
+}
+
+/// Keep one event node per physical keyboard, so a press isn't read twice.
+///
+/// Laptops and some USB keyboards expose several nodes for one keyboard,
+/// which share the physical path `phys` reports. Of those, the one ranked
+/// highest by `rank` is kept, the first on ties. Devices without a physical
+/// path are all kept.
+fn retain_distinct<D, R: Ord>(
+    devices: &mut Vec<(PathBuf, D)>,
+    phys: impl Fn(&D) -> Option<&str>,
+    rank: impl Fn(&D) -> R,
+) {
+    let physs: Vec<Option<String>> = devices
+        .iter()
+        .map(|(_, device)| {
+            phys(device)
+                .filter(|phys| !phys.is_empty())
+                .map(String::from)
+        })
+        .collect();
+    let mut best: HashMap<&str, usize> = HashMap::new();
+    for (idx, phys) in physs.iter().enumerate() {
+        if let Some(phys) = phys {
+            let best_idx = best.entry(phys).or_insert(idx);
+            if rank(&devices[idx].1) > rank(&devices[*best_idx].1) {
+                *best_idx = idx;
+            }
+        }
+    }
+    let mut keep = physs
+        .iter()
+        .enumerate()
+        .map(|(idx, phys)| phys.as_deref().is_none_or(|phys| best[phys] == idx))
+        .collect::<Vec<bool>>()
+        .into_iter();
+    devices.retain(|(path, device)| {
+        let keep = keep.next().unwrap_or(true);
+        if !keep {
+            log::debug!(
+                "Ignoring {:?} at {:?}, another node of the same keyboard",
+                phys(device),
+                path
+            );
+        }
+        keep
+    });
+}
+
+/// How likely a device is to be the real keyboard among nodes sharing a
+/// physical path: one with Escape first, then the one with the most keys.
+fn device_rank(device: &Device) -> (bool, usize) {
+    device.supported_keys().map_or((false, 0), |keys| {
+        (keys.contains(evdev::Key::KEY_ESC), keys.iter().count())
+    })
+}
+
+/// The name an evdev device reports, or "unknown".
+fn device_name(device: &Device) -> &str {
+    device.name().unwrap_or("unknown")
+}
+
+/// The bus an evdev device is connected through.
+fn device_bus(device: &Device) -> BusType {
+    BusType::from_raw(device.input_id().bus_type().0)
+}
+
+// EVIOCGBIT(EV_KEY, ...): the device's current key capability bitmap
+const KEY_CNT: usize = 0x300;
+nix::ioctl_read_buf!(eviocgbit_key, b'E', 0x20 + evdev::EventType::KEY.0, u8);
+
+/// Key capabilities of an open device, queried fresh from the kernel.
+///
+/// `Device::supported_keys` is cached when the device is opened, so it can't
+/// notice composite devices that renegotiate and stop reporting keys.
+pub(crate) trait KeyCapabilities {
+    fn reports_keyboard_keys(&self) -> bool;
+}
+
+impl KeyCapabilities for Device {
+    fn reports_keyboard_keys(&self) -> bool {
+        let mut bits = [0u8; KEY_CNT / 8];
+        // SAFETY: the fd is owned by the device and the buffer length is passed to the kernel.
+        if unsafe { eviocgbit_key(self.as_raw_fd(), &mut bits) }.is_err() {
+            return false;
+        }
+        let code = evdev::Key::KEY_A.code() as usize;
+        bits[code / 8] & (1 << (code % 8)) != 0
+    }
+}
+
+/// Drop devices that no longer report keyboard keys, returning how many were dropped.
+fn retain_keyboards<D: KeyCapabilities>(devices: &mut Vec<D>) -> usize {
+    let before = devices.len();
+    devices.retain(|device| device.reports_keyboard_keys());
+    before - devices.len()
+}
+
+/// Describe a keyboard device without exposing evdev types.
+fn keyboard_info(path: PathBuf, device: &Device) -> KeyboardInfo {
+    reported_keyboard_info(
+        path,
+        device.name(),
+        device.physical_path(),
+        device.input_id(),
+        device
+            .supported_keys()
+            .map_or(0, |keys| keys.iter().count()),
+    )
+}
+
+/// Build a `KeyboardInfo` from the fields a device reports.
+fn reported_keyboard_info(
+    path: PathBuf,
+    name: Option<&str>,
+    physical_path: Option<&str>,
+    id: evdev::InputId,
+    key_count: usize,
+) -> KeyboardInfo {
+    KeyboardInfo {
+        name: name.unwrap_or("unknown").to_string(),
+        path,
+        physical_path: physical_path.map(String::from),
+        bus_type: BusType::from_raw(id.bus_type().0),
+        vendor: id.vendor(),
+        product: id.product(),
+        key_count,
     }
 }
 
+/// List the keyboards currently attached, for display to the user.
+pub fn list_keyboards() -> Vec<KeyboardInfo> {
+    let Ok(paths) = candidate_paths(None) else {
+        return Vec::new();
+    };
+    let (mut devices, _) = open_keyboards(paths);
+    retain_distinct(&mut devices, Device::physical_path, device_rank);
+    let mut keyboards: Vec<KeyboardInfo> = devices
+        .into_iter()
+        .map(|(path, device)| keyboard_info(path, &device))
+        .collect();
+    keyboards.sort_by(|a, b| a.path.cmp(&b.path));
+    keyboards
+}
+
 /// Set non-blocking mode on keyboard devices.
-fn set_nonblocking(keyboards: &[Device]) -> Result<()> {
+pub(crate) fn set_nonblocking(keyboards: &[Device]) -> Result<()> {
     for device in keyboards {
         let fd = device.as_raw_fd();
         let flags = fcntl(fd, FcntlArg::F_GETFL).context("Failed to get fd flags")?;
@@ -86,15 +708,149 @@ fn set_nonblocking(keyboards: &[Device]) -> Result<()> {
     Ok(())
 }
 
+/// How long `verify_devices` polls each device.
+const VERIFY_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// A device whose file descriptor can be checked before listening.
+trait Pollable: AsRawFd {
+    fn label(&self) -> Option<String>;
+    /// Take an exclusive grab (EVIOCGRAB) on the device.
+    fn grab(&mut self) -> std::io::Result<()>;
+    fn ungrab(&mut self) -> std::io::Result<()>;
+}
+
+impl Pollable for Device {
+    fn label(&self) -> Option<String> {
+        self.name().map(String::from)
+    }
+
+    fn grab(&mut self) -> std::io::Result<()> {
+        Device::grab(self)
+    }
+
+    fn ungrab(&mut self) -> std::io::Result<()> {
+        Device::ungrab(self)
+    }
+}
+
+/// Check whether another process holds an exclusive grab on the device.
+///
+/// While grabbed, none of the device's events reach us. There's no way to
+/// query a grab, so try taking one: the kernel refuses with EBUSY if
+/// someone else holds it, and otherwise ours is released right away.
+fn grabbed_elsewhere<D: Pollable>(device: &mut D) -> bool {
+    match device.grab() {
+        Ok(()) => {
+            if let Err(e) = device.ungrab() {
+                log::warn!(
+                    "Failed to release grab check on {:?}: {}",
+                    device.label(),
+                    e
+                );
+            }
+            false
+        }
+        Err(e) => e.raw_os_error() == Some(libc::EBUSY),
+    }
+}
+
+/// Poll a device once, failing if it reports an error condition.
+///
+/// A device with no pending input simply times out, which is fine.
+fn poll_device<D: Pollable>(device: &D, timeout: Duration) -> std::io::Result<()> {
+    let mut fd = libc::pollfd {
+        fd: device.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+    // SAFETY: `fd` is a single valid pollfd that outlives the call.
+    if unsafe { libc::poll(&mut fd, 1, timeout) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    if fd.revents & libc::POLLNVAL != 0 {
+        return Err(std::io::Error::other("invalid file descriptor"));
+    }
+    if fd.revents & (libc::POLLERR | libc::POLLHUP) != 0 {
+        return Err(std::io::Error::other("device reported an error or hang-up"));
+    }
+    Ok(())
+}
+
+/// Labels of the devices that failed `verify_devices`.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct DeviceCheck {
+    /// Devices reporting an error when polled.
+    unreadable: Vec<String>,
+    /// Devices another process holds an exclusive grab on.
+    grabbed: Vec<String>,
+}
+
+/// Poll each device and check for grabs, logging the ones that can't
+/// deliver events.
+fn verify_devices<D: Pollable>(devices: &mut [D], timeout: Duration) -> DeviceCheck {
+    let mut check = DeviceCheck::default();
+    for device in devices {
+        let label = device
+            .label()
+            .unwrap_or_else(|| "unnamed device".to_string());
+        if let Err(e) = poll_device(device, timeout) {
+            log::warn!("Keyboard {} may not deliver events: {}", label, e);
+            check.unreadable.push(label);
+        } else if grabbed_elsewhere(device) {
+            log::warn!(
+                "Keyboard {} is grabbed by another process (e.g. a screen locker \
+                 or key remapper), so hotkeys on it won't be seen",
+                label
+            );
+            check.grabbed.push(label);
+        } else {
+            log::debug!("Verified {} is readable", label);
+        }
+    }
+    check
+}
+
+/// Longest time spent draining a single device before giving up.
+///
+/// A device producing a steady stream of events (e.g. a held key) would
+/// otherwise never report EAGAIN and stall the listener.
+const DRAIN_BUDGET: Duration = Duration::from_millis(50);
+
+/// A source of buffered events that can be discarded.
+pub(crate) trait StaleEvents {
+    fn label(&self) -> Option<String>;
+    /// Read and discard one batch of events, returning how many there were.
+    fn discard_batch(&mut self) -> std::io::Result<usize>;
+}
+
+impl StaleEvents for Device {
+    fn label(&self) -> Option<String> {
+        self.name().map(String::from)
+    }
+
+    fn discard_batch(&mut self) -> std::io::Result<usize> {
+        self.fetch_events().map(|events| events.count())
+    }
+}
+
 /// Drain any stale events from keyboards and verify they're readable.
 /// This is especially important for Bluetooth keyboards after reconnection.
-fn drain_events(keyboards: &mut [Device]) {
+fn drain_events<D: StaleEvents>(keyboards: &mut [D], budget: Duration) {
     for device in keyboards.iter_mut() {
-        let device_name = device.name().map(String::from);
+        let device_name = device.label();
+        let started = Instant::now();
         loop {
-            match device.fetch_events() {
-                Ok(events) => {
-                    let count = events.count();
+            if started.elapsed() >= budget {
+                log::debug!(
+                    "Gave up draining {:?} after {:?}, events keep arriving",
+                    device_name,
+                    budget
+                );
+                break;
+            }
+            match device.discard_batch() {
+                Ok(count) => {
                     if count == 0 {
                         break;
                     }
@@ -116,276 +872,1842 @@ fn drain_events(keyboards: &mut [Device]) {
     }
 }
 
-/// Get the set of device paths for currently detectable keyboards in /dev/input.
-fn get_keyboard_paths() -> HashSet<PathBuf> {
-    let mut paths = HashSet::new();
-    let Ok(entries) = std::fs::read_dir("/dev/input") else {
-        return paths;
+/// Get the set of device paths for currently detectable keyboards at
+/// `paths`, or in /dev/input if `None`.
+fn get_keyboard_paths(paths: Option<&[PathBuf]>) -> HashSet<PathBuf> {
+    let Ok(paths) = candidate_paths(paths) else {
+        return HashSet::new();
     };
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if !path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .map(|n| n.starts_with("event"))
-            .unwrap_or(false)
-        {
-            continue;
-        }
-        if let Ok(device) = Device::open(&path) {
-            if device
-                .supported_keys()
-                .map(|keys| keys.contains(evdev::Key::KEY_A))
-                .unwrap_or(false)
-            {
-                paths.insert(path);
-            }
-        }
-    }
-    paths
+    let (keyboards, _) = open_keyboards(paths);
+    keyboards.into_iter().map(|(path, _)| path).collect()
 }
 
 /// Find keyboard devices at paths not in the known set.
-fn find_new_keyboards(known_paths: &HashSet<PathBuf>) -> Vec<(PathBuf, Device)> {
-    let mut new_keyboards = Vec::new();
-    let Ok(entries) = std::fs::read_dir("/dev/input") else {
-        return new_keyboards;
+fn find_new_keyboards(
+    known_paths: &HashSet<PathBuf>,
+    paths: Option<&[PathBuf]>,
+    pinned: Option<&Path>,
+    group: KeyboardGroup,
+    filter: Option<&DeviceFilter>,
+    dedupe: bool,
+) -> Vec<(PathBuf, Device)> {
+    let Ok(mut paths) = candidate_paths(paths) else {
+        return Vec::new();
     };
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if !path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .map(|n| n.starts_with("event"))
-            .unwrap_or(false)
-        {
-            continue;
-        }
-        if known_paths.contains(&path) {
-            continue;
-        }
-        if let Ok(device) = Device::open(&path) {
-            if device
-                .supported_keys()
-                .map(|keys| keys.contains(evdev::Key::KEY_A))
-                .unwrap_or(false)
-            {
-                log::debug!("Found new keyboard: {:?} at {:?}", device.name(), path);
-                new_keyboards.push((path, device));
-            }
-        }
+    paths.retain(|path| !known_paths.contains(path));
+    let (mut new_keyboards, _) = open_keyboards(paths);
+    retain_pinned(&mut new_keyboards, pinned);
+    retain_group(&mut new_keyboards, group, device_bus);
+    retain_named(&mut new_keyboards, filter, device_name);
+    if dedupe {
+        retain_distinct(&mut new_keyboards, Device::physical_path, device_rank);
     }
     new_keyboards
 }
 
-/// Linux hotkey listener using evdev.
-pub struct HotkeyListener {
-    keyboards: Vec<Device>,
-    hotkeys: Vec<Hotkey>,
+/// Tracks consecutive failed reconnect attempts.
+struct ReconnectPolicy {
+    max_attempts: Option<u32>,
+    failures: u32,
 }
 
-impl HotkeyListener {
-    /// Create a new listener with the given keyboards and hotkeys.
-    pub fn new(keyboards: Vec<Device>, hotkeys: Vec<Hotkey>) -> Self {
-        Self { keyboards, hotkeys }
+/// Result of a single keyboard rescan attempt.
+enum RescanOutcome<T> {
+    Reconnected(Vec<T>),
+    Failed,
+    GaveUp,
+}
+
+impl ReconnectPolicy {
+    fn new(max_attempts: Option<u32>) -> Self {
+        Self {
+            max_attempts,
+            failures: 0,
+        }
     }
 
-    /// Start listening for hotkeys in a background thread.
-    /// Returns a receiver for hotkey events.
-    pub fn start(self, running: Arc<AtomicBool>) -> Result<Receiver<HotkeyEvent>> {
-        let (tx, rx) = mpsc::channel();
-        set_nonblocking(&self.keyboards)?;
-        start_keyboard_listener(self.keyboards, self.hotkeys, running, tx)?;
-        Ok(rx)
+    fn on_success(&mut self) {
+        self.failures = 0;
     }
-}
 
-fn start_keyboard_listener(
-    keyboards: Vec<Device>,
-    hotkeys: Vec<Hotkey>,
-    running: Arc<AtomicBool>,
-    tx: Sender<HotkeyEvent>,
-) -> Result<()> {
-    // Convert hotkeys to evdev keys
-    let evdev_hotkeys: Vec<(evdev::Key, Modifiers)> = hotkeys
-        .iter()
-        .map(|h| (to_evdev_key(h.key), h.modifiers))
-        .collect();
+    /// Whether to rescan now, given the time since the last attempt.
+    ///
+    /// The first attempt after an error happens immediately so a quick replug
+    /// reconnects near-instantly; later attempts wait for `interval`.
+    fn rescan_due(&self, since_last: Duration, interval: Duration) -> bool {
+        self.failures == 0 || since_last >= interval
+    }
 
-    thread::spawn(move || {
-        let mut keyboards = keyboards;
-        let mut current_mods = Modifiers::default();
-        let mut last_rescan = Instant::now();
-        let mut had_error = false;
-
-        // Track known keyboard device paths to detect newly connected devices
-        let mut known_paths: HashSet<PathBuf> = get_keyboard_paths();
-
-        // Timer for periodic new device detection (e.g., Bluetooth keyboards connecting)
-        let mut last_device_scan = Instant::now();
-        const DEVICE_SCAN_INTERVAL: Duration = Duration::from_secs(5);
-
-        // Minimum interval between keyboard rescans (shorter for better UX with BT keyboards)
-        const RESCAN_INTERVAL: Duration = Duration::from_secs(3);
-
-        while running.load(Ordering::Relaxed) {
-            // Check if we need to rescan keyboards (after error and interval passed)
-            if had_error && last_rescan.elapsed() >= RESCAN_INTERVAL {
-                log::info!("Keyboard error detected, rescanning devices...");
-                match find_keyboards() {
-                    Ok(mut new_keyboards) => {
-                        // Give devices time to fully initialize (especially important for BT keyboards)
-                        thread::sleep(Duration::from_millis(100));
-
-                        match set_nonblocking(&new_keyboards) {
-                            Ok(()) => {
-                                log::info!(
-                                    "Keyboards reconnected: found {} device(s)",
-                                    new_keyboards.len()
-                                );
-                                for kb in &new_keyboards {
-                                    log::debug!(
-                                        "  - {:?} ({})",
-                                        kb.name().unwrap_or("unknown"),
-                                        kb.physical_path().unwrap_or("no path")
-                                    );
-                                }
-                                // Drain any stale events before starting to use the keyboards
-                                drain_events(&mut new_keyboards);
-                                // Drop old keyboards explicitly before replacing
-                                keyboards.clear();
-                                keyboards = new_keyboards;
-                                current_mods = Modifiers::default();
-                                had_error = false;
-                                // Rebuild known paths and reset device scan timer
-                                known_paths = get_keyboard_paths();
-                                last_device_scan = Instant::now();
-                            }
-                            Err(e) => {
-                                log::warn!("Failed to set non-blocking on new keyboards: {}", e);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        log::warn!("Failed to rescan keyboards: {}", e);
-                    }
-                }
-                last_rescan = Instant::now();
-            }
-
-            // Periodically check for newly connected keyboards (e.g., Bluetooth)
-            if last_device_scan.elapsed() >= DEVICE_SCAN_INTERVAL {
-                let new_devices = find_new_keyboards(&known_paths);
-                if !new_devices.is_empty() {
-                    log::info!("New keyboard(s) detected: {} device(s)", new_devices.len());
-
-                    // Give devices time to fully initialize
-                    thread::sleep(Duration::from_millis(100));
-
-                    let mut paths = Vec::new();
-                    let mut devices = Vec::new();
-                    for (path, device) in new_devices {
-                        log::info!(
-                            "  - {:?} ({})",
-                            device.name().unwrap_or("unknown"),
-                            device.physical_path().unwrap_or("no path")
-                        );
-                        paths.push(path);
-                        devices.push(device);
-                    }
+    /// Record a failed attempt, returning true once the listener should give up.
+    fn on_failure(&mut self) -> bool {
+        self.failures += 1;
+        self.max_attempts.is_some_and(|max| self.failures >= max)
+    }
 
-                    match set_nonblocking(&devices) {
-                        Ok(()) => {
-                            drain_events(&mut devices);
-                            for path in paths {
-                                known_paths.insert(path);
-                            }
-                            keyboards.extend(devices);
-                        }
-                        Err(e) => {
-                            log::warn!("Failed to set non-blocking on new keyboards: {}", e);
-                        }
-                    }
-                }
-                last_device_scan = Instant::now();
-            }
-
-            let mut any_error = false;
-
-            for device in keyboards.iter_mut() {
-                match device.fetch_events() {
-                    Ok(events) => {
-                        for event in events {
-                            if let evdev::InputEventKind::Key(key) = event.kind() {
-                                let pressed = event.value() == 1;
-                                let released = event.value() == 0;
-
-                                // Track modifier state
-                                match key {
-                                    evdev::Key::KEY_LEFTSHIFT | evdev::Key::KEY_RIGHTSHIFT => {
-                                        current_mods.shift =
-                                            pressed || (!released && current_mods.shift);
-                                        if released {
-                                            current_mods.shift = false;
-                                        }
-                                    }
-                                    evdev::Key::KEY_LEFTCTRL | evdev::Key::KEY_RIGHTCTRL => {
-                                        current_mods.ctrl =
-                                            pressed || (!released && current_mods.ctrl);
-                                        if released {
-                                            current_mods.ctrl = false;
-                                        }
-                                    }
-                                    evdev::Key::KEY_LEFTALT | evdev::Key::KEY_RIGHTALT => {
-                                        current_mods.alt =
-                                            pressed || (!released && current_mods.alt);
-                                        if released {
-                                            current_mods.alt = false;
-                                        }
-                                    }
-                                    _ => {}
-                                }
-
-                                // Check each hotkey
-                                for (idx, (hotkey_key, hotkey_mods)) in
-                                    evdev_hotkeys.iter().enumerate()
-                                {
-                                    if key == *hotkey_key {
-                                        let mods_match = current_mods.shift == hotkey_mods.shift
-                                            && current_mods.ctrl == hotkey_mods.ctrl
-                                            && current_mods.alt == hotkey_mods.alt;
-
-                                        if mods_match {
-                                            if pressed {
-                                                let _ = tx.send(HotkeyEvent::Pressed(idx));
-                                            } else if released {
-                                                let _ = tx.send(HotkeyEvent::Released(idx));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        // EAGAIN/EWOULDBLOCK is expected for non-blocking reads
-                        if e.raw_os_error() != Some(libc::EAGAIN)
-                            && e.raw_os_error() != Some(libc::EWOULDBLOCK)
-                        {
-                            log::debug!("Keyboard read error: {}", e);
-                            any_error = true;
-                        }
-                    }
+    /// Run one rescan through `scan`, applying the attempt limit.
+    fn rescan<T>(&mut self, scan: impl FnOnce() -> Result<Vec<T>>) -> RescanOutcome<T> {
+        match scan() {
+            Ok(devices) => RescanOutcome::Reconnected(devices),
+            Err(e) => {
+                log::warn!("Failed to rescan keyboards: {}", e);
+                if self.on_failure() {
+                    RescanOutcome::GaveUp
+                } else {
+                    RescanOutcome::Failed
                 }
             }
+        }
+    }
+}
 
-            if any_error {
-                had_error = true;
-            }
+/// The `/dev/input` event nodes that can't be opened for lack of permission,
+/// sorted, and whether any event node could be opened.
+fn denied_event_nodes() -> (Vec<PathBuf>, bool) {
+    let Ok(paths) = candidate_paths(None) else {
+        return (Vec::new(), false);
+    };
+    let mut denied = Vec::new();
+    let mut opened = false;
+    for path in paths {
+        match std::fs::File::open(&path) {
+            Ok(_) => opened = true,
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => denied.push(path),
+            Err(_) => {}
+        }
+    }
+    denied.sort();
+    (denied, opened)
+}
+
+/// Check whether `/dev/input` event nodes exist but none of them can be opened
+/// because of missing permissions.
+pub(crate) fn input_permission_denied() -> bool {
+    let (denied, opened) = denied_event_nodes();
+    !opened && !denied.is_empty()
+}
+
+/// How the current user relates to the `input` group that owns `/dev/input`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputGroup {
+    /// The system has no `input` group.
+    Missing,
+    /// The user isn't a member.
+    NotMember,
+    /// The user was added, but this session started before that.
+    PendingLogin,
+    /// The process already runs with the group.
+    Member,
+}
 
-            thread::sleep(Duration::from_millis(10));
+impl InputGroup {
+    /// Look up the current user's membership.
+    fn detect() -> Self {
+        let Ok(Some(group)) = nix::unistd::Group::from_name("input") else {
+            return InputGroup::Missing;
+        };
+        let in_process = nix::unistd::getegid() == group.gid
+            || nix::unistd::getgroups().is_ok_and(|groups| groups.contains(&group.gid));
+        if in_process {
+            return InputGroup::Member;
         }
-    });
+        let listed = nix::unistd::User::from_uid(nix::unistd::getuid())
+            .ok()
+            .flatten()
+            .is_some_and(|user| user.gid == group.gid || group.mem.contains(&user.name));
+        if listed {
+            InputGroup::PendingLogin
+        } else {
+            InputGroup::NotMember
+        }
+    }
 
-    Ok(())
+    fn hint(self) -> &'static str {
+        match self {
+            InputGroup::Missing => {
+                "this system has no 'input' group; run as root or grant read access with a udev rule"
+            }
+            InputGroup::NotMember => {
+                "add your user to the 'input' group with `sudo usermod -aG input $USER`, then log out and back in"
+            }
+            InputGroup::PendingLogin => {
+                "your user is in the 'input' group, but this session started before that; log out and back in"
+            }
+            InputGroup::Member => {
+                "this process is in the 'input' group, so the devices' own permissions deny access"
+            }
+        }
+    }
+}
+
+/// Check that every `/dev/input` event node can be opened for reading.
+///
+/// Meant for installers and setup screens, to tell the user what to fix
+/// before starting a listener. Nothing is read from the devices.
+pub fn check_permissions() -> std::result::Result<(), HotkeyError> {
+    let (denied, _) = denied_event_nodes();
+    if denied.is_empty() {
+        return Ok(());
+    }
+    Err(permission_denied(denied, InputGroup::detect()))
+}
+
+fn permission_denied(paths: Vec<PathBuf>, group: InputGroup) -> HotkeyError {
+    HotkeyError::PermissionDenied {
+        paths,
+        hint: group.hint().to_string(),
+    }
+}
+
+/// A sandbox that hides `/dev/input` unless granted access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sandbox {
+    Flatpak,
+    Snap,
+}
+
+/// Detect whether we run under Flatpak or Snap.
+///
+/// Flatpak always provides `/.flatpak-info` inside the sandbox and snapd sets
+/// `SNAP` for confined apps; both are passed in so tests can fake them.
+fn detect_sandbox(
+    exists: impl Fn(&Path) -> bool,
+    has_var: impl Fn(&str) -> bool,
+) -> Option<Sandbox> {
+    if exists(Path::new("/.flatpak-info")) {
+        Some(Sandbox::Flatpak)
+    } else if has_var("SNAP") {
+        Some(Sandbox::Snap)
+    } else {
+        None
+    }
+}
+
+/// Explain how to grant input access when discovery failed inside a sandbox.
+pub(crate) fn with_sandbox_hint(error: anyhow::Error) -> anyhow::Error {
+    let sandbox = detect_sandbox(Path::exists, |name| std::env::var_os(name).is_some());
+    match sandbox {
+        Some(Sandbox::Flatpak) => error.context(
+            "Running inside Flatpak, where /dev/input is only visible with the \
+             --device=all permission, e.g. `flatpak override --user --device=all <app-id>`",
+        ),
+        Some(Sandbox::Snap) => error.context(
+            "Running inside a Snap, which needs the raw-input interface to read \
+             /dev/input, e.g. `snap connect <snap>:raw-input`",
+        ),
+        None => error,
+    }
+}
+
+/// Decide whether to fall back to libinput after evdev discovery found no keyboards.
+///
+/// Only a permission problem is worth retrying through the seat; if the devices
+/// simply aren't there, libinput won't find them either.
+fn use_libinput_fallback(permission_denied: bool, libinput_enabled: bool) -> bool {
+    permission_denied && libinput_enabled
+}
+
+/// Decide whether to fall back to X11 after evdev discovery failed.
+///
+/// Only when the backend wasn't forced to evdev and there's a display to
+/// connect to.
+fn use_x11_fallback(backend: Backend, display_set: bool, x11_enabled: bool) -> bool {
+    backend == Backend::Auto && display_set && x11_enabled
+}
+
+/// Where the Linux listener reads its input events from.
+enum Source {
+    /// Live keyboard devices.
+    Devices(Vec<Device>),
+    /// A recorded evemu capture.
+    Evemu(Vec<EvemuEvent>),
+    /// A virtual keyboard driven by tests.
+    #[cfg(feature = "test-util")]
+    Mock(crate::MockKeyboard),
+    /// Keyboards on the session's seat, read through libinput.
+    #[cfg(feature = "libinput")]
+    Libinput,
+    /// Hotkeys grabbed through the X server.
+    #[cfg(feature = "x11")]
+    X11,
+}
+
+/// Linux hotkey listener using evdev.
+pub struct HotkeyListener {
+    source: Source,
+    hotkeys: Vec<Hotkey>,
+    config: ListenerConfig,
+}
+
+impl HotkeyListener {
+    /// Create a new listener with the given keyboards, hotkeys and options.
+    pub fn new(keyboards: Vec<Device>, hotkeys: Vec<Hotkey>, config: ListenerConfig) -> Self {
+        Self {
+            source: Source::Devices(keyboards),
+            hotkeys,
+            config,
+        }
+    }
+
+    /// Create a listener for the keyboards found in /dev/input.
+    ///
+    /// If no keyboard can be opened because of missing permissions and the
+    /// `libinput` feature is enabled, falls back to reading the session's
+    /// seat through libinput. Otherwise, if the `x11` feature is enabled and
+    /// `$DISPLAY` is set, falls back to grabbing the hotkeys through X11.
+    pub fn discover(hotkeys: Vec<Hotkey>, config: ListenerConfig) -> Result<Self> {
+        #[cfg(feature = "x11")]
+        if config.backend == Backend::X11 {
+            return Ok(Self::with_x11(hotkeys, config));
+        }
+        match find_selected_keyboards(
+            config.device_paths.as_deref(),
+            config.pinned_keyboard.as_deref(),
+            config.keyboard_group,
+            config.device_filter.as_ref(),
+            !config.keep_duplicate_devices,
+        ) {
+            Ok(keyboards) => Ok(Self::new(keyboards, hotkeys, config)),
+            Err(e) => {
+                // Explicit device paths aren't in /dev/input, which libinput reads
+                let permission_denied = config.device_paths.is_none() && input_permission_denied();
+                if use_libinput_fallback(permission_denied, cfg!(feature = "libinput")) {
+                    log::info!("Permission denied on /dev/input, falling back to libinput");
+                    return Ok(Self::with_libinput(hotkeys, config));
+                }
+                if config.allow_no_devices && !permission_denied {
+                    log::info!("{}, waiting for a keyboard to be connected", e);
+                    return Ok(Self::new(Vec::new(), hotkeys, config));
+                }
+                let display_set = std::env::var_os("DISPLAY").is_some();
+                if use_x11_fallback(config.backend, display_set, cfg!(feature = "x11")) {
+                    log::info!("{}, falling back to X11", e);
+                    return Ok(Self::with_x11(hotkeys, config));
+                }
+                Err(with_sandbox_hint(e))
+            }
+        }
+    }
+
+    #[cfg(feature = "libinput")]
+    fn with_libinput(hotkeys: Vec<Hotkey>, config: ListenerConfig) -> Self {
+        Self {
+            source: Source::Libinput,
+            hotkeys,
+            config,
+        }
+    }
+
+    #[cfg(not(feature = "libinput"))]
+    fn with_libinput(_hotkeys: Vec<Hotkey>, _config: ListenerConfig) -> Self {
+        unreachable!("libinput fallback selected without the libinput feature")
+    }
+
+    #[cfg(feature = "x11")]
+    fn with_x11(hotkeys: Vec<Hotkey>, config: ListenerConfig) -> Self {
+        Self {
+            source: Source::X11,
+            hotkeys,
+            config,
+        }
+    }
+
+    #[cfg(not(feature = "x11"))]
+    fn with_x11(_hotkeys: Vec<Hotkey>, _config: ListenerConfig) -> Self {
+        unreachable!("X11 fallback selected without the x11 feature")
+    }
+
+    /// Create a listener reading a mock keyboard instead of real devices.
+    #[cfg(feature = "test-util")]
+    pub fn with_mock(
+        keyboard: crate::MockKeyboard,
+        hotkeys: Vec<Hotkey>,
+        config: ListenerConfig,
+    ) -> Self {
+        Self {
+            source: Source::Mock(keyboard),
+            hotkeys,
+            config,
+        }
+    }
+
+    /// Create a listener that replays an evemu capture instead of reading devices.
+    pub fn from_evemu(path: &Path, hotkeys: Vec<Hotkey>, config: ListenerConfig) -> Result<Self> {
+        Ok(Self {
+            source: Source::Evemu(read_evemu(path)?),
+            hotkeys,
+            config,
+        })
+    }
+
+    /// Start listening for hotkeys in a background thread.
+    /// Returns a receiver for hotkey events.
+    pub fn start(self, running: Arc<AtomicBool>) -> Result<Receiver<HotkeyEvent>> {
+        let (tx, rx) = channel::channel();
+        match self.source {
+            Source::Devices(mut keyboards) => {
+                set_nonblocking(&keyboards)?;
+                if self.config.verify_devices {
+                    let check = verify_devices(&mut keyboards, VERIFY_TIMEOUT);
+                    self.config.shared.status.grabbed(check.grabbed);
+                }
+                start_keyboard_listener(keyboards, self.hotkeys, self.config, running, tx)?;
+            }
+            Source::Evemu(events) => start_replay(events, self.hotkeys, self.config, running, tx),
+            #[cfg(feature = "test-util")]
+            Source::Mock(keyboard) => {
+                crate::mock::start_mock_listener(keyboard, self.hotkeys, self.config, running, tx);
+            }
+            #[cfg(feature = "libinput")]
+            Source::Libinput => {
+                crate::libinput::start_libinput_listener(self.hotkeys, self.config, running, tx)?;
+            }
+            #[cfg(feature = "x11")]
+            Source::X11 => {
+                crate::x11::start_x11_listener(self.hotkeys, self.config, running, tx)?;
+            }
+        }
+        Ok(rx)
+    }
+}
+
+/// An open keyboard read by the listener loop.
+pub(crate) trait Keyboard: StaleEvents + KeyCapabilities + Send + 'static {
+    /// Read the events that are currently available, without blocking.
+    fn read_events(&mut self) -> std::io::Result<Vec<evdev::InputEvent>>;
+    /// The keyboard's physical path, if it reports one.
+    fn device_id(&self) -> Option<String>;
+    /// Whether `led` is lit, if the keyboard has it.
+    fn led(&self, led: evdev::LedType) -> Option<bool>;
+    /// The descriptor to wait on for input, if the keyboard has one.
+    fn raw_fd(&self) -> Option<RawFd>;
+    /// Take or release an exclusive grab (EVIOCGRAB), hiding the keyboard's
+    /// events from everyone else.
+    fn set_grabbed(&mut self, grabbed: bool) -> std::io::Result<()>;
+}
+
+impl Keyboard for Device {
+    fn read_events(&mut self) -> std::io::Result<Vec<evdev::InputEvent>> {
+        self.fetch_events().map(|events| events.collect())
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.as_raw_fd())
+    }
+
+    fn set_grabbed(&mut self, grabbed: bool) -> std::io::Result<()> {
+        if grabbed {
+            Device::grab(self)
+        } else {
+            Device::ungrab(self)
+        }
+    }
+
+    fn device_id(&self) -> Option<String> {
+        self.physical_path().map(String::from)
+    }
+
+    fn led(&self, led: evdev::LedType) -> Option<bool> {
+        let supports_led = self.supported_leds().is_some_and(|leds| leds.contains(led));
+        if !supports_led {
+            return None;
+        }
+        let leds = self.get_led_state().ok()?;
+        Some(leds.contains(led))
+    }
+}
+
+/// How long the listener blocks waiting for input before it checks whether
+/// it should stop, rescan or release stuck keys.
+const WAIT_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Sleep between reads when the keyboards can't be waited on with epoll.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Epoll token of the descriptor signalled by injected keys.
+const WAKE_TOKEN: u64 = u64::MAX;
+
+/// Blocks the listener loop until a keyboard has input or a key is injected.
+///
+/// Rebuilt with [`watch`](Self::watch) whenever the set of keyboards
+/// changes, since closing a device drops it from the epoll set.
+struct InputWaiter {
+    /// `None` when a keyboard has no descriptor or epoll failed, in which
+    /// case the loop falls back to polling.
+    epoll: Option<Epoll>,
+    /// Number of keyboards watched.
+    len: usize,
+    /// Indices of the keyboards the last wait found input on, or `None`
+    /// if any of them may have some.
+    ready: Option<Vec<usize>>,
+}
+
+impl InputWaiter {
+    /// Wait on exactly the given keyboards, and on `wake` if given.
+    fn watch<K: Keyboard>(keyboards: &[K], wake: Option<RawFd>) -> Self {
+        let epoll = keyboards
+            .iter()
+            .map(Keyboard::raw_fd)
+            .collect::<Option<Vec<RawFd>>>()
+            .and_then(|fds| match Self::epoll(&fds, wake) {
+                Ok(epoll) => Some(epoll),
+                Err(e) => {
+                    log::warn!("Failed to set up epoll, polling keyboards instead: {}", e);
+                    None
+                }
+            });
+        Self {
+            epoll,
+            len: keyboards.len(),
+            ready: None,
+        }
+    }
+
+    /// Whether the keyboard at `idx` may have input to read, so keyboards
+    /// epoll didn't report aren't read for nothing.
+    fn is_ready(&self, idx: usize) -> bool {
+        self.ready.as_ref().is_none_or(|ready| ready.contains(&idx))
+    }
+
+    fn epoll(fds: &[RawFd], wake: Option<RawFd>) -> nix::Result<Epoll> {
+        let epoll = Epoll::new(EpollCreateFlags::EPOLL_CLOEXEC)?;
+        let tokens = (0..fds.len() as u64).chain(wake.map(|_| WAKE_TOKEN));
+        for (fd, token) in fds.iter().chain(&wake).zip(tokens) {
+            // SAFETY: the keyboards and the injector owning the fds are open
+            // for the duration of the call; the kernel drops the
+            // registration once one is closed.
+            let fd = unsafe { BorrowedFd::borrow_raw(*fd) };
+            epoll.add(fd, EpollEvent::new(EpollFlags::EPOLLIN, token))?;
+        }
+        Ok(epoll)
+    }
+
+    /// Return once a keyboard may have input, a key was injected, at
+    /// `deadline`, or after [`WAIT_TIMEOUT`], whichever comes first, noting
+    /// which keyboards have input.
+    fn wait(&mut self, deadline: Option<Instant>) {
+        self.ready = None;
+        let Some(epoll) = &self.epoll else {
+            thread::sleep(POLL_INTERVAL);
+            return;
+        };
+        let mut events = vec![EpollEvent::empty(); self.len + 1];
+        let timeout = deadline.map_or(WAIT_TIMEOUT, |deadline| {
+            // Round up so the deadline has passed when epoll returns
+            let until = deadline.saturating_duration_since(Instant::now());
+            until.min(WAIT_TIMEOUT) + Duration::from_millis(1)
+        });
+        let timeout = EpollTimeout::try_from(timeout).expect("timeout fits in epoll");
+        match epoll.wait(&mut events, timeout) {
+            Ok(count) => {
+                let ready = events[..count]
+                    .iter()
+                    .map(EpollEvent::data)
+                    .filter(|&token| token != WAKE_TOKEN);
+                self.ready = Some(ready.map(|idx| idx as usize).collect());
+            }
+            Err(Errno::EINTR) => {}
+            Err(e) => {
+                log::debug!("epoll_wait failed: {}", e);
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+/// Take or release exclusive grabs on `keyboards` when `grab_keys` is set.
+///
+/// Closing a device releases its grab, so keyboards dropped after an error
+/// need no explicit release.
+fn set_grabbed<K: Keyboard>(keyboards: &mut [K], config: &ListenerConfig, grabbed: bool) {
+    if !config.grab_keys {
+        return;
+    }
+    for keyboard in keyboards {
+        if let Err(e) = keyboard.set_grabbed(grabbed) {
+            log::warn!(
+                "Failed to {} keyboard {:?}: {}",
+                if grabbed { "grab" } else { "release" },
+                keyboard.label().unwrap_or_else(|| "unknown".to_string()),
+                e
+            );
+        }
+    }
+}
+
+/// Seed the NumLock and CapsLock states from the first keyboard reporting
+/// each, so they're right even if a lock was already on.
+///
+/// Later changes arrive as LED events on the keyboards themselves.
+fn read_lock_leds<K: Keyboard>(keyboards: &[K], processor: &mut EventProcessor) {
+    let lit = |led| keyboards.iter().find_map(|keyboard| keyboard.led(led));
+    if let Some(on) = lit(evdev::LedType::LED_NUML) {
+        processor.set_num_lock(on);
+    }
+    if let Some(on) = lit(evdev::LedType::LED_CAPSL) {
+        processor.set_caps_lock(on);
+    }
+}
+
+/// Finds keyboards for the listener loop, returning them ready for
+/// non-blocking reads.
+pub(crate) trait KeyboardScanner: Send + 'static {
+    type Keyboard: Keyboard;
+    /// All keyboards the listener should use.
+    fn scan(&self) -> Result<Vec<Self::Keyboard>>;
+    /// Keyboards at paths not in `known`.
+    fn scan_new(&self, known: &HashSet<PathBuf>) -> Vec<(PathBuf, Self::Keyboard)>;
+    /// Paths of the keyboards currently present.
+    fn paths(&self) -> HashSet<PathBuf>;
+}
+
+/// Scans `/dev/input` for the keyboards selected on the builder.
+struct DevInputScanner {
+    paths: Option<Vec<PathBuf>>,
+    pinned: Option<PathBuf>,
+    group: KeyboardGroup,
+    filter: Option<DeviceFilter>,
+    dedupe: bool,
+}
+
+impl KeyboardScanner for DevInputScanner {
+    type Keyboard = Device;
+
+    fn scan(&self) -> Result<Vec<Device>> {
+        let keyboards = find_selected_keyboards(
+            self.paths.as_deref(),
+            self.pinned.as_deref(),
+            self.group,
+            self.filter.as_ref(),
+            self.dedupe,
+        )?;
+        set_nonblocking(&keyboards)?;
+        for kb in &keyboards {
+            log::debug!(
+                "  - {:?} ({})",
+                kb.name().unwrap_or("unknown"),
+                kb.physical_path().unwrap_or("no path")
+            );
+        }
+        Ok(keyboards)
+    }
+
+    fn scan_new(&self, known: &HashSet<PathBuf>) -> Vec<(PathBuf, Device)> {
+        let new_devices = find_new_keyboards(
+            known,
+            self.paths.as_deref(),
+            self.pinned.as_deref(),
+            self.group,
+            self.filter.as_ref(),
+            self.dedupe,
+        );
+        if new_devices.is_empty() {
+            return new_devices;
+        }
+        for (_, device) in &new_devices {
+            log::info!(
+                "  - {:?} ({})",
+                device.name().unwrap_or("unknown"),
+                device.physical_path().unwrap_or("no path")
+            );
+        }
+        let (paths, devices): (Vec<PathBuf>, Vec<Device>) = new_devices.into_iter().unzip();
+        match set_nonblocking(&devices) {
+            Ok(()) => paths.into_iter().zip(devices).collect(),
+            Err(e) => {
+                log::warn!("Failed to set non-blocking on new keyboards: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    fn paths(&self) -> HashSet<PathBuf> {
+        get_keyboard_paths(self.paths.as_deref())
+    }
+}
+
+fn start_keyboard_listener(
+    keyboards: Vec<Device>,
+    hotkeys: Vec<Hotkey>,
+    config: ListenerConfig,
+    running: Arc<AtomicBool>,
+    tx: Sender<HotkeyEvent>,
+) -> Result<()> {
+    let scanner = DevInputScanner {
+        paths: config.device_paths.clone(),
+        pinned: config.pinned_keyboard.clone(),
+        group: config.keyboard_group,
+        filter: config.device_filter.clone(),
+        dedupe: !config.keep_duplicate_devices,
+    };
+    let processor = EventProcessor::new(hotkeys, config.clone());
+    let listener_thread = config.shared.thread.clone();
+    listener_thread.set(thread::spawn(move || {
+        run_keyboard_loop(keyboards, scanner, processor, &config, &running, &tx)
+    }));
+    Ok(())
+}
+
+/// Send [`HotkeyEvent::DevicesChanged`] for `connected` keyboards, if enabled.
+fn report_device_count(config: &ListenerConfig, tx: &Sender<HotkeyEvent>, connected: usize) {
+    if config.emit_device_changes {
+        let event = HotkeyEvent::DevicesChanged { connected };
+        config.shared.record(event, SystemTime::now(), None, None);
+        let _ = tx.send(event);
+    }
+}
+
+/// Read the keyboards until stopped, reconnecting through `scanner` after errors.
+pub(crate) fn run_keyboard_loop<S: KeyboardScanner>(
+    mut keyboards: Vec<S::Keyboard>,
+    scanner: S,
+    mut processor: EventProcessor,
+    config: &ListenerConfig,
+    running: &AtomicBool,
+    tx: &Sender<HotkeyEvent>,
+) {
+    let status = &config.shared.status;
+    let _stopped = status.stop_on_drop();
+    status.connected(keyboards.len());
+    set_grabbed(&mut keyboards, config, true);
+    read_lock_leds(&keyboards, &mut processor);
+    let wake = config.shared.injector.wake_fd();
+    let watch = |keyboards: &[S::Keyboard]| InputWaiter::watch(keyboards, wake);
+    let mut waiter = watch(&keyboards);
+    let mut last_rescan = Instant::now();
+    // Without keyboards, e.g. with `allow_no_devices`, rescan straight away
+    let mut had_error = keyboards.is_empty();
+    let mut reconnect = ReconnectPolicy::new(config.max_reconnect_attempts);
+
+    // Track known keyboard device paths to detect newly connected devices
+    let mut known_paths: HashSet<PathBuf> = scanner.paths();
+
+    // Timer for periodic new device detection (e.g., Bluetooth keyboards connecting)
+    let mut last_device_scan = Instant::now();
+    const DEVICE_SCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+    // Minimum interval between keyboard rescans and the time new devices get
+    // to initialize
+    let rescan_interval = config.rescan_interval.unwrap_or(DEFAULT_RESCAN_INTERVAL);
+    let settle = config.reconnect_settle.unwrap_or(DEFAULT_RECONNECT_SETTLE);
+
+    // Timer for re-checking that open devices still report keyboard keys
+    let mut last_capability_check = Instant::now();
+    const CAPABILITY_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+    while running.load(Ordering::Relaxed) {
+        #[cfg(any(test, feature = "test-util"))]
+        if config.shared.disconnect.take() {
+            log::info!("Simulating keyboard disconnect");
+            status.disconnected("simulated disconnect");
+            keyboards.clear();
+            report_device_count(config, tx, 0);
+            waiter = watch(&keyboards);
+            had_error = true;
+        }
+
+        if had_error && config.no_rescan {
+            log::error!("Keyboard error with auto reconnect off, stopping listener");
+            running.store(false, Ordering::SeqCst);
+            break;
+        }
+
+        // Rescan after an error: immediately at first, then once per interval
+        if had_error && reconnect.rescan_due(last_rescan.elapsed(), rescan_interval) {
+            log::info!("Keyboard error detected, rescanning devices...");
+            match reconnect.rescan(|| scanner.scan()) {
+                RescanOutcome::Reconnected(mut new_keyboards) => {
+                    // Give devices time to fully initialize (especially important for BT keyboards)
+                    thread::sleep(settle);
+                    log::info!(
+                        "Keyboards reconnected: found {} device(s)",
+                        new_keyboards.len()
+                    );
+                    // Drain any stale events before starting to use the keyboards
+                    drain_events(&mut new_keyboards, DRAIN_BUDGET);
+                    // Drop old keyboards explicitly before replacing
+                    keyboards.clear();
+                    set_grabbed(&mut new_keyboards, config, true);
+                    keyboards = new_keyboards;
+                    waiter = watch(&keyboards);
+                    processor.reset();
+                    read_lock_leds(&keyboards, &mut processor);
+                    had_error = false;
+                    reconnect.on_success();
+                    status.connected(keyboards.len());
+                    report_device_count(config, tx, keyboards.len());
+                    // Rebuild known paths and reset device scan timer
+                    known_paths = scanner.paths();
+                    last_device_scan = Instant::now();
+                }
+                RescanOutcome::Failed => {}
+                RescanOutcome::GaveUp => {
+                    log::error!("Giving up on reconnecting keyboards, stopping listener");
+                    running.store(false, Ordering::SeqCst);
+                    break;
+                }
+            }
+            last_rescan = Instant::now();
+        }
+
+        // Periodically check for newly connected keyboards (e.g., Bluetooth)
+        if !config.no_rescan && last_device_scan.elapsed() >= DEVICE_SCAN_INTERVAL {
+            let new_devices = scanner.scan_new(&known_paths);
+            if !new_devices.is_empty() {
+                log::info!("New keyboard(s) detected: {} device(s)", new_devices.len());
+
+                // Give devices time to fully initialize
+                thread::sleep(settle);
+
+                let (paths, mut devices): (Vec<PathBuf>, Vec<S::Keyboard>) =
+                    new_devices.into_iter().unzip();
+                drain_events(&mut devices, DRAIN_BUDGET);
+                set_grabbed(&mut devices, config, true);
+                known_paths.extend(paths);
+                keyboards.extend(devices);
+                waiter = watch(&keyboards);
+                status.keyboard_count(keyboards.len());
+                report_device_count(config, tx, keyboards.len());
+            }
+            last_device_scan = Instant::now();
+        }
+
+        // Composite devices can renegotiate and stop reporting keys; reading them
+        // would block hotkeys forever, so drop them and rescan
+        if last_capability_check.elapsed() >= CAPABILITY_CHECK_INTERVAL {
+            let dropped = retain_keyboards(&mut keyboards);
+            if dropped > 0 {
+                waiter = watch(&keyboards);
+                log::info!(
+                    "{} device(s) no longer report keyboard keys, rescanning",
+                    dropped
+                );
+                status.disconnected(format!(
+                    "{} device(s) no longer report keyboard keys",
+                    dropped
+                ));
+                report_device_count(config, tx, keyboards.len());
+                had_error = true;
+            }
+            last_capability_check = Instant::now();
+        }
+
+        let mut any_error = false;
+
+        for (idx, device) in keyboards.iter_mut().enumerate() {
+            if !waiter.is_ready(idx) {
+                continue;
+            }
+            if config.report_devices {
+                processor.set_device(device.device_id());
+            }
+            match device.read_events() {
+                Ok(events) => {
+                    for hotkey_event in processor.process_batch(events) {
+                        let _ = tx.send(hotkey_event);
+                    }
+                }
+                Err(e) => {
+                    // EAGAIN/EWOULDBLOCK is expected for non-blocking reads
+                    if e.raw_os_error() != Some(libc::EAGAIN)
+                        && e.raw_os_error() != Some(libc::EWOULDBLOCK)
+                    {
+                        log::debug!("Keyboard read error: {}", e);
+                        if !had_error {
+                            status.disconnected(&e);
+                        }
+                        any_error = true;
+                    }
+                }
+            }
+        }
+
+        if any_error && !had_error {
+            // The keyboards are unusable until the rescan finds them again
+            report_device_count(config, tx, 0);
+        }
+        if any_error {
+            had_error = true;
+        }
+
+        for hotkey_event in processor
+            .process_injected()
+            .into_iter()
+            .chain(processor.release_stuck())
+            .chain(processor.poll_long_presses())
+        {
+            let _ = tx.send(hotkey_event);
+        }
+
+        // A failing device stays readable, so epoll would spin until the
+        // rescan replaces it
+        if had_error {
+            thread::sleep(POLL_INTERVAL);
+            waiter.ready = None;
+        } else {
+            waiter.wait(processor.next_deadline());
+        }
+    }
+    set_grabbed(&mut keyboards, config, false);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{DeviceHotkeyEvent, TimedHotkeyEvent};
+    use crate::hotkey::parse_hotkey;
+    use std::cell::Cell;
+
+    fn key_event(key: evdev::Key, value: i32) -> evdev::InputEvent {
+        evdev::InputEvent::new(evdev::EventType::KEY, key.code(), value)
+    }
+
+    struct MockDevice {
+        has_keys: Cell<bool>,
+    }
+
+    impl KeyCapabilities for MockDevice {
+        fn reports_keyboard_keys(&self) -> bool {
+            self.has_keys.get()
+        }
+    }
+
+    /// A device that never runs out of events.
+    struct FloodingDevice {
+        batches: usize,
+    }
+
+    impl StaleEvents for FloodingDevice {
+        fn label(&self) -> Option<String> {
+            None
+        }
+
+        fn discard_batch(&mut self) -> std::io::Result<usize> {
+            self.batches += 1;
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn test_every_key_maps_to_distinct_evdev_key() {
+        let mut seen = HashSet::new();
+        for &key in Key::all() {
+            let evdev_key = to_evdev_key(key);
+            assert!(seen.insert(evdev_key), "{} shares {:?}", key, evdev_key);
+            assert_eq!(from_evdev_key(evdev_key, false), Some(key));
+        }
+    }
+
+    #[test]
+    fn test_print_screen_and_menu_evdev_codes() {
+        assert_eq!(to_evdev_key(Key::PrintScreen), evdev::Key::KEY_SYSRQ);
+        assert_eq!(to_evdev_key(Key::Menu), evdev::Key::KEY_COMPOSE);
+        assert_eq!(
+            from_evdev_key(evdev::Key::KEY_PRINT, true),
+            Some(Key::PrintScreen)
+        );
+        assert_eq!(from_evdev_key(evdev::Key::KEY_MENU, true), Some(Key::Menu));
+        assert_eq!(from_evdev_key(evdev::Key::KEY_MENU, false), None);
+    }
+
+    #[test]
+    fn test_numpad_evdev_codes() {
+        assert_eq!(to_evdev_key(Key::Numpad0), evdev::Key::KEY_KP0);
+        assert_eq!(to_evdev_key(Key::Numpad9), evdev::Key::KEY_KP9);
+        assert_eq!(to_evdev_key(Key::NumpadEnter), evdev::Key::KEY_KPENTER);
+        assert_eq!(to_evdev_key(Key::NumpadPlus), evdev::Key::KEY_KPPLUS);
+        assert_eq!(to_evdev_key(Key::NumpadMinus), evdev::Key::KEY_KPMINUS);
+        assert_eq!(
+            to_evdev_key(Key::NumpadMultiply),
+            evdev::Key::KEY_KPASTERISK
+        );
+        assert_eq!(to_evdev_key(Key::NumpadDivide), evdev::Key::KEY_KPSLASH);
+        assert_eq!(
+            from_evdev_key(evdev::Key::KEY_KP5, false),
+            Some(Key::Numpad5)
+        );
+        assert_eq!(from_evdev_key(evdev::Key::KEY_5, false), Some(Key::Digit5));
+    }
+
+    #[test]
+    fn test_raw_code_of_named_key_binds_that_key() {
+        assert_eq!(Key::parse("raw:66").unwrap(), Key::F8);
+        assert!(parse_hotkey("raw:42").is_err());
+
+        let mut processor = EventProcessor::new(
+            vec![parse_hotkey("Ctrl+raw:66").unwrap()],
+            ListenerConfig::default(),
+        );
+        processor.process(&key_event(evdev::Key::KEY_LEFTCTRL, 1));
+        assert_eq!(
+            processor.process(&key_event(evdev::Key::KEY_F8, 1)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+    }
+
+    #[test]
+    fn test_raw_keycode_passes_through() {
+        assert_eq!(to_evdev_key(Key::Raw(190)), evdev::Key::new(190));
+
+        let mut processor = EventProcessor::new(
+            vec![parse_hotkey("Ctrl+raw:190").unwrap()],
+            ListenerConfig::default(),
+        );
+        processor.process(&key_event(evdev::Key::KEY_LEFTCTRL, 1));
+        assert_eq!(
+            processor.process(&key_event(evdev::Key::new(190), 1)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        assert_eq!(
+            processor.process(&key_event(evdev::Key::new(190), 0)),
+            vec![HotkeyEvent::Released(0)]
+        );
+        // Unbound codes are still just other keys
+        assert!(processor
+            .process(&key_event(evdev::Key::new(191), 1))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_modifier_lookahead_catches_late_modifier() {
+        let timed = |key: evdev::Key, value, micros| {
+            evdev::InputEvent::from(libc::input_event {
+                time: libc::timeval {
+                    tv_sec: 1,
+                    tv_usec: micros,
+                },
+                type_: evdev::EventType::KEY.0,
+                code: key.code(),
+                value,
+            })
+        };
+        let syn = evdev::InputEvent::new(evdev::EventType::SYNCHRONIZATION, 0, 0);
+        let batch = |shift_micros| {
+            vec![
+                timed(evdev::Key::KEY_F8, 1, 0),
+                syn,
+                timed(evdev::Key::KEY_LEFTSHIFT, 1, shift_micros),
+                syn,
+            ]
+        };
+        let hotkeys = vec![parse_hotkey("Shift+F8").unwrap()];
+
+        let mut processor = EventProcessor::new(hotkeys.clone(), ListenerConfig::default());
+        assert!(processor.process_batch(batch(2_000)).is_empty());
+
+        let config = ListenerConfig {
+            modifier_lookahead: Some(Duration::from_millis(5)),
+            ..ListenerConfig::default()
+        };
+        let mut processor = EventProcessor::new(hotkeys.clone(), config.clone());
+        assert_eq!(
+            processor.process_batch(batch(2_000)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        // A modifier pressed well after the key isn't part of the combo
+        let mut processor = EventProcessor::new(hotkeys, config);
+        assert!(processor.process_batch(batch(20_000)).is_empty());
+    }
+
+    #[test]
+    fn test_num_lock_led_event_updates_state() {
+        let mut processor = EventProcessor::new(
+            vec![Hotkey::new(Key::Numpad1).when_num_lock(true)],
+            ListenerConfig::default(),
+        );
+        let num_lock =
+            |on: i32| evdev::InputEvent::new(evdev::EventType::LED, evdev::LedType::LED_NUML.0, on);
+
+        processor.process(&num_lock(1));
+        assert_eq!(
+            processor.process(&key_event(evdev::Key::KEY_KP1, 1)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        processor.process(&key_event(evdev::Key::KEY_KP1, 0));
+
+        processor.process(&num_lock(0));
+        assert!(processor
+            .process(&key_event(evdev::Key::KEY_KP1, 1))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_caps_lock_is_read_at_startup_and_tracked() {
+        let mut processor = EventProcessor::new(
+            vec![
+                parse_hotkey("F8").unwrap().when_caps_lock(true),
+                Hotkey::new(Key::Numpad1).when_num_lock(false),
+            ],
+            ListenerConfig::default(),
+        );
+        let keyboard = QueueKeyboard(
+            Default::default(),
+            Default::default(),
+            vec![evdev::LedType::LED_CAPSL],
+        );
+
+        // Started with CapsLock already on
+        read_lock_leds(&[keyboard], &mut processor);
+        assert_eq!(
+            processor.process(&key_event(evdev::Key::KEY_F8, 1)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        processor.process(&key_event(evdev::Key::KEY_F8, 0));
+        assert_eq!(
+            processor.process(&key_event(evdev::Key::KEY_KP1, 1)),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+        processor.process(&key_event(evdev::Key::KEY_KP1, 0));
+
+        processor.process(&evdev::InputEvent::new(
+            evdev::EventType::LED,
+            evdev::LedType::LED_CAPSL.0,
+            0,
+        ));
+        assert!(processor
+            .process(&key_event(evdev::Key::KEY_F8, 1))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_events_carry_their_device() {
+        let config = ListenerConfig::default();
+        let injector = config.shared.injector.clone();
+        let events = config.shared.subscribers.subscribe_with_device();
+        let mut processor = EventProcessor::new(vec![parse_hotkey("F8").unwrap()], config);
+
+        processor.set_device(Some("usb-0000:00:14.0-2/input0".into()));
+        processor.process(&key_event(evdev::Key::KEY_F8, 1));
+        assert_eq!(
+            events.try_recv().unwrap(),
+            DeviceHotkeyEvent {
+                event: HotkeyEvent::Pressed(0),
+                device: Some("usb-0000:00:14.0-2/input0".into()),
+                char_hint: None,
+            }
+        );
+
+        // Injected keys come from no keyboard
+        injector.inject(Key::F8, false);
+        processor.process_injected();
+        assert_eq!(events.try_recv().unwrap().device, None);
+    }
+
+    #[test]
+    fn test_injected_keys_are_matched() {
+        let config = ListenerConfig::default();
+        let injector = config.shared.injector.clone();
+        let mut processor = EventProcessor::new(vec![parse_hotkey("Shift+F8").unwrap()], config);
+
+        injector.inject(Key::F8, true);
+        injector.inject(Key::F8, false);
+        assert!(processor.process_injected().is_empty());
+
+        // Modifiers come from the physical keyboard
+        processor.process(&key_event(evdev::Key::KEY_LEFTSHIFT, 1));
+        injector.inject(Key::F8, true);
+        injector.inject(Key::F8, false);
+        assert_eq!(
+            processor.process_injected(),
+            vec![HotkeyEvent::Pressed(0), HotkeyEvent::Released(0)]
+        );
+        assert!(processor.process_injected().is_empty());
+    }
+
+    #[test]
+    fn test_drain_events_respects_budget() {
+        let mut devices = vec![FloodingDevice { batches: 0 }, FloodingDevice { batches: 0 }];
+        let started = Instant::now();
+        drain_events(&mut devices, Duration::from_millis(20));
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(devices.iter().all(|device| device.batches > 0));
+    }
+
+    /// A socket standing in for a device; dropping the peer makes it hang up.
+    struct SocketDevice(&'static str, std::os::unix::net::UnixStream);
+
+    impl SocketDevice {
+        fn pair(label: &'static str) -> (Self, std::os::unix::net::UnixStream) {
+            let (device, peer) = std::os::unix::net::UnixStream::pair().unwrap();
+            (Self(label, device), peer)
+        }
+    }
+
+    impl AsRawFd for SocketDevice {
+        fn as_raw_fd(&self) -> std::os::fd::RawFd {
+            self.1.as_raw_fd()
+        }
+    }
+
+    impl Pollable for SocketDevice {
+        fn label(&self) -> Option<String> {
+            Some(self.0.to_string())
+        }
+
+        // Devices labelled "grabbed" act as if another process grabbed them
+        fn grab(&mut self) -> std::io::Result<()> {
+            if self.0 == "grabbed" {
+                return Err(std::io::Error::from_raw_os_error(libc::EBUSY));
+            }
+            Ok(())
+        }
+
+        fn ungrab(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_verify_devices_reports_non_delivering_device() {
+        let (healthy, _peer) = SocketDevice::pair("healthy");
+        let (broken, broken_peer) = SocketDevice::pair("broken");
+        drop(broken_peer);
+        let mut devices = vec![healthy, broken];
+
+        let check = verify_devices(&mut devices, Duration::from_millis(10));
+        assert_eq!(check.unreadable, vec!["broken".to_string()]);
+        assert!(check.grabbed.is_empty());
+    }
+
+    #[test]
+    fn test_verify_devices_reports_grabbed_device() {
+        let (healthy, _peer) = SocketDevice::pair("healthy");
+        let (grabbed, _grabbed_peer) = SocketDevice::pair("grabbed");
+        let mut devices = vec![healthy, grabbed];
+
+        let check = verify_devices(&mut devices, Duration::from_millis(10));
+        assert!(check.unreadable.is_empty());
+        assert_eq!(check.grabbed, vec!["grabbed".to_string()]);
+    }
+
+    /// A keyboard reading events pushed onto a shared queue.
+    #[derive(Clone, Default)]
+    struct QueueKeyboard(
+        Arc<std::sync::Mutex<std::collections::VecDeque<evdev::InputEvent>>>,
+        /// Whether the keyboard is grabbed.
+        Arc<AtomicBool>,
+        /// The LEDs that are lit.
+        Vec<evdev::LedType>,
+    );
+
+    impl QueueKeyboard {
+        fn tap(&self, key: evdev::Key) {
+            let mut queue = self.0.lock().unwrap();
+            queue.push_back(key_event(key, 1));
+            queue.push_back(key_event(key, 0));
+        }
+    }
+
+    impl StaleEvents for QueueKeyboard {
+        fn label(&self) -> Option<String> {
+            None
+        }
+
+        // Keep queued events so a test can queue them before the reconnect
+        fn discard_batch(&mut self) -> std::io::Result<usize> {
+            Ok(0)
+        }
+    }
+
+    impl KeyCapabilities for QueueKeyboard {
+        fn reports_keyboard_keys(&self) -> bool {
+            true
+        }
+    }
+
+    impl Keyboard for QueueKeyboard {
+        fn read_events(&mut self) -> std::io::Result<Vec<evdev::InputEvent>> {
+            Ok(self.0.lock().unwrap().drain(..).collect())
+        }
+
+        fn device_id(&self) -> Option<String> {
+            None
+        }
+
+        fn led(&self, led: evdev::LedType) -> Option<bool> {
+            Some(self.2.contains(&led))
+        }
+
+        fn raw_fd(&self) -> Option<RawFd> {
+            None
+        }
+
+        fn set_grabbed(&mut self, grabbed: bool) -> std::io::Result<()> {
+            self.1.store(grabbed, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// Finds the given keyboard on every rescan.
+    struct MockScanner(QueueKeyboard);
+
+    impl KeyboardScanner for MockScanner {
+        type Keyboard = QueueKeyboard;
+
+        fn scan(&self) -> Result<Vec<QueueKeyboard>> {
+            Ok(vec![self.0.clone()])
+        }
+
+        fn scan_new(&self, _known: &HashSet<PathBuf>) -> Vec<(PathBuf, QueueKeyboard)> {
+            Vec::new()
+        }
+
+        fn paths(&self) -> HashSet<PathBuf> {
+            HashSet::new()
+        }
+    }
+
+    #[test]
+    fn test_events_carry_kernel_timestamp() {
+        let config = ListenerConfig::default();
+        let timed = config.shared.subscribers.subscribe_timed();
+        let mut processor = EventProcessor::new(vec![parse_hotkey("F8").unwrap()], config);
+
+        let event = evdev::InputEvent::from(libc::input_event {
+            time: libc::timeval {
+                tv_sec: 1_700_000_000,
+                tv_usec: 250_000,
+            },
+            type_: evdev::EventType::KEY.0,
+            code: evdev::Key::KEY_F8.code(),
+            value: 1,
+        });
+        assert_eq!(processor.process(&event), vec![HotkeyEvent::Pressed(0)]);
+        assert_eq!(
+            timed.try_recv().unwrap(),
+            TimedHotkeyEvent {
+                event: HotkeyEvent::Pressed(0),
+                timestamp: SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 250_000_000),
+                char_hint: None,
+            }
+        );
+
+        // Synthesized events have no kernel time and are stamped on emission
+        let before = SystemTime::now();
+        processor.process(&key_event(evdev::Key::KEY_F8, 0));
+        assert!(timed.try_recv().unwrap().timestamp >= before);
+    }
+
+    #[test]
+    fn test_input_waiter_wakes_on_input() {
+        use std::io::{Read, Write};
+
+        let (idle, _idle_peer) = std::os::unix::net::UnixStream::pair().unwrap();
+        let (mut device, mut peer) = std::os::unix::net::UnixStream::pair().unwrap();
+        let mut waiter = InputWaiter {
+            epoll: Some(InputWaiter::epoll(&[idle.as_raw_fd(), device.as_raw_fd()], None).unwrap()),
+            len: 2,
+            ready: None,
+        };
+        assert!(waiter.is_ready(0) && waiter.is_ready(1));
+
+        peer.write_all(b"x").unwrap();
+        let start = Instant::now();
+        waiter.wait(None);
+        assert!(start.elapsed() < WAIT_TIMEOUT);
+        // Only the keyboard with input is read
+        assert!(!waiter.is_ready(0));
+        assert!(waiter.is_ready(1));
+
+        device.read_exact(&mut [0; 1]).unwrap();
+        let start = Instant::now();
+        waiter.wait(None);
+        assert!(start.elapsed() >= WAIT_TIMEOUT);
+        assert!(!waiter.is_ready(1));
+
+        // A deadline, e.g. a pending long press, cuts the wait short
+        let start = Instant::now();
+        waiter.wait(Some(start + Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert!(start.elapsed() < WAIT_TIMEOUT);
+    }
+
+    #[test]
+    fn test_input_waiter_wakes_on_injected_key() {
+        let (idle, _idle_peer) = std::os::unix::net::UnixStream::pair().unwrap();
+        let injector = crate::listener::KeyInjector::default();
+        let mut waiter = InputWaiter {
+            epoll: Some(InputWaiter::epoll(&[idle.as_raw_fd()], injector.wake_fd()).unwrap()),
+            len: 1,
+            ready: None,
+        };
+
+        injector.inject(Key::F8, true);
+        let start = Instant::now();
+        waiter.wait(None);
+        assert!(start.elapsed() < WAIT_TIMEOUT);
+        // Waking for an injected key doesn't make the keyboards readable
+        assert!(!waiter.is_ready(0));
+
+        // Draining the injected keys resets the wake-up
+        assert_eq!(injector.drain(), vec![(Key::F8, true)]);
+        let start = Instant::now();
+        waiter.wait(None);
+        assert!(start.elapsed() >= WAIT_TIMEOUT);
+    }
+
+    /// Runs the keyboard loop for an F8 hotkey on a thread, returning the
+    /// handle that reads its events and the thread to join once it's dropped.
+    fn spawn_f8_loop(
+        keyboards: Vec<QueueKeyboard>,
+        scanner: MockScanner,
+        config: ListenerConfig,
+    ) -> (
+        crate::listener::HotkeyListenerHandle,
+        thread::JoinHandle<()>,
+    ) {
+        let processor = EventProcessor::new(vec![parse_hotkey("F8").unwrap()], config.clone());
+        let running = Arc::new(AtomicBool::new(true));
+        let (tx, rx) = channel::channel();
+        let handle = crate::listener::HotkeyListenerHandle::new(
+            Arc::clone(&running),
+            rx,
+            config.shared.clone(),
+        );
+        let listener = thread::spawn(move || {
+            run_keyboard_loop(keyboards, scanner, processor, &config, &running, &tx)
+        });
+        (handle, listener)
+    }
+
+    #[test]
+    fn test_starts_without_keyboards_and_picks_one_up() {
+        let keyboard = QueueKeyboard::default();
+        let config = ListenerConfig {
+            allow_no_devices: true,
+            ..ListenerConfig::default()
+        };
+        let (handle, listener) = spawn_f8_loop(Vec::new(), MockScanner(keyboard.clone()), config);
+        let timeout = Duration::from_secs(2);
+
+        keyboard.tap(evdev::Key::KEY_F8);
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Pressed(0)));
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Released(0)));
+
+        drop(handle);
+        listener.join().unwrap();
+    }
+
+    #[test]
+    fn test_simulated_disconnect_reconnects_to_rescanned_keyboards() {
+        let first = QueueKeyboard::default();
+        let replacement = QueueKeyboard::default();
+        let config = ListenerConfig::default();
+        let (handle, listener) = spawn_f8_loop(
+            vec![first.clone()],
+            MockScanner(replacement.clone()),
+            config,
+        );
+        let timeout = Duration::from_secs(2);
+
+        first.tap(evdev::Key::KEY_F8);
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Pressed(0)));
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Released(0)));
+
+        handle.simulate_disconnect();
+        replacement.tap(evdev::Key::KEY_F8);
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Pressed(0)));
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Released(0)));
+
+        drop(handle);
+        listener.join().unwrap();
+    }
+
+    #[test]
+    fn test_device_changes_are_reported_when_enabled() {
+        let first = QueueKeyboard::default();
+        let config = ListenerConfig {
+            emit_device_changes: true,
+            ..ListenerConfig::default()
+        };
+        let (handle, listener) =
+            spawn_f8_loop(vec![first], MockScanner(QueueKeyboard::default()), config);
+        let timeout = Duration::from_secs(2);
+
+        handle.simulate_disconnect();
+        assert_eq!(
+            handle.recv_timeout(timeout),
+            Ok(HotkeyEvent::DevicesChanged { connected: 0 })
+        );
+        assert_eq!(
+            handle.recv_timeout(timeout),
+            Ok(HotkeyEvent::DevicesChanged { connected: 1 })
+        );
+
+        drop(handle);
+        listener.join().unwrap();
+    }
+
+    #[test]
+    fn test_grab_keys_grabs_keyboards_until_stopped() {
+        let first = QueueKeyboard::default();
+        let replacement = QueueKeyboard::default();
+        let config = ListenerConfig {
+            grab_keys: true,
+            ..ListenerConfig::default()
+        };
+        let (handle, listener) = spawn_f8_loop(
+            vec![first.clone()],
+            MockScanner(replacement.clone()),
+            config,
+        );
+        let timeout = Duration::from_secs(2);
+
+        first.tap(evdev::Key::KEY_F8);
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Pressed(0)));
+        assert!(first.1.load(Ordering::SeqCst));
+
+        // Keyboards found by the rescan are grabbed too
+        handle.simulate_disconnect();
+        replacement.tap(evdev::Key::KEY_F8);
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Released(0)));
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Pressed(0)));
+        assert!(replacement.1.load(Ordering::SeqCst));
+
+        drop(handle);
+        listener.join().unwrap();
+        assert!(!replacement.1.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_detect_sandbox() {
+        let flatpak_info = |path: &Path| path == Path::new("/.flatpak-info");
+        let no_file = |_: &Path| false;
+        let snap_var = |name: &str| name == "SNAP";
+        let no_var = |_: &str| false;
+
+        assert_eq!(detect_sandbox(flatpak_info, no_var), Some(Sandbox::Flatpak));
+        assert_eq!(detect_sandbox(no_file, snap_var), Some(Sandbox::Snap));
+        assert_eq!(detect_sandbox(no_file, no_var), None);
+    }
+
+    #[test]
+    fn test_retain_group_filters_by_bus_type() {
+        let mut devices: Vec<(PathBuf, BusType)> = [
+            ("event3", BusType::I8042),
+            ("event5", BusType::Usb),
+            ("event7", BusType::Bluetooth),
+            ("event9", BusType::Virtual),
+        ]
+        .iter()
+        .map(|&(name, bus)| (Path::new("/dev/input").join(name), bus))
+        .collect();
+
+        let mut all = devices.clone();
+        retain_group(&mut all, KeyboardGroup::All, |bus| *bus);
+        assert_eq!(all.len(), 4);
+
+        let mut internal = devices.clone();
+        retain_group(&mut internal, KeyboardGroup::Internal, |bus| *bus);
+        assert_eq!(internal, vec![devices[0].clone()]);
+
+        retain_group(&mut devices, KeyboardGroup::External, |bus| *bus);
+        let buses: Vec<BusType> = devices.iter().map(|(_, bus)| *bus).collect();
+        assert_eq!(buses, vec![BusType::Usb, BusType::Bluetooth]);
+    }
+
+    #[test]
+    fn test_retain_distinct_keeps_one_node_per_keyboard() {
+        // Physical path, whether it has Escape, and its key count
+        type FakeDevice = (Option<&'static str>, bool, usize);
+        let mut devices: Vec<(PathBuf, FakeDevice)> = [
+            ("event2", (Some("isa0060/serio0/input0"), false, 8)),
+            ("event3", (Some("isa0060/serio0/input0"), true, 104)),
+            ("event4", (Some("usb-0000:00:14.0-1/input0"), true, 87)),
+            ("event5", (None, true, 104)),
+            ("event6", (None, true, 104)),
+        ]
+        .iter()
+        .map(|&(name, device)| (Path::new("/dev/input").join(name), device))
+        .collect();
+
+        retain_distinct(
+            &mut devices,
+            |device| device.0,
+            |device| (device.1, device.2),
+        );
+        let kept: Vec<&str> = devices
+            .iter()
+            .map(|(path, _)| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(kept, vec!["event3", "event4", "event5", "event6"]);
+    }
+
+    #[test]
+    fn test_keyboard_info_from_reported_fields() {
+        let id = evdev::InputId::new(evdev::BusType::BUS_USB, 0x046d, 0xc31c, 0x0110);
+        let info = reported_keyboard_info(
+            PathBuf::from("/dev/input/event5"),
+            Some("Logitech USB Keyboard"),
+            Some("usb-0000:00:14.0-2/input0"),
+            id,
+            104,
+        );
+        assert_eq!(info.name, "Logitech USB Keyboard");
+        assert_eq!(info.path, PathBuf::from("/dev/input/event5"));
+        assert_eq!(
+            info.physical_path.as_deref(),
+            Some("usb-0000:00:14.0-2/input0")
+        );
+        assert_eq!(info.bus_type, BusType::Usb);
+        assert_eq!((info.vendor, info.product), (0x046d, 0xc31c));
+        assert_eq!(info.key_count, 104);
+
+        let id = evdev::InputId::new(evdev::BusType::BUS_I8042, 0, 0, 0);
+        let info = reported_keyboard_info(PathBuf::from("/dev/input/event3"), None, None, id, 0);
+        assert_eq!(info.name, "unknown");
+        assert_eq!(info.physical_path, None);
+        assert_eq!(info.bus_type, BusType::I8042);
+    }
+
+    #[test]
+    fn test_retain_named_applies_device_filter() {
+        let mut devices: Vec<(PathBuf, &str)> = [
+            ("event3", "AT Translated Set 2 keyboard"),
+            ("event4", "HDA Intel PCH Power Button"),
+            ("event7", "Logitech USB Keyboard"),
+        ]
+        .iter()
+        .map(|&(node, name)| (Path::new("/dev/input").join(node), name))
+        .collect();
+
+        let mut unfiltered = devices.clone();
+        retain_named(&mut unfiltered, None, |name| name);
+        assert_eq!(unfiltered.len(), 3);
+
+        let filter = DeviceFilter::new(|name| name.to_lowercase().contains("keyboard"));
+        retain_named(&mut devices, Some(&filter), |name| name);
+        let names: Vec<&str> = devices.iter().map(|(_, name)| *name).collect();
+        assert_eq!(
+            names,
+            vec!["AT Translated Set 2 keyboard", "Logitech USB Keyboard"]
+        );
+    }
+
+    #[test]
+    fn test_device_paths_report_each_failure() {
+        let paths = vec![
+            PathBuf::from("/nonexistent/event0"),
+            PathBuf::from("/dev/null"),
+        ];
+
+        let (keyboards, failures) = open_keyboards(paths.clone());
+        assert!(keyboards.is_empty());
+        assert_eq!(failures.len(), 2);
+        assert!(failures[0].starts_with("/nonexistent/event0: "));
+        assert!(failures[1].starts_with("/dev/null: "));
+
+        let error = find_selected_keyboards(Some(&paths), None, KeyboardGroup::All, None, true)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("/nonexistent/event0: "), "{}", error);
+        assert!(error.contains("/dev/null: "), "{}", error);
+        assert_eq!(get_keyboard_paths(Some(&paths)), HashSet::new());
+    }
+
+    #[test]
+    fn test_retain_pinned_keeps_only_pinned_device() {
+        let mut devices: Vec<(PathBuf, MockDevice)> = ["event3", "event5", "event7"]
+            .iter()
+            .map(|name| {
+                (
+                    Path::new("/dev/input").join(name),
+                    MockDevice {
+                        has_keys: Cell::new(true),
+                    },
+                )
+            })
+            .collect();
+
+        retain_pinned(&mut devices, None);
+        assert_eq!(devices.len(), 3);
+
+        retain_pinned(&mut devices, Some(Path::new("/dev/input/event5")));
+        let paths: Vec<&Path> = devices.iter().map(|(path, _)| path.as_path()).collect();
+        assert_eq!(paths, vec![Path::new("/dev/input/event5")]);
+    }
+
+    #[test]
+    fn test_retain_keyboards_drops_device_that_lost_keys() {
+        let mut devices = vec![
+            MockDevice {
+                has_keys: Cell::new(true),
+            },
+            MockDevice {
+                has_keys: Cell::new(true),
+            },
+        ];
+        assert_eq!(retain_keyboards(&mut devices), 0);
+
+        devices[1].has_keys.set(false);
+        assert_eq!(retain_keyboards(&mut devices), 1);
+        assert_eq!(devices.len(), 1);
+        assert!(devices[0].has_keys.get());
+    }
+
+    #[test]
+    fn test_alternate_code_matches_when_enabled() {
+        let hotkeys = vec![parse_hotkey("Pause").unwrap()];
+
+        let mut processor = EventProcessor::new(hotkeys.clone(), ListenerConfig::default());
+        assert!(processor
+            .process(&key_event(evdev::Key::KEY_BREAK, 1))
+            .is_empty());
+
+        let config = ListenerConfig {
+            alternate_codes: true,
+            ..ListenerConfig::default()
+        };
+        let mut processor = EventProcessor::new(hotkeys, config);
+        assert_eq!(
+            processor.process(&key_event(evdev::Key::KEY_BREAK, 1)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        assert_eq!(
+            processor.process(&key_event(evdev::Key::KEY_PAUSE, 0)),
+            vec![HotkeyEvent::Released(0)]
+        );
+    }
+
+    #[test]
+    fn test_ghosting_guard_suppresses_extra_keys() {
+        let config = ListenerConfig {
+            max_simultaneous_keys: Some(3),
+            ..ListenerConfig::default()
+        };
+        let mut processor = EventProcessor::new(vec![parse_hotkey("F8").unwrap()], config);
+
+        for key in [evdev::Key::KEY_A, evdev::Key::KEY_S, evdev::Key::KEY_D] {
+            assert!(processor.process(&key_event(key, 1)).is_empty());
+        }
+        // A fourth simultaneous key is treated as a phantom, press and release
+        assert!(processor
+            .process(&key_event(evdev::Key::KEY_F8, 1))
+            .is_empty());
+        assert!(processor
+            .process(&key_event(evdev::Key::KEY_F8, 0))
+            .is_empty());
+
+        processor.process(&key_event(evdev::Key::KEY_D, 0));
+        assert_eq!(
+            processor.process(&key_event(evdev::Key::KEY_F8, 1)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+    }
+
+    #[cfg(feature = "raw-evdev")]
+    #[test]
+    fn test_raw_callback_receives_every_event() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let seen_by_callback = Arc::clone(&seen);
+        let config = ListenerConfig {
+            raw_callback: Some(RawEvdevCallback::new(move |event| {
+                seen_by_callback
+                    .lock()
+                    .unwrap()
+                    .push((event.code(), event.value()));
+            })),
+            ..ListenerConfig::default()
+        };
+        let mut processor = EventProcessor::new(vec![parse_hotkey("F8").unwrap()], config);
+
+        processor.process(&evdev::InputEvent::new(evdev::EventType::MISC, 4, 0x42));
+        processor.process(&key_event(evdev::Key::KEY_A, 1));
+        assert_eq!(
+            processor.process(&key_event(evdev::Key::KEY_F8, 1)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (4, 0x42),
+                (evdev::Key::KEY_A.code(), 1),
+                (evdev::Key::KEY_F8.code(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_libinput_fallback_selection() {
+        assert!(use_libinput_fallback(true, true));
+        // Without the feature there is nothing to fall back to
+        assert!(!use_libinput_fallback(true, false));
+        // Missing devices aren't a permission problem
+        assert!(!use_libinput_fallback(false, true));
+        assert!(!use_libinput_fallback(false, false));
+    }
+
+    #[test]
+    fn test_permission_denied_lists_paths_and_hint() {
+        let paths = vec![
+            PathBuf::from("/dev/input/event0"),
+            PathBuf::from("/dev/input/event3"),
+        ];
+        let error = permission_denied(paths.clone(), InputGroup::NotMember);
+        assert!(matches!(
+            &error,
+            HotkeyError::PermissionDenied { paths: denied, .. } if *denied == paths
+        ));
+        let message = error.to_string();
+        assert!(message.contains("/dev/input/event0, /dev/input/event3"));
+        assert!(message.contains("usermod -aG input"));
+
+        let message = permission_denied(paths, InputGroup::PendingLogin).to_string();
+        assert!(message.contains("log out and back in"));
+    }
+
+    #[test]
+    fn test_x11_fallback_selection() {
+        assert!(use_x11_fallback(Backend::Auto, true, true));
+        // Forced to evdev
+        assert!(!use_x11_fallback(Backend::Evdev, true, true));
+        // No display to connect to
+        assert!(!use_x11_fallback(Backend::Auto, false, true));
+        // Feature disabled
+        assert!(!use_x11_fallback(Backend::Auto, true, false));
+    }
+
+    #[test]
+    fn test_reconnect_gives_up_after_max_attempts() {
+        let mut policy = ReconnectPolicy::new(Some(3));
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match policy.rescan::<()>(|| Err(anyhow!("No keyboards found"))) {
+                RescanOutcome::Failed => continue,
+                RescanOutcome::GaveUp => break,
+                RescanOutcome::Reconnected(_) => unreachable!(),
+            }
+        }
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_reconnect_success_resets_attempts() {
+        let mut policy = ReconnectPolicy::new(Some(2));
+        assert!(!policy.on_failure());
+        policy.on_success();
+        assert!(!policy.on_failure());
+        assert!(policy.on_failure());
+
+        // Unlimited by default
+        let mut policy = ReconnectPolicy::new(None);
+        assert!((0..100).all(|_| !policy.on_failure()));
+    }
+
+    #[test]
+    fn test_first_rescan_after_error_is_immediate() {
+        let interval = Duration::from_secs(3);
+        let mut policy = ReconnectPolicy::new(None);
+        assert!(policy.rescan_due(Duration::ZERO, interval));
+
+        policy.on_failure();
+        assert!(!policy.rescan_due(Duration::from_millis(100), interval));
+        assert!(policy.rescan_due(interval, interval));
+
+        policy.on_success();
+        assert!(policy.rescan_due(Duration::ZERO, interval));
+    }
 }
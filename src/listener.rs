@@ -1,48 +1,1649 @@
 //! Platform-agnostic listener builder.
 
-use crate::event::HotkeyEvent;
-use crate::hotkey::Hotkey;
+use crate::channel::{self, Receiver, Sender};
+use crate::event::{DeviceHotkeyEvent, HotkeyEvent, TimedHotkeyEvent};
+use crate::hotkey::{Chord, Hotkey, Modifiers};
+use crate::key::Key;
+use crate::keyboard::{KeyboardGroup, KeyboardInfo};
+use crate::status::StatusReporter;
 use anyhow::Result;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{Receiver, RecvError, RecvTimeoutError};
-use std::sync::Arc;
-use std::time::Duration;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvError, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Minimum time between keyboard rescans after an error, unless configured.
+pub(crate) const DEFAULT_RESCAN_INTERVAL: Duration = Duration::from_secs(3);
+
+/// How long newly found keyboards are left to initialize, unless configured.
+pub(crate) const DEFAULT_RECONNECT_SETTLE: Duration = Duration::from_millis(100);
+
+/// How often a bounded join checks whether the listener thread has exited.
+const JOIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often a forwarding thread checks whether its receiving side was dropped.
+#[cfg(any(feature = "futures", feature = "tokio"))]
+const FORWARD_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+type EventFn = dyn FnMut(HotkeyEvent) + Send;
+
+/// Callback invoked with every event, see `on_event`.
+#[derive(Clone)]
+pub(crate) struct EventCallback(Arc<Mutex<EventFn>>);
+
+impl EventCallback {
+    pub fn new(callback: impl FnMut(HotkeyEvent) + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(callback)))
+    }
+
+    fn call(&self, event: HotkeyEvent) {
+        if let Ok(mut callback) = self.0.lock() {
+            callback(event);
+        }
+    }
+}
+
+impl std::fmt::Debug for EventCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EventCallback(..)")
+    }
+}
+
+/// Invoke `callback` with the events from `rx` on a dedicated thread until the
+/// listener stops.
+///
+/// Returns the receiver for the handle, which gets no events and disconnects
+/// once the callback thread has finished.
+fn forward_to_callback(
+    rx: Receiver<HotkeyEvent>,
+    running: Arc<AtomicBool>,
+    callback: EventCallback,
+) -> Receiver<HotkeyEvent> {
+    let (done_tx, done_rx) = channel::channel();
+    std::thread::spawn(move || {
+        for event in rx.iter() {
+            if !running.load(Ordering::SeqCst) {
+                break;
+            }
+            callback.call(event);
+        }
+        drop(done_tx);
+    });
+    done_rx
+}
+
+/// Pass the events from `rx` on to a channel holding at most `capacity` of
+/// them, counting the events dropped because it was full in `dropped`.
+///
+/// Returns the receiver for the handle. The forwarding thread stops once the
+/// listener has stopped or the receiver is dropped.
+fn forward_bounded(
+    rx: Receiver<HotkeyEvent>,
+    capacity: usize,
+    dropped: Arc<AtomicU64>,
+) -> Receiver<HotkeyEvent> {
+    let (tx, bounded_rx) = channel::bounded(capacity);
+    std::thread::spawn(move || {
+        for event in rx.iter() {
+            match channel::try_send(&tx, event) {
+                Ok(()) => {}
+                Err(channel::TrySendError::Full) => {
+                    log::debug!("Event channel full, dropping {:?}", event);
+                    dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(channel::TrySendError::Disconnected) => break,
+            }
+        }
+    });
+    bounded_rx
+}
+
+/// Predicate deciding whether hotkeys are active at a given time.
+#[derive(Clone)]
+pub(crate) struct Schedule(Arc<dyn Fn(SystemTime) -> bool + Send + Sync>);
+
+impl Schedule {
+    pub fn new(schedule: impl Fn(SystemTime) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(schedule))
+    }
+
+    pub fn is_active(&self, now: SystemTime) -> bool {
+        (self.0)(now)
+    }
+}
+
+impl std::fmt::Debug for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Schedule(..)")
+    }
+}
+
+/// Predicate on device names deciding which keyboards are opened.
+#[derive(Clone)]
+pub(crate) struct DeviceFilter(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl DeviceFilter {
+    pub fn new(filter: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(filter))
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        (self.0)(name)
+    }
+}
+
+impl std::fmt::Debug for DeviceFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DeviceFilter(..)")
+    }
+}
+
+/// Slot through which the handle asks the backend for the next key combo.
+#[derive(Clone, Default)]
+pub(crate) struct ComboCapture(Arc<Mutex<Option<mpsc::Sender<Hotkey>>>>);
+
+impl ComboCapture {
+    /// Capture the next combo instead of matching it, delivering it to the receiver.
+    pub fn arm(&self) -> mpsc::Receiver<Hotkey> {
+        let (tx, rx) = mpsc::channel();
+        *self.0.lock().unwrap() = Some(tx);
+        rx
+    }
+
+    /// Stop waiting for a combo.
+    pub fn disarm(&self) {
+        self.0.lock().unwrap().take();
+    }
+
+    /// Hand a pressed combo to a pending capture, returning true if it was consumed.
+    pub fn offer(&self, hotkey: Hotkey) -> bool {
+        match self.0.lock().unwrap().take() {
+            Some(tx) => tx.send(hotkey).is_ok(),
+            None => false,
+        }
+    }
+}
+
+impl std::fmt::Debug for ComboCapture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ComboCapture(..)")
+    }
+}
+
+/// Bounded in-memory log of emitted events, see `record_events`.
+#[derive(Debug, Clone)]
+pub(crate) struct EventRecorder {
+    capacity: usize,
+    events: Arc<Mutex<VecDeque<(Instant, HotkeyEvent)>>>,
+}
+
+impl EventRecorder {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    /// Record an event, dropping the oldest one once full.
+    pub fn record(&self, event: HotkeyEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut events = self.events.lock().unwrap();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back((Instant::now(), event));
+    }
+
+    pub fn events(&self) -> Vec<(Instant, HotkeyEvent)> {
+        self.events.lock().unwrap().iter().copied().collect()
+    }
+}
+
+/// Extra receivers of emitted events, see `HotkeyListenerHandle::subscribe`.
+///
+/// Also tracks which hotkeys are held so late subscribers can be given a
+/// snapshot of the current state.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Subscribers(Arc<Mutex<SubscriberState>>);
+
+#[derive(Debug, Default)]
+struct SubscriberState {
+    senders: Vec<Sender<HotkeyEvent>>,
+    timed_senders: Vec<Sender<TimedHotkeyEvent>>,
+    device_senders: Vec<Sender<DeviceHotkeyEvent>>,
+    /// Outstanding presses per event id.
+    held: BTreeMap<usize, u32>,
+}
+
+impl Subscribers {
+    /// Track the event and forward it to every live subscriber.
+    pub fn publish(
+        &self,
+        event: HotkeyEvent,
+        timestamp: SystemTime,
+        device: Option<&str>,
+        char_hint: Option<char>,
+    ) {
+        let mut state = self.0.lock().unwrap();
+        match event {
+            HotkeyEvent::Pressed(id) => *state.held.entry(id).or_default() += 1,
+            HotkeyEvent::Released(id) => {
+                if let Some(count) = state.held.get_mut(&id) {
+                    *count -= 1;
+                    if *count == 0 {
+                        state.held.remove(&id);
+                    }
+                }
+            }
+            HotkeyEvent::Repeat(_)
+            | HotkeyEvent::DoubleTapped(_)
+            | HotkeyEvent::LongPressed(_)
+            | HotkeyEvent::ChordCompleted(_)
+            | HotkeyEvent::DevicesChanged { .. }
+            | HotkeyEvent::Tapped(_) => {}
+        }
+        state.senders.retain(|tx| tx.send(event).is_ok());
+        let timed = TimedHotkeyEvent {
+            event,
+            timestamp,
+            char_hint,
+        };
+        state.timed_senders.retain(|tx| tx.send(timed).is_ok());
+        state.device_senders.retain(|tx| {
+            tx.send(DeviceHotkeyEvent {
+                event,
+                device: device.map(String::from),
+                char_hint,
+            })
+            .is_ok()
+        });
+    }
+
+    /// Add a subscriber receiving events with the keyboard they came from.
+    pub fn subscribe_with_device(&self) -> Receiver<DeviceHotkeyEvent> {
+        let (tx, rx) = channel::channel();
+        self.0.lock().unwrap().device_senders.push(tx);
+        rx
+    }
+
+    /// Add a subscriber receiving events with their timestamps.
+    pub fn subscribe_timed(&self) -> Receiver<TimedHotkeyEvent> {
+        let (tx, rx) = channel::channel();
+        self.0.lock().unwrap().timed_senders.push(tx);
+        rx
+    }
+
+    /// Add a subscriber through a sender the caller owns.
+    pub fn add_sender(&self, tx: Sender<HotkeyEvent>) {
+        self.0.lock().unwrap().senders.push(tx);
+    }
+
+    /// Add a subscriber, first replaying a press for every held hotkey if `snapshot`.
+    pub fn subscribe(&self, snapshot: bool) -> Receiver<HotkeyEvent> {
+        let (tx, rx) = channel::channel();
+        let mut state = self.0.lock().unwrap();
+        if snapshot {
+            for &id in state.held.keys() {
+                let _ = tx.send(HotkeyEvent::Pressed(id));
+            }
+        }
+        state.senders.push(tx);
+        rx
+    }
+}
+
+/// A change to the registered hotkeys requested through the handle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum HotkeyUpdate {
+    Add(Hotkey),
+    Remove(usize),
+}
+
+/// Hotkey changes from `add_hotkey`/`remove_hotkey`, applied by the backend.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HotkeyUpdates(Arc<Mutex<HotkeyUpdateState>>);
+
+#[derive(Debug, Default)]
+struct HotkeyUpdateState {
+    /// Index the next added hotkey gets; indices are never reused.
+    next_idx: usize,
+    pending: Vec<HotkeyUpdate>,
+}
+
+impl HotkeyUpdates {
+    /// Start numbering added hotkeys after the `count` given to the builder.
+    pub fn registered(&self, count: usize) {
+        self.0.lock().unwrap().next_idx = count;
+    }
+
+    pub fn add(&self, hotkey: Hotkey) -> usize {
+        let mut state = self.0.lock().unwrap();
+        let idx = state.next_idx;
+        state.next_idx += 1;
+        state.pending.push(HotkeyUpdate::Add(hotkey));
+        idx
+    }
+
+    pub fn remove(&self, idx: usize) {
+        self.0
+            .lock()
+            .unwrap()
+            .pending
+            .push(HotkeyUpdate::Remove(idx));
+    }
+
+    /// Take the pending changes, oldest first.
+    pub fn drain(&self) -> Vec<HotkeyUpdate> {
+        std::mem::take(&mut self.0.lock().unwrap().pending)
+    }
+}
+
+/// Hotkeys suspended with `set_hotkey_enabled`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DisabledHotkeys(Arc<Mutex<HashSet<usize>>>);
+
+impl DisabledHotkeys {
+    pub fn set_enabled(&self, idx: usize, enabled: bool) {
+        let mut disabled = self.0.lock().unwrap();
+        if enabled {
+            disabled.remove(&idx);
+        } else {
+            disabled.insert(idx);
+        }
+    }
+
+    pub fn is_enabled(&self, idx: usize) -> bool {
+        !self.0.lock().unwrap().contains(&idx)
+    }
+}
+
+/// Set while the whole listener is muted with `set_enabled(false)`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Paused(Arc<AtomicBool>);
+
+impl Paused {
+    pub fn set(&self, paused: bool) {
+        self.0.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The backend's listener thread, so the handle can wait for it to exit.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ListenerThread(Arc<Mutex<Option<JoinHandle<()>>>>);
+
+impl ListenerThread {
+    pub fn set(&self, thread: JoinHandle<()>) {
+        *self.0.lock().unwrap() = Some(thread);
+    }
+
+    /// Wait for the thread to exit, for at most `timeout` if given. Returns
+    /// whether it has exited, which is also the case if none was set.
+    pub fn join(&self, timeout: Option<Duration>) -> bool {
+        let Some(thread) = self.0.lock().unwrap().take() else {
+            return true;
+        };
+        if let Some(timeout) = timeout {
+            let deadline = Instant::now() + timeout;
+            while !thread.is_finished() {
+                if Instant::now() >= deadline {
+                    *self.0.lock().unwrap() = Some(thread);
+                    return false;
+                }
+                std::thread::sleep(JOIN_POLL_INTERVAL);
+            }
+        }
+        if thread.join().is_err() {
+            log::error!("Listener thread panicked");
+        }
+        true
+    }
+}
+
+/// Runtime overrides of the id emitted for a hotkey, see `set_event_index`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EventRemap(Arc<Mutex<HashMap<usize, usize>>>);
+
+impl EventRemap {
+    pub fn set(&self, hotkey_idx: usize, emit_idx: usize) {
+        self.0.lock().unwrap().insert(hotkey_idx, emit_idx);
+    }
+
+    pub fn get(&self, hotkey_idx: usize) -> Option<usize> {
+        self.0.lock().unwrap().get(&hotkey_idx).copied()
+    }
+}
+
+/// Request from `simulate_disconnect` for the backend to drop its keyboards.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DisconnectSignal(Arc<AtomicBool>);
+
+#[cfg(any(test, feature = "test-util"))]
+impl DisconnectSignal {
+    pub fn raise(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether a disconnect was requested since the last call.
+    pub fn take(&self) -> bool {
+        self.0.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Queue of key events injected through the handle, drained by the backend.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct KeyInjector {
+    queue: Arc<Mutex<VecDeque<(Key, bool)>>>,
+    #[cfg(target_os = "linux")]
+    wake: InjectWake,
+}
+
+impl KeyInjector {
+    pub fn inject(&self, key: Key, pressed: bool) {
+        self.queue.lock().unwrap().push_back((key, pressed));
+        #[cfg(target_os = "linux")]
+        self.wake.signal();
+    }
+
+    /// Take all pending key events, oldest first.
+    pub fn drain(&self) -> Vec<(Key, bool)> {
+        #[cfg(target_os = "linux")]
+        self.wake.clear();
+        self.queue.lock().unwrap().drain(..).collect()
+    }
+
+    /// A descriptor that becomes readable when a key is injected, for the
+    /// listener thread to wait on alongside the keyboards.
+    #[cfg(target_os = "linux")]
+    pub fn wake_fd(&self) -> Option<std::os::fd::RawFd> {
+        use std::os::fd::AsRawFd;
+        self.wake.0.as_ref().map(|fd| fd.as_raw_fd())
+    }
+}
+
+/// An eventfd signalled on every injected key, or `None` if it couldn't be
+/// created, in which case injected keys wait for the listener's next wake.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone)]
+struct InjectWake(Option<Arc<nix::sys::eventfd::EventFd>>);
+
+#[cfg(target_os = "linux")]
+impl Default for InjectWake {
+    fn default() -> Self {
+        use nix::sys::eventfd::{EfdFlags, EventFd};
+        let fd = EventFd::from_flags(EfdFlags::EFD_CLOEXEC | EfdFlags::EFD_NONBLOCK);
+        if let Err(e) = &fd {
+            log::warn!("Failed to create eventfd for injected keys: {}", e);
+        }
+        Self(fd.ok().map(Arc::new))
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl InjectWake {
+    fn signal(&self) {
+        if let Some(fd) = &self.0 {
+            let _ = fd.arm();
+        }
+    }
+
+    /// Reset the eventfd; fails harmlessly with EAGAIN if it wasn't signalled.
+    fn clear(&self) {
+        if let Some(fd) = &self.0 {
+            let _ = fd.read();
+        }
+    }
+}
+
+/// The modifiers the listener thread currently sees held, for
+/// `current_modifiers`. Only written by the listener thread.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HeldModifiers(Arc<Mutex<Modifiers>>);
+
+impl HeldModifiers {
+    pub fn set(&self, modifiers: Modifiers) {
+        *self.0.lock().unwrap() = modifiers;
+    }
+
+    pub fn get(&self) -> Modifiers {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// State shared between the backend and the handle.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Shared {
+    /// Implements `capture_next_combo`.
+    pub capture: ComboCapture,
+    /// Publishes status transitions.
+    pub status: StatusReporter,
+    /// Logs emitted events when `record_events` is set.
+    pub recorder: Option<EventRecorder>,
+    /// Key events injected through the handle.
+    pub injector: KeyInjector,
+    /// Modifiers currently held, as tracked by the matcher.
+    pub held_modifiers: HeldModifiers,
+    /// Receivers added with `subscribe`.
+    pub subscribers: Subscribers,
+    /// Ids set with `set_event_index`, taking precedence over the builder's.
+    pub remap: EventRemap,
+    /// Hotkeys added or removed through the handle.
+    pub updates: HotkeyUpdates,
+    /// Hotkeys suspended through the handle.
+    pub disabled: DisabledHotkeys,
+    /// Whether the handle muted every hotkey.
+    pub paused: Paused,
+    /// Events dropped because the bounded channel was full.
+    pub dropped: Arc<AtomicU64>,
+    /// The listener thread, once started.
+    pub thread: ListenerThread,
+    /// Set by `simulate_disconnect`.
+    #[cfg(any(test, feature = "test-util"))]
+    pub disconnect: DisconnectSignal,
+}
+
+impl Shared {
+    /// Note an event about to be sent to the handle, with when its key
+    /// transition happened, the keyboard it came from and the character its
+    /// key typed, if known.
+    pub fn record(
+        &self,
+        event: HotkeyEvent,
+        timestamp: SystemTime,
+        device: Option<&str>,
+        char_hint: Option<char>,
+    ) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(event);
+        }
+        self.subscribers
+            .publish(event, timestamp, device, char_hint);
+    }
+}
+
+/// Per-hotkey options set when the hotkey is added to the builder.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct HotkeyOptions {
+    /// Id carried by the hotkey's events.
+    pub id: u32,
+    /// Minimum time between two presses of the hotkey.
+    pub cooldown: Option<Duration>,
+    /// Two presses within this long emit `DoubleTapped`.
+    pub double_tap: Option<Duration>,
+    /// Holding the hotkey this long emits `LongPressed`.
+    pub long_press: Option<Duration>,
+    /// Overrides the listener's [`ModifierMatch`] for this hotkey.
+    pub modifier_match: Option<ModifierMatch>,
+}
+
+/// How the held modifiers must relate to a hotkey's modifiers for it to match.
+///
+/// When several hotkeys on the same key match a press, only the most
+/// specific fire: those requiring the most modifiers, then the most
+/// modifier sides, then the most NumLock and CapsLock conditions. So with
+/// `F8` set to [`IgnoreModifiers`](Self::IgnoreModifiers) next to
+/// `Shift+F8`, pressing Shift+F8 fires only the latter. Hotkeys tied on all
+/// of these all fire.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ModifierMatch {
+    /// Shift, Ctrl, Alt and Super must be held exactly as the hotkey
+    /// requires, so `F8` doesn't fire while Shift is held and `Ctrl+F8`
+    /// doesn't fire with Ctrl+Alt.
+    #[default]
+    Exact,
+    /// The hotkey's modifiers must be held, but extra ones are allowed, so
+    /// `Ctrl+F8` also fires with Ctrl+Shift held.
+    AtLeast,
+    /// Only the key counts: `F8` and `Ctrl+F8` both fire whatever modifiers
+    /// are held.
+    IgnoreModifiers,
+}
+
+/// Which input backend a Linux listener reads keys through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Read `/dev/input` through evdev, falling back to libinput or X11 if
+    /// their features are enabled and the devices can't be read.
+    #[default]
+    Auto,
+    /// Read `/dev/input` through evdev, never falling back to X11.
+    Evdev,
+    /// Grab the hotkeys through the X server with `XGrabKey`. Needs no
+    /// access to `/dev/input`, but only supports hotkeys with a main key and
+    /// can't tell left and right modifiers apart.
+    #[cfg(all(target_os = "linux", feature = "x11"))]
+    X11,
+}
+
+/// How to report a hotkey pressed on several keyboards at once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MultiDevicePress {
+    /// Report every press and release, so overlapping presses on two
+    /// keyboards produce two `Pressed` and two `Released` events.
+    #[default]
+    Separate,
+    /// Treat overlapping presses as one: `Pressed` on the first press and
+    /// `Released` once every keyboard has released the hotkey.
+    Coalesce,
+}
+
+/// Options configured on the builder and passed to the platform backend.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ListenerConfig {
+    /// Also match alternate evdev codes known to be sent for some keys.
+    pub alternate_codes: bool,
+    /// Only emit events while this returns true.
+    pub active_schedule: Option<Schedule>,
+    /// Suppress presses beyond this many simultaneously held keys (Linux only).
+    pub max_simultaneous_keys: Option<usize>,
+    /// Stop after this many consecutive failed reconnect attempts.
+    pub max_reconnect_attempts: Option<u32>,
+    /// Minimum time between keyboard rescans after an error.
+    pub rescan_interval: Option<Duration>,
+    /// How long newly found keyboards are left to initialize before use.
+    pub reconnect_settle: Option<Duration>,
+    /// Never rescan for keyboards; a device error stops the listener.
+    pub no_rescan: bool,
+    /// Synthesize a release for hotkeys held longer than this (Linux only).
+    pub max_hold: Option<Duration>,
+    /// Apply a modifier pressed this soon after a key in the same batch of
+    /// events first (evdev only).
+    pub modifier_lookahead: Option<Duration>,
+    /// How held modifiers are compared with each hotkey's modifiers.
+    pub modifier_match: ModifierMatch,
+    /// How presses of the same hotkey on several keyboards are reported.
+    pub multi_device_press: MultiDevicePress,
+    /// Which input backend to read keys through (Linux only).
+    pub backend: Backend,
+    /// Drop a repeated press within this long unless the key was released.
+    pub debounce: Option<Duration>,
+    /// Emit `Repeat` events while a hotkey's key auto-repeats.
+    pub emit_repeats: bool,
+    /// Emit `DevicesChanged` when keyboards disconnect or reconnect.
+    pub emit_device_changes: bool,
+    /// Emit `Tapped` on release instead of `Pressed` and `Released`.
+    pub tap_mode: bool,
+    /// Drop the second press of a double-tap and its release.
+    pub suppress_double_tap_presses: bool,
+    /// Held modifiers stop counting after this long.
+    pub modifier_timeout: Option<Duration>,
+    /// Check that each keyboard is pollable before starting (Linux only).
+    pub verify_devices: bool,
+    /// Start without keyboards and wait for one to be connected (Linux only).
+    pub allow_no_devices: bool,
+    /// Fail to build without any hotkeys or chords.
+    pub require_hotkeys: bool,
+    /// Only use keyboards in this group (Linux only).
+    pub keyboard_group: KeyboardGroup,
+    /// Open these device paths instead of scanning /dev/input (Linux only).
+    pub device_paths: Option<Vec<std::path::PathBuf>>,
+    /// Open every event node of a keyboard, not just one (Linux only).
+    pub keep_duplicate_devices: bool,
+    /// Only use the keyboard at this device path (Linux only).
+    pub pinned_keyboard: Option<std::path::PathBuf>,
+    /// Only open keyboards whose name matches.
+    pub device_filter: Option<DeviceFilter>,
+    /// Receives the events instead of the handle.
+    pub on_event: Option<EventCallback>,
+    /// Hold at most this many undelivered events, dropping the rest.
+    pub bounded: Option<usize>,
+    /// How long stopping the handle waits for the listener thread to exit.
+    pub join_timeout: Option<Duration>,
+    /// Take exclusive grabs on the keyboards.
+    pub grab_keys: bool,
+    /// Track which keyboard each event came from (Linux only).
+    pub report_devices: bool,
+    /// Chords, by the index their `ChordCompleted` events carry.
+    pub chords: Vec<Chord>,
+    /// Options for each hotkey, by position; hotkeys without any emit their index.
+    pub hotkey_options: Vec<HotkeyOptions>,
+    /// State shared with the handle.
+    pub shared: Shared,
+    /// Called with every raw evdev event before matching.
+    #[cfg(all(target_os = "linux", feature = "raw-evdev"))]
+    pub raw_callback: Option<crate::linux::RawEvdevCallback>,
+}
+
+impl ListenerConfig {
+    /// Whether events should be emitted at the given time.
+    pub fn is_active(&self, now: SystemTime) -> bool {
+        !self.shared.paused.is_paused()
+            && self
+                .active_schedule
+                .as_ref()
+                .is_none_or(|schedule| schedule.is_active(now))
+    }
+
+    /// The id carried by events for the hotkey at `idx`.
+    pub fn event_id(&self, idx: usize) -> usize {
+        if let Some(id) = self.shared.remap.get(idx) {
+            return id;
+        }
+        self.hotkey_options
+            .get(idx)
+            .map_or(idx, |options| options.id as usize)
+    }
+
+    /// The cooldown configured for the hotkey at `idx`.
+    pub fn cooldown(&self, idx: usize) -> Option<Duration> {
+        self.hotkey_options
+            .get(idx)
+            .and_then(|options| options.cooldown)
+    }
+
+    /// The double-tap window configured for the hotkey at `idx`.
+    pub fn double_tap(&self, idx: usize) -> Option<Duration> {
+        self.hotkey_options
+            .get(idx)
+            .and_then(|options| options.double_tap)
+    }
+
+    /// The long-press threshold configured for the hotkey at `idx`.
+    pub fn long_press(&self, idx: usize) -> Option<Duration> {
+        self.hotkey_options
+            .get(idx)
+            .and_then(|options| options.long_press)
+    }
+
+    /// How held modifiers are matched for the hotkey at `idx`.
+    pub fn hotkey_modifier_match(&self, idx: usize) -> ModifierMatch {
+        self.hotkey_options
+            .get(idx)
+            .and_then(|options| options.modifier_match)
+            .unwrap_or(self.modifier_match)
+    }
+}
 
 /// Builder for creating a hotkey listener.
 #[derive(Default)]
 pub struct HotkeyListenerBuilder {
-    hotkeys: Vec<Hotkey>,
+    hotkeys: Vec<(Hotkey, HotkeyOptions)>,
+    config: ListenerConfig,
 }
 
-impl HotkeyListenerBuilder {
-    /// Create a new builder.
-    pub fn new() -> Self {
-        Self::default()
+impl HotkeyListenerBuilder {
+    /// Create a new builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a hotkey to listen for.
+    ///
+    /// Its events carry the order in which it was added as their index.
+    pub fn add_hotkey(self, hotkey: Hotkey) -> Self {
+        let id = self.hotkeys.len() as u32;
+        self.add_hotkey_with_id(hotkey, id)
+    }
+
+    /// Add a hotkey whose events carry `id` instead of its insertion index.
+    ///
+    /// This keeps event identity stable when the set of hotkeys changes
+    /// between builds. Ids are not checked for uniqueness, so avoid mixing
+    /// them with [`add_hotkey`](Self::add_hotkey), or with hotkeys added
+    /// through [`HotkeyListenerHandle::add_hotkey`] later, unless they can't
+    /// collide with the indices those assign.
+    pub fn add_hotkey_with_id(mut self, hotkey: Hotkey, id: u32) -> Self {
+        self.hotkeys.push((
+            hotkey,
+            HotkeyOptions {
+                id,
+                ..HotkeyOptions::default()
+            },
+        ));
+        self
+    }
+
+    /// Add a hotkey that ignores presses within `cooldown` of the last one it fired for.
+    ///
+    /// A deliberate throttle for expensive actions, possibly seconds long,
+    /// rather than a filter for hardware bounce. Presses dropped by the
+    /// cooldown emit neither `Pressed` nor `Released`. Events carry the
+    /// insertion index like [`add_hotkey`](Self::add_hotkey).
+    pub fn add_hotkey_with_cooldown(mut self, hotkey: Hotkey, cooldown: Duration) -> Self {
+        let id = self.hotkeys.len() as u32;
+        self.hotkeys.push((
+            hotkey,
+            HotkeyOptions {
+                id,
+                cooldown: Some(cooldown),
+                ..HotkeyOptions::default()
+            },
+        ));
+        self
+    }
+
+    /// Add a chord, emitting [`HotkeyEvent::ChordCompleted`] when its steps
+    /// are pressed in order.
+    ///
+    /// Chords are numbered separately from hotkeys, in the order they're
+    /// added. A step pressed more than the chord's timeout after the previous
+    /// one, or a press of any other key, starts the chord over; modifiers
+    /// pressed on their own don't interrupt it. Steps that are also
+    /// registered hotkeys still emit their own `Pressed` and `Released`,
+    /// with `ChordCompleted` following the final step's `Pressed`, so a
+    /// chord whose first step is a hotkey also triggers that hotkey.
+    pub fn add_chord(mut self, chord: Chord) -> Self {
+        self.config.chords.push(chord);
+        self
+    }
+
+    /// Add a hotkey triggered by pressing `key` `count` times in a row within `window`.
+    ///
+    /// See [`Hotkey::sequence`]. Events carry the insertion index like
+    /// [`add_hotkey`](Self::add_hotkey).
+    pub fn add_hotkey_sequence(self, key: Key, count: u32, window: Duration) -> Self {
+        self.add_hotkey(Hotkey::sequence(key, count, window))
+    }
+
+    /// Keep the last `capacity` emitted events in memory.
+    ///
+    /// The events and their emission times are available from
+    /// [`HotkeyListenerHandle::recorded_events`], which is handy for test
+    /// assertions and crash dumps. Off by default.
+    pub fn record_events(mut self, capacity: usize) -> Self {
+        self.config.shared.recorder = Some(EventRecorder::new(capacity));
+        self
+    }
+
+    /// Also match alternate key codes that some keyboards send for a key.
+    ///
+    /// A few keyboards report keys under nonstandard codes because of firmware
+    /// quirks, e.g. Pause arriving as `KEY_BREAK`. With this enabled, the Linux
+    /// listener consults a small table of known alternates when a code doesn't
+    /// map directly. This is best-effort and has no effect on macOS.
+    pub fn match_alternate_codes(mut self, enabled: bool) -> Self {
+        self.config.alternate_codes = enabled;
+        self
+    }
+
+    /// Only emit hotkey events while `schedule` returns true for the current time.
+    ///
+    /// Useful for hotkeys that should only be active during certain hours.
+    /// Modifier state keeps being tracked while the schedule is inactive, so
+    /// hotkeys match correctly as soon as it becomes active again. The
+    /// predicate is called on the listener thread for every matched hotkey
+    /// transition, so it should be cheap.
+    pub fn active_schedule(
+        mut self,
+        schedule: impl Fn(SystemTime) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.config.active_schedule = Some(Schedule::new(schedule));
+        self
+    }
+
+    /// Suppress key presses that look like keyboard ghosting.
+    ///
+    /// Cheap keyboards can report phantom keys when several keys are held at
+    /// once. With this enabled, any press that arrives while
+    /// `max_simultaneous_keys` keys are already held is ignored (along with its
+    /// release) and logged at debug level. This is a heuristic and is off by
+    /// default; it is only implemented on Linux.
+    pub fn ghosting_guard(mut self, max_simultaneous_keys: usize) -> Self {
+        self.config.max_simultaneous_keys = Some(max_simultaneous_keys);
+        self
+    }
+
+    /// Treat a modifier pressed within `window` after a key, in the same
+    /// batch of events read from the keyboard, as pressed before the key.
+    ///
+    /// Some keyboards report both keys of a combo pressed at once in either
+    /// order, so `Shift+F8` can arrive as F8 then Shift and be missed. A
+    /// window of a few milliseconds (e.g. 5ms) catches these without
+    /// reordering deliberate presses; the kernel timestamps of the events
+    /// are compared. Off by default. Only implemented by the Linux evdev
+    /// backend, where events arriving in separate reads are never reordered.
+    pub fn modifier_lookahead(mut self, window: Duration) -> Self {
+        self.config.modifier_lookahead = Some(window);
+        self
+    }
+
+    /// Release a hotkey automatically once it has been held for `max_hold`.
+    ///
+    /// Guards against a physically stuck key jamming e.g. a push-to-talk
+    /// action. When a hotkey is held longer than this, the listener emits
+    /// `Released` for it and logs a warning; the eventual real release is
+    /// then ignored. Off by default and only implemented on Linux.
+    pub fn max_hold(mut self, max_hold: Duration) -> Self {
+        self.config.max_hold = Some(max_hold);
+        self
+    }
+
+    /// Choose how held modifiers are compared with each hotkey's modifiers.
+    ///
+    /// Defaults to [`ModifierMatch::Exact`]. Chords and hotkeys added through
+    /// the handle always use this; override it for a hotkey added here with
+    /// [`hotkey_modifier_match`](Self::hotkey_modifier_match). The X11
+    /// backend grabs each hotkey's exact modifiers whatever this is set to.
+    pub fn modifier_match(mut self, modifier_match: ModifierMatch) -> Self {
+        self.config.modifier_match = modifier_match;
+        self
+    }
+
+    /// Choose how held modifiers are compared with the modifiers of the
+    /// hotkey added at position `idx`, instead of the listener-wide
+    /// [`modifier_match`](Self::modifier_match).
+    ///
+    /// # Panics
+    ///
+    /// Panics if no hotkey has been added at `idx`.
+    pub fn hotkey_modifier_match(mut self, idx: usize, modifier_match: ModifierMatch) -> Self {
+        let (_, options) = self
+            .hotkeys
+            .get_mut(idx)
+            .unwrap_or_else(|| panic!("no hotkey added at index {}", idx));
+        options.modifier_match = Some(modifier_match);
+        self
+    }
+
+    /// Choose how a hotkey pressed on several keyboards at once is reported.
+    ///
+    /// Defaults to [`MultiDevicePress::Separate`]. Use
+    /// [`MultiDevicePress::Coalesce`] for setups where either keyboard can
+    /// trigger the hotkey and overlapping presses should count as one.
+    pub fn multi_device_press(mut self, policy: MultiDevicePress) -> Self {
+        self.config.multi_device_press = policy;
+        self
+    }
+
+    /// Ignore a second press of a key within `window` when the key wasn't
+    /// released in between.
+    ///
+    /// Worn switches can bounce, reporting press-press for a single press.
+    /// Unlike a plain time window, this never swallows a fast deliberate
+    /// double-tap, which always has a release between the presses. Keys are
+    /// tracked across all keyboards, so with [`MultiDevicePress::Coalesce`]
+    /// overlapping presses on two keyboards within `window` count as bounce.
+    /// Off by default.
+    pub fn debounce(mut self, window: Duration) -> Self {
+        self.config.debounce = Some(window);
+        self
+    }
+
+    /// Emit [`HotkeyEvent::Repeat`] while a pressed hotkey's key auto-repeats.
+    ///
+    /// Off by default, so push-to-talk users only see `Pressed` and
+    /// `Released`. On Linux this follows the kernel's auto-repeat; on macOS
+    /// the repeated key presses rdev delivers become `Repeat` events, and are
+    /// dropped when this is off.
+    pub fn emit_repeats(mut self, enabled: bool) -> Self {
+        self.config.emit_repeats = enabled;
+        self
+    }
+
+    /// Emit [`HotkeyEvent::DevicesChanged`] with the number of keyboards
+    /// being read whenever it changes.
+    ///
+    /// Off by default. Lets a UI show when hotkeys are unavailable because
+    /// the keyboards disconnected, and when they're back. Only implemented
+    /// by the Linux evdev backend.
+    pub fn emit_device_changes(mut self, enabled: bool) -> Self {
+        self.config.emit_device_changes = enabled;
+        self
+    }
+
+    /// Emit a single [`HotkeyEvent::Tapped`] when a hotkey is released,
+    /// instead of `Pressed` and `Released`.
+    ///
+    /// For consumers that only care that a hotkey was triggered, e.g. to
+    /// toggle something. A release emits `Tapped` only if its press was
+    /// seen, so keys already held at startup don't count. A press held
+    /// until its [`long_press`](Self::long_press) threshold emits
+    /// `LongPressed` and no `Tapped`, and one released by
+    /// [`max_hold`](Self::max_hold) emits nothing. `Repeat` isn't emitted.
+    /// `DoubleTapped` and `ChordCompleted` are still emitted on the press
+    /// that completes them, and a second press suppressed by
+    /// [`suppress_double_tap_presses`](Self::suppress_double_tap_presses)
+    /// emits no `Tapped`. Off by default.
+    pub fn tap_mode(mut self, enabled: bool) -> Self {
+        self.config.tap_mode = enabled;
+        self
+    }
+
+    /// Emit [`HotkeyEvent::DoubleTapped`] when the hotkey added at position
+    /// `idx` is pressed twice within `window`.
+    ///
+    /// Both presses still emit `Pressed` and `Released` unless
+    /// [`suppress_double_tap_presses`](Self::suppress_double_tap_presses) is
+    /// set. Only presses that match the hotkey, modifiers included, count,
+    /// and a third press starts a new double-tap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no hotkey has been added at `idx`.
+    pub fn double_tap(mut self, idx: usize, window: Duration) -> Self {
+        let (_, options) = self
+            .hotkeys
+            .get_mut(idx)
+            .unwrap_or_else(|| panic!("no hotkey added at index {}", idx));
+        options.double_tap = Some(window);
+        self
+    }
+
+    /// Emit [`HotkeyEvent::LongPressed`] once the hotkey added at position
+    /// `idx` has been held down for `threshold`.
+    ///
+    /// The press still emits `Pressed` straight away, and `Released` when it
+    /// ends; releasing it before the threshold emits nothing more. The
+    /// listener thread wakes up when the threshold is reached, without
+    /// waiting for further input. Only implemented on Linux.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no hotkey has been added at `idx`.
+    pub fn long_press(mut self, idx: usize, threshold: Duration) -> Self {
+        let (_, options) = self
+            .hotkeys
+            .get_mut(idx)
+            .unwrap_or_else(|| panic!("no hotkey added at index {}", idx));
+        options.long_press = Some(threshold);
+        self
+    }
+
+    /// Drop the `Pressed` and `Released` of the press that completes a
+    /// double-tap, so it only emits [`HotkeyEvent::DoubleTapped`].
+    ///
+    /// The first press can't be known to start a double-tap when it happens,
+    /// so it is always reported. Off by default.
+    pub fn suppress_double_tap_presses(mut self, suppress: bool) -> Self {
+        self.config.suppress_double_tap_presses = suppress;
+        self
+    }
+
+    /// Stop counting a modifier once it has been held for longer than `timeout`.
+    ///
+    /// This is the single timing knob for modifier handling, consulted by:
+    ///
+    /// - hotkey matching: a modifier held past the timeout no longer combines
+    ///   with the next key, which also guards against a lost release event
+    ///   leaving a modifier stuck;
+    /// - [`HotkeyListenerHandle::capture_next_combo`], which applies the same
+    ///   rule to the combo it returns;
+    /// - modifier-only hotkeys: holding a modifier past the timeout doesn't
+    ///   count as a tap.
+    ///
+    /// There is no timeout by default.
+    pub fn modifier_timeout(mut self, timeout: Duration) -> Self {
+        self.config.modifier_timeout = Some(timeout);
+        self
+    }
+
+    /// Give up after `attempts` consecutive failed keyboard reconnect attempts.
+    ///
+    /// By default the Linux listener keeps trying to reconnect forever after a
+    /// device error. With a limit set, it stops once the limit is reached:
+    /// [`HotkeyListenerHandle::is_running`] returns false and the event channel
+    /// is closed, so `recv` returns an error.
+    pub fn max_reconnect_attempts(mut self, attempts: u32) -> Self {
+        self.config.max_reconnect_attempts = Some(attempts);
+        self
+    }
+
+    /// Rescan for keyboards at most once per `interval` while reconnecting.
+    ///
+    /// The first rescan after a device error is immediate. Defaults to 3
+    /// seconds; a shorter interval picks up keyboards that drop out often,
+    /// like some Bluetooth ones, sooner. Linux only.
+    pub fn rescan_interval(mut self, interval: Duration) -> Self {
+        self.config.rescan_interval = Some(interval);
+        self
+    }
+
+    /// Wait `settle` after finding keyboards, on reconnect or when a new one
+    /// is plugged in, before reading them.
+    ///
+    /// Gives devices time to finish initializing. Defaults to 100ms. Linux
+    /// only.
+    pub fn reconnect_settle(mut self, settle: Duration) -> Self {
+        self.config.reconnect_settle = Some(settle);
+        self
+    }
+
+    /// Whether to rescan for keyboards, on by default.
+    ///
+    /// When off, the listener only reads the keyboards it started with: newly
+    /// connected keyboards are ignored and a device error stops it, as if
+    /// [`max_reconnect_attempts`](Self::max_reconnect_attempts) had been
+    /// reached. For callers that manage devices themselves. Linux only.
+    pub fn auto_reconnect(mut self, enabled: bool) -> Self {
+        self.config.no_rescan = !enabled;
+        self
+    }
+
+    /// Check that every keyboard can be polled before `start` returns.
+    ///
+    /// Opening a device doesn't guarantee it delivers events; some Bluetooth
+    /// keyboards open fine but report an error or hang-up when polled. With
+    /// this enabled, `start` briefly polls each keyboard and logs a warning
+    /// for any that isn't usable. It also detects keyboards another process,
+    /// like a screen locker, holds an exclusive grab on, by briefly trying to
+    /// grab each one; these are reported in
+    /// [`ListenerStatus::grabbed_keyboards`](crate::ListenerStatus). The
+    /// keyboards are still listened to. Off by default since it delays
+    /// startup. Linux only.
+    pub fn verify_devices(mut self, verify: bool) -> Self {
+        self.config.verify_devices = verify;
+        self
+    }
+
+    /// Build and start the listener even if no keyboard is connected.
+    ///
+    /// By default `build` fails when `/dev/input` has no keyboards. With this
+    /// enabled, the listener starts without any and rescans for keyboards
+    /// straight away and then every
+    /// [`rescan_interval`](Self::rescan_interval), so one plugged in later,
+    /// e.g. into a headless server, is picked up. `build` still fails if the
+    /// keyboards can't be opened because of missing permissions. Linux only.
+    pub fn allow_no_devices(mut self, allow: bool) -> Self {
+        self.config.allow_no_devices = allow;
+        self
+    }
+
+    /// Make building fail with [`HotkeyError::NoHotkeys`] if no hotkey or
+    /// chord was added.
+    ///
+    /// A listener without any can never emit an event, which usually means
+    /// the hotkey list failed to load, e.g. because of a typo in a config
+    /// file. Off by default, since hotkeys can also be added through the
+    /// handle once the listener runs.
+    ///
+    /// [`HotkeyError::NoHotkeys`]: crate::HotkeyError::NoHotkeys
+    pub fn require_hotkeys(mut self) -> Self {
+        self.config.require_hotkeys = true;
+        self
     }
 
-    /// Add a hotkey to listen for.
-    pub fn add_hotkey(mut self, hotkey: Hotkey) -> Self {
-        self.hotkeys.push(hotkey);
+    /// Only listen to keyboards in `group`, e.g. [`KeyboardGroup::External`]
+    /// to ignore the built-in laptop keyboard.
+    ///
+    /// Applies to all hotkeys and to keyboards connected later. See
+    /// [`KeyboardGroup`] for how keyboards are classified. Combined with
+    /// [`use_only`](Self::use_only), the pinned keyboard must also be in the
+    /// group. Has no effect on macOS.
+    pub fn bind_to(mut self, group: KeyboardGroup) -> Self {
+        self.config.keyboard_group = group;
+        self
+    }
+
+    /// Only listen to the given keyboard, as returned by [`list_keyboards`].
+    ///
+    /// Meant for setup flows that ask the user which keyboard to use: list the
+    /// keyboards, let the user pick one, then build a listener pinned to it.
+    /// Other keyboards are ignored, including when rescanning after a
+    /// reconnect, and `build` fails if the keyboard isn't present. The
+    /// keyboard is identified by its device path, which can change across
+    /// reboots. Has no effect on macOS.
+    ///
+    /// [`list_keyboards`]: crate::list_keyboards
+    pub fn use_only(self, keyboard: KeyboardInfo) -> Self {
+        self.use_device_path(keyboard.path)
+    }
+
+    /// Only listen to the keyboard at `path`, e.g. `/dev/input/event3`.
+    ///
+    /// Like [`use_only`](Self::use_only), for callers that stored the path
+    /// of a keyboard picked earlier. Has no effect on macOS.
+    pub fn use_device_path(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.config.pinned_keyboard = Some(path.into());
+        self
+    }
+
+    /// Open only one event node per physical keyboard.
+    ///
+    /// On by default. Many keyboards expose several event nodes sharing a
+    /// physical path, and reading all of them can report one press twice.
+    /// Of those nodes, the one with Escape and the most keys is used.
+    /// Disable this to open every node, e.g. if the hotkeys are on a node
+    /// with fewer keys, like a separate media key node. Has no effect on
+    /// macOS.
+    pub fn dedupe_devices(mut self, dedupe: bool) -> Self {
+        self.config.keep_duplicate_devices = !dedupe;
+        self
+    }
+
+    /// Open exactly these device nodes instead of scanning `/dev/input`.
+    ///
+    /// For containers where `/dev/input` is bind-mounted elsewhere, or to
+    /// restrict the listener to known event nodes. Paths without keyboard
+    /// keys are skipped like in a scan, and rescans after a reconnect
+    /// reopen the same paths. A path that can't be opened is logged and
+    /// skipped; `build` fails, listing each path's error, only if none of
+    /// them is a usable keyboard. Has no effect on macOS.
+    pub fn device_paths(mut self, paths: Vec<std::path::PathBuf>) -> Self {
+        self.config.device_paths = Some(paths);
+        self
+    }
+
+    /// Choose the input backend instead of detecting it, see [`Backend`].
+    ///
+    /// Mostly useful for testing the X11 backend on a machine where
+    /// `/dev/input` is readable. Has no effect on macOS.
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.config.backend = backend;
+        self
+    }
+
+    /// Grab the keyboards exclusively so their keys, hotkeys included, don't
+    /// reach other applications.
+    ///
+    /// Uses `EVIOCGRAB`, which hides **every** key of a grabbed keyboard, not
+    /// just the registered hotkeys. Only enable it for keyboards dedicated to
+    /// hotkeys, such as a macro pad selected with
+    /// [`use_only`](Self::use_only): grabbing the only keyboard leaves the
+    /// desktop without keyboard input until the listener stops. Grabs are
+    /// released when the listener stops and when a keyboard is dropped after
+    /// an error, and keyboards found on a rescan are grabbed again. Linux
+    /// evdev backend only; has no effect with libinput, X11 or on macOS.
+    pub fn grab_keys(mut self, grab: bool) -> Self {
+        self.config.grab_keys = grab;
+        self
+    }
+
+    /// Track which keyboard produced each event, for
+    /// [`HotkeyListenerHandle::subscribe_with_device`].
+    ///
+    /// Off by default, leaving the device of every event `None`, so the
+    /// listener doesn't copy a device path per key event nobody asked for.
+    /// Only implemented by the Linux evdev backend; rdev on macOS doesn't
+    /// say which keyboard a key came from.
+    pub fn report_devices(mut self, enabled: bool) -> Self {
+        self.config.report_devices = enabled;
+        self
+    }
+
+    /// Only listen to keyboards whose device name satisfies `filter`.
+    ///
+    /// Useful to skip devices that advertise keyboard keys without being
+    /// keyboards, like power buttons. Applies when rescanning after a
+    /// reconnect too. Has no effect on macOS.
+    pub fn include_device(mut self, filter: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.config.device_filter = Some(DeviceFilter::new(filter));
+        self
+    }
+
+    /// Only listen to keyboards whose device name contains `pattern`.
+    ///
+    /// Shorthand for [`include_device`](Self::include_device).
+    pub fn device_name_contains(self, pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        self.include_device(move |name| name.contains(&pattern))
+    }
+
+    /// Also send every event to `sender`, e.g. a channel already used in a
+    /// `crossbeam_channel::select!` loop across several sources.
+    ///
+    /// Events are sent directly from the listener thread. The sender is a
+    /// `std::sync::mpsc::Sender` by default, or a `crossbeam_channel::Sender`
+    /// with the `crossbeam` feature. The handle's own receiver still gets
+    /// every event too, and the listener stops when the handle is dropped.
+    pub fn with_sender(self, sender: Sender<HotkeyEvent>) -> Self {
+        self.config.shared.subscribers.add_sender(sender);
+        self
+    }
+
+    /// Invoke `callback` with every event instead of delivering it to the handle.
+    ///
+    /// For simple tools that don't want a receive loop. The callback runs on
+    /// a dedicated thread, so a slow callback delays later callbacks but not
+    /// the reading of the keyboards. The handle returned by `start` still
+    /// stops the listener with `stop` or when dropped, but its `recv`
+    /// methods get no events; [`subscribe`](HotkeyListenerHandle::subscribe)
+    /// still works.
+    pub fn on_event(mut self, callback: impl FnMut(HotkeyEvent) + Send + 'static) -> Self {
+        self.config.on_event = Some(EventCallback::new(callback));
+        self
+    }
+
+    /// Hold at most `capacity` events the handle hasn't received yet,
+    /// dropping new events while it's full.
+    ///
+    /// By default the channel is unbounded, so a stalled consumer makes it
+    /// grow without limit. Dropped events are counted by
+    /// [`HotkeyListenerHandle::dropped_events`], e.g. to tell the user input
+    /// is lagging. Dropping can leave a `Pressed` without its `Released`.
+    /// Subscribers and senders added with [`with_sender`](Self::with_sender)
+    /// are not bounded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn bounded(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "bounded channel capacity must be at least 1");
+        self.config.bounded = Some(capacity);
+        self
+    }
+
+    /// Make [`HotkeyListenerHandle::stop`], and dropping the handle, wait up
+    /// to `timeout` for the listener thread to exit.
+    ///
+    /// By default stopping only signals the thread, which notices within a
+    /// few hundred milliseconds and then ungrabs and closes the keyboards.
+    /// Waiting makes sure they're released before `stop` returns, e.g. so a
+    /// new listener can grab them straight away. See also
+    /// [`HotkeyListenerHandle::stop_and_join`]. Has no effect on macOS,
+    /// where rdev's listener thread can't be stopped.
+    pub fn join_on_stop(mut self, timeout: Duration) -> Self {
+        self.config.join_timeout = Some(timeout);
+        self
+    }
+
+    /// Receive every raw `evdev::InputEvent` read from the keyboards.
+    ///
+    /// The callback runs on the listener thread before hotkey matching, for
+    /// all event types including ones that aren't key events. This lets
+    /// consumers do their own processing while reusing the device discovery
+    /// and reconnection handling. Linux only, behind the `raw-evdev` feature;
+    /// the event type comes from the `evdev` crate version this crate depends
+    /// on, so upgrading `evdev` here is a breaking change for this API.
+    #[cfg(all(target_os = "linux", feature = "raw-evdev"))]
+    pub fn on_raw_evdev(mut self, callback: impl Fn(&evdev::InputEvent) + Send + 'static) -> Self {
+        self.config.raw_callback = Some(crate::linux::RawEvdevCallback::new(callback));
         self
     }
 
+    /// Describe the hotkeys and options configured so far, one per line.
+    ///
+    /// Meant for debug output and bug reports; the format is not stable.
+    pub fn describe(&self) -> String {
+        use std::fmt::Write;
+
+        let config = &self.config;
+        let or = |value: Option<String>, default: &str| value.unwrap_or_else(|| default.into());
+        let mut out = String::from("hotkeys:\n");
+        for (hotkey, options) in &self.hotkeys {
+            let _ = write!(out, "  {}: {}", options.id, hotkey);
+            if let Some(cooldown) = options.cooldown {
+                let _ = write!(out, " (cooldown {:?})", cooldown);
+            }
+            if let Some(window) = options.double_tap {
+                let _ = write!(out, " (double-tap {:?})", window);
+            }
+            if let Some(threshold) = options.long_press {
+                let _ = write!(out, " (long press {:?})", threshold);
+            }
+            if let Some(modifier_match) = options.modifier_match {
+                let _ = write!(out, " (modifiers {:?})", modifier_match);
+            }
+            out.push('\n');
+        }
+        for (idx, chord) in config.chords.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "chord {}: {} (timeout {:?})",
+                idx, chord, chord.timeout
+            );
+        }
+        let _ = writeln!(out, "alternate codes: {}", config.alternate_codes);
+        let _ = writeln!(
+            out,
+            "active schedule: {}",
+            if config.active_schedule.is_some() {
+                "set"
+            } else {
+                "none"
+            }
+        );
+        let _ = writeln!(
+            out,
+            "ghosting guard: {}",
+            or(
+                config
+                    .max_simultaneous_keys
+                    .map(|max| format!("{} keys", max)),
+                "off"
+            )
+        );
+        let _ = writeln!(
+            out,
+            "max reconnect attempts: {}",
+            or(
+                config.max_reconnect_attempts.map(|max| max.to_string()),
+                "unlimited"
+            )
+        );
+        if config.no_rescan {
+            let _ = writeln!(out, "auto reconnect: off");
+        } else {
+            let _ = writeln!(
+                out,
+                "auto reconnect: every {:?}, settle {:?}",
+                config.rescan_interval.unwrap_or(DEFAULT_RESCAN_INTERVAL),
+                config.reconnect_settle.unwrap_or(DEFAULT_RECONNECT_SETTLE)
+            );
+        }
+        let _ = writeln!(out, "modifier match: {:?}", config.modifier_match);
+        let _ = writeln!(out, "multi-device press: {:?}", config.multi_device_press);
+        let _ = writeln!(out, "backend: {:?}", config.backend);
+        let _ = writeln!(
+            out,
+            "max hold: {}",
+            or(config.max_hold.map(|max| format!("{:?}", max)), "none")
+        );
+        let _ = writeln!(
+            out,
+            "modifier lookahead: {}",
+            or(
+                config
+                    .modifier_lookahead
+                    .map(|window| format!("{:?}", window)),
+                "off"
+            )
+        );
+        let _ = writeln!(
+            out,
+            "debounce: {}",
+            or(config.debounce.map(|window| format!("{:?}", window)), "off")
+        );
+        let _ = writeln!(out, "emit repeats: {}", config.emit_repeats);
+        let _ = writeln!(out, "emit device changes: {}", config.emit_device_changes);
+        let _ = writeln!(out, "tap mode: {}", config.tap_mode);
+        let _ = writeln!(
+            out,
+            "modifier timeout: {}",
+            or(
+                config
+                    .modifier_timeout
+                    .map(|timeout| format!("{:?}", timeout)),
+                "none"
+            )
+        );
+        let _ = writeln!(out, "verify devices: {}", config.verify_devices);
+        let _ = writeln!(out, "allow no devices: {}", config.allow_no_devices);
+        let _ = writeln!(out, "require hotkeys: {}", config.require_hotkeys);
+        let _ = writeln!(out, "grab keys: {}", config.grab_keys);
+        let _ = writeln!(out, "report devices: {}", config.report_devices);
+        let _ = writeln!(out, "keyboard group: {:?}", config.keyboard_group);
+        let _ = writeln!(out, "dedupe devices: {}", !config.keep_duplicate_devices);
+        if let Some(paths) = &config.device_paths {
+            let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+            let _ = writeln!(out, "device paths: {}", paths.join(", "));
+        }
+        let _ = writeln!(
+            out,
+            "keyboards: {}",
+            or(
+                config
+                    .pinned_keyboard
+                    .as_ref()
+                    .map(|path| path.display().to_string()),
+                "all"
+            )
+        );
+        let _ = writeln!(
+            out,
+            "channel capacity: {}",
+            or(
+                config.bounded.map(|capacity| capacity.to_string()),
+                "unbounded"
+            )
+        );
+        let _ = writeln!(
+            out,
+            "join on stop: {}",
+            or(
+                config.join_timeout.map(|timeout| format!("{:?}", timeout)),
+                "no"
+            )
+        );
+        let _ = writeln!(
+            out,
+            "event callback: {}",
+            if config.on_event.is_some() {
+                "set"
+            } else {
+                "none"
+            }
+        );
+        let _ = writeln!(
+            out,
+            "device filter: {}",
+            if config.device_filter.is_some() {
+                "set"
+            } else {
+                "none"
+            }
+        );
+        let _ = writeln!(
+            out,
+            "recorded events: {}",
+            or(
+                config
+                    .shared
+                    .recorder
+                    .as_ref()
+                    .map(|recorder| format!("last {}", recorder.capacity)),
+                "off"
+            )
+        );
+        #[cfg(all(target_os = "linux", feature = "raw-evdev"))]
+        let _ = writeln!(
+            out,
+            "raw evdev callback: {}",
+            if config.raw_callback.is_some() {
+                "set"
+            } else {
+                "none"
+            }
+        );
+        out
+    }
+
+    /// Split the registered hotkeys from their options, which go into the config.
+    ///
+    /// Fails if hotkeys are required and there are none.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn into_parts(self) -> Result<(Vec<Hotkey>, ListenerConfig)> {
+        if self.config.require_hotkeys && self.hotkeys.is_empty() && self.config.chords.is_empty() {
+            return Err(crate::HotkeyError::NoHotkeys.into());
+        }
+        let (hotkeys, hotkey_options): (Vec<Hotkey>, _) = self.hotkeys.into_iter().unzip();
+        crate::layout::warn_on_layout_mismatch(&hotkeys, crate::layout::detect_layout);
+        self.config.shared.updates.registered(hotkeys.len());
+        let config = ListenerConfig {
+            hotkey_options,
+            ..self.config
+        };
+        Ok((hotkeys, config))
+    }
+
+    /// Build a calloop event source instead of a threaded listener.
+    ///
+    /// The source reads the keyboards from the calloop loop it is inserted
+    /// into, see [`HotkeySource`](crate::HotkeySource). Linux only, behind the
+    /// `calloop` feature.
+    #[cfg(all(target_os = "linux", feature = "calloop"))]
+    pub fn build_calloop_source(self) -> Result<crate::HotkeySource> {
+        let (hotkeys, config) = self.into_parts()?;
+        crate::calloop::HotkeySource::discover(hotkeys, config)
+    }
+
     /// Build the listener.
     #[cfg(target_os = "linux")]
     pub fn build(self) -> Result<HotkeyListener> {
-        let keyboards = crate::linux::find_keyboards()?;
+        let (hotkeys, config) = self.into_parts()?;
         Ok(HotkeyListener {
-            inner: crate::linux::HotkeyListener::new(keyboards, self.hotkeys),
+            shared: config.shared.clone(),
+            on_event: config.on_event.clone(),
+            bounded: config.bounded,
+            join_timeout: config.join_timeout,
+            inner: crate::linux::HotkeyListener::discover(hotkeys, config)?,
         })
     }
 
     /// Build the listener.
     #[cfg(target_os = "macos")]
     pub fn build(self) -> Result<HotkeyListener> {
+        let (hotkeys, config) = self.into_parts()?;
+        if let Some(hotkey) = hotkeys
+            .iter()
+            .find(|hotkey| matches!(hotkey.key, Some(Key::Raw(_))))
+        {
+            return Err(crate::HotkeyError::InvalidHotkey(format!(
+                "{}: raw keycodes are only supported on Linux",
+                hotkey
+            ))
+            .into());
+        }
         Ok(HotkeyListener {
-            inner: crate::macos::HotkeyListener::new(self.hotkeys),
+            shared: config.shared.clone(),
+            on_event: config.on_event.clone(),
+            bounded: config.bounded,
+            join_timeout: config.join_timeout,
+            inner: crate::macos::HotkeyListener::new(hotkeys, config),
         })
     }
 
+    /// Build a listener reading a [`MockKeyboard`](crate::MockKeyboard)
+    /// instead of the keyboards in `/dev/input`.
+    ///
+    /// For testing an application's hotkey handling without input devices,
+    /// e.g. in CI: keys pushed to the returned keyboard go through the same
+    /// matching as real ones. Linux only, behind the `test-util` feature.
+    #[cfg(all(target_os = "linux", feature = "test-util"))]
+    pub fn build_mock(self) -> Result<(HotkeyListener, crate::MockKeyboard)> {
+        let (hotkeys, config) = self.into_parts()?;
+        let keyboard = crate::MockKeyboard::new();
+        let listener = HotkeyListener {
+            shared: config.shared.clone(),
+            on_event: config.on_event.clone(),
+            bounded: config.bounded,
+            join_timeout: config.join_timeout,
+            inner: crate::linux::HotkeyListener::with_mock(keyboard.clone(), hotkeys, config),
+        };
+        Ok((listener, keyboard))
+    }
+
+    /// Build the listener and report on the environment it starts in.
+    ///
+    /// Like [`build`](Self::build), but also returns a [`StartupReport`]
+    /// with the keyboards found, whether `/dev/input` permissions are
+    /// missing, hotkeys the platform can't detect and other warnings, for
+    /// display in a diagnostics panel. If building fails, only the error is
+    /// returned; the report's warnings are still logged at debug level.
+    ///
+    /// [`StartupReport`]: crate::StartupReport
+    pub fn build_with_report(self) -> Result<(HotkeyListener, crate::StartupReport)> {
+        let hotkeys: Vec<Hotkey> = self
+            .hotkeys
+            .iter()
+            .map(|(hotkey, _)| hotkey.clone())
+            .collect();
+        let report = crate::report::StartupReport::collect(&hotkeys);
+        for warning in &report.warnings {
+            log::debug!("Startup check: {}", warning);
+        }
+        Ok((self.build()?, report))
+    }
+
     /// Build the listener (unsupported platform stub).
     #[cfg(not(any(target_os = "linux", target_os = "macos")))]
     pub fn build(self) -> Result<HotkeyListener> {
@@ -58,18 +1659,66 @@ pub struct HotkeyListener {
     inner: crate::macos::HotkeyListener,
     #[cfg(not(any(target_os = "linux", target_os = "macos")))]
     inner: (),
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    shared: Shared,
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    on_event: Option<EventCallback>,
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    bounded: Option<usize>,
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    join_timeout: Option<Duration>,
 }
 
 impl HotkeyListener {
+    /// Create a listener that replays a capture recorded with `evemu-record`.
+    ///
+    /// Key events from the capture are fed through the same matching logic as
+    /// live keyboards, with their original timing, so a recording of a
+    /// misbehaving hotkey becomes a reproducible test. The event channel closes
+    /// once the capture has been replayed.
+    #[cfg(target_os = "linux")]
+    pub fn from_evemu(path: impl AsRef<std::path::Path>, hotkeys: Vec<Hotkey>) -> Result<Self> {
+        let config = ListenerConfig::default();
+        Ok(Self {
+            shared: config.shared.clone(),
+            on_event: None,
+            bounded: None,
+            join_timeout: None,
+            inner: crate::linux::HotkeyListener::from_evemu(path.as_ref(), hotkeys, config)?,
+        })
+    }
+
     /// Start listening for hotkeys in a background thread.
     ///
     /// Returns a [`HotkeyListenerHandle`] that receives hotkey events.
     /// The background thread automatically stops when the handle is dropped.
+    ///
+    /// On macOS, a failure to install the keyboard event tap is returned as a
+    /// [`HotkeyError`](crate::HotkeyError), usually
+    /// [`EventTap`](crate::HotkeyError::EventTap) when the process lacks
+    /// Accessibility permission. This makes `start` wait briefly for `rdev`
+    /// to report such failures.
     #[cfg(any(target_os = "linux", target_os = "macos"))]
     pub fn start(self) -> Result<HotkeyListenerHandle> {
         let running = Arc::new(AtomicBool::new(true));
-        let rx = self.inner.start(Arc::clone(&running))?;
-        Ok(HotkeyListenerHandle { running, rx })
+        // Before the backend thread exists, so its exit always reports last
+        self.shared.status.started();
+        let mut rx = match self.inner.start(Arc::clone(&running)) {
+            Ok(rx) => rx,
+            Err(e) => {
+                self.shared.status.stopped();
+                return Err(e);
+            }
+        };
+        if let Some(callback) = self.on_event {
+            rx = forward_to_callback(rx, Arc::clone(&running), callback);
+        }
+        if let Some(capacity) = self.bounded {
+            rx = forward_bounded(rx, capacity, Arc::clone(&self.shared.dropped));
+        }
+        let mut handle = HotkeyListenerHandle::new(running, rx, self.shared);
+        handle.join_timeout = self.join_timeout;
+        Ok(handle)
     }
 
     /// Start listening (unsupported platform stub).
@@ -115,24 +1764,413 @@ impl HotkeyListener {
 pub struct HotkeyListenerHandle {
     running: Arc<AtomicBool>,
     rx: Receiver<HotkeyEvent>,
+    peeked: Cell<Option<HotkeyEvent>>,
+    shared: Shared,
+    join_timeout: Option<Duration>,
 }
 
 impl HotkeyListenerHandle {
+    pub(crate) fn new(running: Arc<AtomicBool>, rx: Receiver<HotkeyEvent>, shared: Shared) -> Self {
+        Self {
+            running,
+            rx,
+            peeked: Cell::new(None),
+            shared,
+            join_timeout: None,
+        }
+    }
+
     /// Block until the next hotkey event.
     pub fn recv(&self) -> Result<HotkeyEvent, RecvError> {
-        self.rx.recv()
+        if let Some(event) = self.peeked.take() {
+            return Ok(event);
+        }
+        self.rx.recv().map_err(|_| RecvError)
     }
 
     /// Wait for the next hotkey event with a timeout.
     pub fn recv_timeout(&self, timeout: Duration) -> Result<HotkeyEvent, RecvTimeoutError> {
-        self.rx.recv_timeout(timeout)
+        if let Some(event) = self.peeked.take() {
+            return Ok(event);
+        }
+        self.channel_recv_timeout(timeout)
     }
 
     /// Try to receive a hotkey event without blocking.
-    pub fn try_recv(&self) -> Result<HotkeyEvent, std::sync::mpsc::TryRecvError> {
+    pub fn try_recv(&self) -> Result<HotkeyEvent, TryRecvError> {
+        if let Some(event) = self.peeked.take() {
+            return Ok(event);
+        }
+        self.channel_try_recv()
+    }
+
+    /// Block until a hotkey is pressed and return the index its `Pressed`
+    /// event carries.
+    ///
+    /// Every other event, releases included, is received and discarded.
+    /// Fails once the listener has stopped.
+    pub fn wait_for_press(&self) -> Result<usize, RecvError> {
+        loop {
+            if let HotkeyEvent::Pressed(idx) = self.recv()? {
+                return Ok(idx);
+            }
+        }
+    }
+
+    /// Like [`wait_for_press`](Self::wait_for_press), but give up after `timeout`.
+    ///
+    /// Events discarded while waiting don't extend the timeout.
+    pub fn wait_for_press_timeout(&self, timeout: Duration) -> Result<usize, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if let HotkeyEvent::Pressed(idx) = self.recv_timeout(remaining)? {
+                return Ok(idx);
+            }
+        }
+    }
+
+    /// Iterate over events, blocking for each one like [`recv`](Self::recv).
+    ///
+    /// The iterator ends once the listener has stopped and every event has
+    /// been received, like [`std::sync::mpsc::Receiver::iter`].
+    ///
+    /// ```no_run
+    /// # fn main() -> anyhow::Result<()> {
+    /// use hotkey_listener::{parse_hotkey, HotkeyEvent, HotkeyListenerBuilder};
+    ///
+    /// let handle = HotkeyListenerBuilder::new()
+    ///     .add_hotkey(parse_hotkey("Shift+F8")?)
+    ///     .build()?
+    ///     .start()?;
+    /// for event in handle.iter() {
+    ///     if let HotkeyEvent::Pressed(idx) = event {
+    ///         println!("Hotkey {} pressed", idx);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = HotkeyEvent> + '_ {
+        std::iter::from_fn(|| self.recv().ok())
+    }
+
+    /// Iterate over the events already pending, without blocking.
+    ///
+    /// Like [`std::sync::mpsc::Receiver::try_iter`], the iterator ends at
+    /// the first [`try_recv`](Self::try_recv) that finds no event.
+    pub fn try_iter(&self) -> impl Iterator<Item = HotkeyEvent> + '_ {
+        std::iter::from_fn(|| self.try_recv().ok())
+    }
+
+    /// How many events were dropped because the handle's channel was full.
+    ///
+    /// Always 0 unless the listener was built with
+    /// [`HotkeyListenerBuilder::bounded`].
+    pub fn dropped_events(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Look at the next pending event without consuming it.
+    ///
+    /// Returns `None` if no event is pending. The peeked event is buffered in
+    /// the handle and returned by the next `recv`, `recv_timeout` or `try_recv`
+    /// call, giving lookahead-by-one semantics. This assumes the handle is the
+    /// only consumer of the channel; events taken directly from `receiver`,
+    /// with the `crossbeam` feature, bypass the buffer.
+    pub fn peek(&self) -> Option<HotkeyEvent> {
+        if self.peeked.get().is_none() {
+            self.peeked.set(self.channel_try_recv().ok());
+        }
+        self.peeked.get()
+    }
+
+    #[cfg(not(feature = "crossbeam"))]
+    fn channel_recv_timeout(&self, timeout: Duration) -> Result<HotkeyEvent, RecvTimeoutError> {
+        self.rx.recv_timeout(timeout)
+    }
+
+    #[cfg(feature = "crossbeam")]
+    fn channel_recv_timeout(&self, timeout: Duration) -> Result<HotkeyEvent, RecvTimeoutError> {
+        self.rx.recv_timeout(timeout).map_err(|e| match e {
+            crossbeam_channel::RecvTimeoutError::Timeout => RecvTimeoutError::Timeout,
+            crossbeam_channel::RecvTimeoutError::Disconnected => RecvTimeoutError::Disconnected,
+        })
+    }
+
+    #[cfg(not(feature = "crossbeam"))]
+    fn channel_try_recv(&self) -> Result<HotkeyEvent, TryRecvError> {
         self.rx.try_recv()
     }
 
+    #[cfg(feature = "crossbeam")]
+    fn channel_try_recv(&self) -> Result<HotkeyEvent, TryRecvError> {
+        self.rx.try_recv().map_err(|e| match e {
+            crossbeam_channel::TryRecvError::Empty => TryRecvError::Empty,
+            crossbeam_channel::TryRecvError::Disconnected => TryRecvError::Disconnected,
+        })
+    }
+
+    /// The underlying crossbeam receiver, for use with `crossbeam_channel::select!`.
+    ///
+    /// Only available with the `crossbeam` feature. The listener thread still
+    /// stops when this handle is dropped, so keep the handle alive while
+    /// selecting on the receiver. An event buffered by [`peek`](Self::peek) is
+    /// not visible through the receiver.
+    #[cfg(feature = "crossbeam")]
+    pub fn receiver(&self) -> &crossbeam_channel::Receiver<HotkeyEvent> {
+        &self.rx
+    }
+
+    /// Wait for the user to press a key combo and return it as a [`Hotkey`].
+    ///
+    /// The next press of a supported key is captured together with the
+    /// modifiers held at the time, e.g. holding Shift and Ctrl and pressing F8
+    /// returns `Ctrl+Shift+F8`. The captured press doesn't trigger registered
+    /// hotkeys. Modifier presses on their own are not captured. Fails if no
+    /// combo is pressed within `timeout`.
+    pub fn capture_next_combo(&self, timeout: Duration) -> Result<Hotkey> {
+        let rx = self.shared.capture.arm();
+        match rx.recv_timeout(timeout) {
+            Ok(hotkey) => Ok(hotkey),
+            Err(_) => {
+                self.shared.capture.disarm();
+                anyhow::bail!("No key combo pressed within {:?}", timeout)
+            }
+        }
+    }
+
+    /// Watch the listener status for changes.
+    ///
+    /// The receiver reflects whether the listener is running, how many
+    /// keyboards it reads and the last device error, so async UIs can
+    /// `.changed().await` instead of polling [`is_running`](Self::is_running).
+    /// Only available with the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub fn status(&self) -> tokio::sync::watch::Receiver<crate::ListenerStatus> {
+        self.shared.status.subscribe()
+    }
+
+    /// Feed a key event into the matching as if it came from a keyboard.
+    ///
+    /// Lets other input sources, like an on-screen keyboard, trigger the
+    /// registered hotkeys with normal `Pressed`/`Released` events. Modifiers
+    /// can't be injected, so they are taken from the physical keyboards.
+    /// Only implemented by the Linux keyboard and libinput backends.
+    pub fn inject_key(&self, key: Key, pressed: bool) {
+        self.shared.injector.inject(key, pressed);
+    }
+
+    /// Register another hotkey while the listener runs, returning its index.
+    ///
+    /// Indices are never reused: the new hotkey gets the index after the
+    /// last one ever registered, whether added to the builder or here, and
+    /// its events carry that index. Takes effect on the listener thread's
+    /// next key event.
+    ///
+    /// The index isn't checked against ids given to
+    /// [`HotkeyListenerBuilder::add_hotkey_with_id`], so if the builder
+    /// assigned ids that can reach the indices handed out here, events of
+    /// the two hotkeys can't be told apart. Pick such ids from a range above
+    /// any index the handle will assign, e.g. starting at `u32::MAX / 2`.
+    pub fn add_hotkey(&self, hotkey: Hotkey) -> usize {
+        self.shared.updates.add(hotkey)
+    }
+
+    /// Stop matching the hotkey at `idx` while the listener runs.
+    ///
+    /// The index becomes a tombstone: other hotkeys keep their indices, and
+    /// `idx` isn't given to a hotkey added later. A hotkey held when it's
+    /// removed still gets its `Released`. Unknown indices are ignored.
+    pub fn remove_hotkey(&self, idx: usize) {
+        self.shared.updates.remove(idx);
+    }
+
+    /// Suspend or resume the hotkey at `idx` without unregistering it.
+    ///
+    /// A disabled hotkey keeps its index and settings but emits no
+    /// `Pressed` or `Repeat` events, e.g. while a modal dialog is open. A
+    /// press emitted before disabling still gets its `Released`, so
+    /// presses stay paired. Enabling it again resumes matching from the
+    /// next key press. Hotkeys are enabled by default.
+    pub fn set_hotkey_enabled(&self, idx: usize, enabled: bool) {
+        self.shared.disabled.set_enabled(idx, enabled);
+    }
+
+    /// Mute or unmute every hotkey at once, e.g. while the application
+    /// doesn't have focus.
+    ///
+    /// Like disabling each hotkey with
+    /// [`set_hotkey_enabled`](Self::set_hotkey_enabled), but the keyboards
+    /// stay open and modifiers are still tracked while muted, so a modifier
+    /// held across re-enabling counts. Presses emitted before muting still
+    /// get their `Released`. The listener is enabled by default.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.shared.paused.set(!enabled);
+    }
+
+    /// Whether the listener is emitting events, see
+    /// [`set_enabled`](Self::set_enabled).
+    pub fn is_enabled(&self) -> bool {
+        !self.shared.paused.is_paused()
+    }
+
+    /// Emit `emit_idx` for the hotkey at `hotkey_idx` from now on.
+    ///
+    /// `hotkey_idx` is the position the hotkey was added to the builder in,
+    /// and `emit_idx` replaces the index or id its events carry. This lets
+    /// an app swap two actions without rebinding keys: map each hotkey to
+    /// the other's index. Applies to the next event, so change the mapping
+    /// while the hotkey isn't held or its `Released` carries the new index.
+    pub fn set_event_index(&self, hotkey_idx: usize, emit_idx: usize) {
+        self.shared.remap.set(hotkey_idx, emit_idx);
+    }
+
+    /// The modifiers currently held, as last seen by the listener thread.
+    ///
+    /// Polls the live state, e.g. for a diagnostics overlay, instead of
+    /// reconstructing it from events. Only presses the listener saw count,
+    /// so a modifier already held when it started shows up once pressed
+    /// again. Always empty with the X11 backend, which only sees grabbed
+    /// combinations.
+    pub fn current_modifiers(&self) -> Modifiers {
+        self.shared.held_modifiers.get()
+    }
+
+    /// Make the backend treat its keyboards as disconnected.
+    ///
+    /// The keyboards are dropped and the listener goes through the same
+    /// rescan and reconnect path as after a device error, so reconnection
+    /// handling can be exercised without unplugging hardware. Only
+    /// implemented by the Linux keyboard backend, behind the `test-util`
+    /// feature.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn simulate_disconnect(&self) {
+        self.shared.disconnect.raise();
+    }
+
+    /// Receive a copy of every event emitted from now on.
+    ///
+    /// Each subscriber gets its own channel, independent of this handle and
+    /// of other subscribers, so several components can observe the hotkeys.
+    /// The receiver is a `std::sync::mpsc::Receiver`, or a
+    /// `crossbeam_channel::Receiver` with the `crossbeam` feature. It stops
+    /// receiving events once the handle is dropped.
+    pub fn subscribe(&self) -> Receiver<HotkeyEvent> {
+        self.shared.subscribers.subscribe(false)
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but first queue a `Pressed`
+    /// event for every hotkey currently held.
+    ///
+    /// A subscriber joining mid-run, like a UI component opened while a
+    /// push-to-talk key is down, then starts from the correct state and
+    /// receives the matching `Released` later.
+    pub fn subscribe_with_snapshot(&self) -> Receiver<HotkeyEvent> {
+        self.shared.subscribers.subscribe(true)
+    }
+
+    /// Receive a copy of every event emitted from now on, with the time its
+    /// key transition happened.
+    ///
+    /// For measuring reaction times: the timestamp comes from the input
+    /// event itself, not from when the event was received. Otherwise like
+    /// [`subscribe`](Self::subscribe).
+    pub fn subscribe_timed(&self) -> Receiver<TimedHotkeyEvent> {
+        self.shared.subscribers.subscribe_timed()
+    }
+
+    /// Receive a copy of every event emitted from now on, with the keyboard
+    /// that produced it.
+    ///
+    /// Tells apart e.g. a macro pad and the main keyboard. Devices are only
+    /// tracked when enabled with
+    /// [`HotkeyListenerBuilder::report_devices`]; see
+    /// [`DeviceHotkeyEvent::device`] for when it is `None`. Otherwise like
+    /// [`subscribe`](Self::subscribe).
+    pub fn subscribe_with_device(&self) -> Receiver<DeviceHotkeyEvent> {
+        self.shared.subscribers.subscribe_with_device()
+    }
+
+    /// The events emitted so far, oldest first, with the time they were emitted.
+    ///
+    /// Empty unless [`HotkeyListenerBuilder::record_events`] was set. Only the
+    /// most recent events up to the configured capacity are kept, including
+    /// ones not yet received from this handle.
+    pub fn recorded_events(&self) -> Vec<(Instant, HotkeyEvent)> {
+        self.shared
+            .recorder
+            .as_ref()
+            .map_or_else(Vec::new, EventRecorder::events)
+    }
+
+    /// Turn this handle into a `futures::Stream` of events.
+    ///
+    /// Works with any executor, see [`HotkeyEventStream`](crate::HotkeyEventStream).
+    /// Only available with the `futures` feature.
+    #[cfg(feature = "futures")]
+    pub fn into_stream(self) -> crate::HotkeyEventStream {
+        crate::stream::HotkeyEventStream::new(self)
+    }
+
+    /// Turn this handle into a tokio channel of events.
+    ///
+    /// `recv().await` waits for the next event without blocking the runtime.
+    /// A forwarding thread owns the handle, so dropping the receiver stops
+    /// the listener shortly after, and `recv` returns `None` once the
+    /// listener has stopped. Only available with the `tokio` feature.
+    ///
+    /// ```no_run
+    /// # async fn run() -> anyhow::Result<()> {
+    /// use hotkey_listener::{parse_hotkey, HotkeyListenerBuilder};
+    ///
+    /// let handle = HotkeyListenerBuilder::new()
+    ///     .add_hotkey(parse_hotkey("Shift+F8")?)
+    ///     .build()?
+    ///     .start()?;
+    /// let mut events = handle.into_tokio_receiver();
+    /// while let Some(event) = events.recv().await {
+    ///     println!("{:?}", event);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn into_tokio_receiver(self) -> tokio::sync::mpsc::UnboundedReceiver<HotkeyEvent> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let closed = tx.clone();
+        self.forward(
+            move || closed.is_closed(),
+            move |event| tx.send(event).is_ok(),
+        );
+        rx
+    }
+
+    /// Pass events to `send` on a background thread until it fails, `closed`
+    /// returns true or the listener stops, then drop the handle.
+    #[cfg(any(feature = "futures", feature = "tokio"))]
+    pub(crate) fn forward(
+        self,
+        closed: impl Fn() -> bool + Send + 'static,
+        mut send: impl FnMut(HotkeyEvent) -> bool + Send + 'static,
+    ) {
+        std::thread::spawn(move || {
+            while !closed() {
+                match self.recv_timeout(FORWARD_CHECK_INTERVAL) {
+                    Ok(event) => {
+                        if !send(event) {
+                            break;
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            // Stop the listener before the receiving side sees its end
+            drop(self);
+        });
+    }
+
     /// Check if the listener is still running.
     pub fn is_running(&self) -> bool {
         self.running.load(Ordering::SeqCst)
@@ -140,14 +2178,391 @@ impl HotkeyListenerHandle {
 
     /// Manually stop the listener.
     ///
-    /// This is called automatically when the handle is dropped.
+    /// This is called automatically when the handle is dropped. Returns
+    /// straight away unless [`HotkeyListenerBuilder::join_on_stop`] was set,
+    /// in which case it first waits for the listener thread to exit.
     pub fn stop(&self) {
         self.running.store(false, Ordering::SeqCst);
+        if let Some(timeout) = self.join_timeout {
+            if !self.shared.thread.join(Some(timeout)) {
+                log::warn!("Listener thread still running {:?} after stop", timeout);
+            }
+        }
+    }
+
+    /// Stop the listener and wait for its thread to exit.
+    ///
+    /// Once this returns the keyboards have been ungrabbed and closed, so
+    /// another listener can grab them. On macOS, where rdev's listener
+    /// thread can't be stopped, this returns straight away.
+    pub fn stop_and_join(self) {
+        self.running.store(false, Ordering::SeqCst);
+        self.shared.thread.join(None);
     }
 }
 
 impl Drop for HotkeyListenerHandle {
     fn drop(&mut self) {
-        self.running.store(false, Ordering::SeqCst);
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::{self, Sender};
+
+    fn test_handle() -> (Sender<HotkeyEvent>, HotkeyListenerHandle) {
+        let (tx, rx) = channel::channel();
+        (
+            tx,
+            HotkeyListenerHandle::new(Arc::new(AtomicBool::new(true)), rx, Shared::default()),
+        )
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_tokio_receiver_yields_events_until_listener_stops() {
+        let (tx, rx) = channel::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let handle = HotkeyListenerHandle::new(Arc::clone(&running), rx, Shared::default());
+        let mut events = handle.into_tokio_receiver();
+        let wait = Duration::from_secs(1);
+
+        tx.send(HotkeyEvent::Pressed(0)).unwrap();
+        let event = tokio::time::timeout(wait, events.recv()).await.unwrap();
+        assert_eq!(event, Some(HotkeyEvent::Pressed(0)));
+
+        drop(tx);
+        let event = tokio::time::timeout(wait, events.recv()).await.unwrap();
+        assert_eq!(event, None);
+        assert!(!running.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_with_sender_receives_events() {
+        let (tx, rx) = channel::channel();
+        let builder = HotkeyListenerBuilder::new().with_sender(tx);
+        let shared = &builder.config.shared;
+
+        shared.record(HotkeyEvent::Pressed(0), SystemTime::now(), None, None);
+        shared.record(HotkeyEvent::Released(0), SystemTime::now(), None, None);
+        assert_eq!(rx.try_recv().unwrap(), HotkeyEvent::Pressed(0));
+        assert_eq!(rx.try_recv().unwrap(), HotkeyEvent::Released(0));
+
+        // A dropped receiver is forgotten
+        drop(rx);
+        shared.record(HotkeyEvent::Pressed(0), SystemTime::now(), None, None);
+        assert!(shared.subscribers.0.lock().unwrap().senders.is_empty());
+    }
+
+    #[test]
+    fn test_stop_and_join_waits_for_listener_thread() {
+        let (_tx, rx) = channel::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let exited = Arc::new(AtomicBool::new(false));
+        let shared = Shared::default();
+        shared.thread.set(std::thread::spawn({
+            let running = Arc::clone(&running);
+            let exited = Arc::clone(&exited);
+            move || {
+                while running.load(Ordering::SeqCst) {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                exited.store(true, Ordering::SeqCst);
+            }
+        }));
+        assert!(!shared.thread.join(Some(Duration::from_millis(20))));
+
+        let handle = HotkeyListenerHandle::new(Arc::clone(&running), rx, shared);
+        handle.stop_and_join();
+        assert!(exited.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_bounded_channel_counts_dropped_events() {
+        let (tx, rx) = channel::channel();
+        let shared = Shared::default();
+        let rx = forward_bounded(rx, 2, Arc::clone(&shared.dropped));
+        let handle = HotkeyListenerHandle::new(Arc::new(AtomicBool::new(true)), rx, shared);
+
+        for idx in 0..5 {
+            tx.send(HotkeyEvent::Pressed(idx)).unwrap();
+        }
+        let start = Instant::now();
+        while handle.dropped_events() < 3 && start.elapsed() < Duration::from_secs(1) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(handle.dropped_events(), 3);
+
+        // The oldest events were kept
+        assert_eq!(handle.try_recv(), Ok(HotkeyEvent::Pressed(0)));
+        assert_eq!(handle.try_recv(), Ok(HotkeyEvent::Pressed(1)));
+        assert_eq!(handle.try_recv(), Err(TryRecvError::Empty));
+
+        // Room was made, so the next event gets through
+        tx.send(HotkeyEvent::Released(0)).unwrap();
+        assert_eq!(
+            handle.recv_timeout(Duration::from_secs(1)),
+            Ok(HotkeyEvent::Released(0))
+        );
+        assert_eq!(handle.dropped_events(), 3);
+    }
+
+    #[test]
+    fn test_callback_receives_events_on_its_own_thread() {
+        let (tx, rx) = channel::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let (seen_tx, seen_rx) = mpsc::channel();
+        let callback = EventCallback::new(move |event| {
+            seen_tx.send((event, std::thread::current().id())).unwrap();
+        });
+        let handle_rx = forward_to_callback(rx, Arc::clone(&running), callback);
+
+        tx.send(HotkeyEvent::Pressed(0)).unwrap();
+        tx.send(HotkeyEvent::Released(0)).unwrap();
+        let timeout = Duration::from_secs(1);
+        let (first, thread) = seen_rx.recv_timeout(timeout).unwrap();
+        assert_eq!(first, HotkeyEvent::Pressed(0));
+        assert_ne!(thread, std::thread::current().id());
+        assert_eq!(
+            seen_rx.recv_timeout(timeout).unwrap().0,
+            HotkeyEvent::Released(0)
+        );
+
+        // Once stopped, later events are dropped and the handle's receiver closes
+        running.store(false, Ordering::SeqCst);
+        tx.send(HotkeyEvent::Pressed(1)).unwrap();
+        assert!(handle_rx.recv().is_err());
+        assert!(seen_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_subscribe_with_snapshot_replays_held_hotkeys() {
+        let (_tx, handle) = test_handle();
+        let shared = &handle.shared;
+        let plain = handle.subscribe();
+
+        shared.record(HotkeyEvent::Pressed(0), SystemTime::now(), None, None);
+        shared.record(HotkeyEvent::Pressed(1), SystemTime::now(), None, None);
+        shared.record(HotkeyEvent::Released(1), SystemTime::now(), None, None);
+
+        let late = handle.subscribe_with_snapshot();
+        assert_eq!(late.try_recv().unwrap(), HotkeyEvent::Pressed(0));
+        assert!(late.try_recv().is_err());
+        assert!(handle.subscribe().try_recv().is_err());
+
+        shared.record(HotkeyEvent::Released(0), SystemTime::now(), None, None);
+        assert_eq!(late.try_recv().unwrap(), HotkeyEvent::Released(0));
+        let events: Vec<HotkeyEvent> = plain.try_iter().collect();
+        assert_eq!(
+            events,
+            vec![
+                HotkeyEvent::Pressed(0),
+                HotkeyEvent::Pressed(1),
+                HotkeyEvent::Released(1),
+                HotkeyEvent::Released(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe_reflects_options() {
+        let description = HotkeyListenerBuilder::new()
+            .add_hotkey(crate::parse_hotkey("Shift+F8").unwrap())
+            .add_hotkey_with_id(crate::parse_hotkey("F9").unwrap(), 42)
+            .ghosting_guard(4)
+            .modifier_timeout(Duration::from_millis(750))
+            .add_chord(Chord::new(
+                [
+                    crate::parse_hotkey("Ctrl+F1").unwrap(),
+                    crate::parse_hotkey("F2").unwrap(),
+                ],
+                Duration::from_secs(1),
+            ))
+            .describe();
+
+        assert!(description.contains("  0: Shift+F8\n"));
+        assert!(description.contains("  42: F9\n"));
+        assert!(description.contains("chord 0: Ctrl+F1 then F2 (timeout 1s)\n"));
+        assert!(description.contains("ghosting guard: 4 keys\n"));
+        assert!(description.contains("modifier timeout: 750ms\n"));
+        assert!(description.contains("max reconnect attempts: unlimited\n"));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn test_require_hotkeys_rejects_empty_list() {
+        // Checked before discovery, so this doesn't depend on the machine's keyboards
+        let no_hotkeys = |builder: HotkeyListenerBuilder| {
+            builder
+                .into_parts()
+                .err()
+                .and_then(|error| error.downcast_ref::<crate::HotkeyError>().cloned())
+                == Some(crate::HotkeyError::NoHotkeys)
+        };
+        assert!(no_hotkeys(HotkeyListenerBuilder::new().require_hotkeys()));
+        // Either a hotkey or a chord satisfies it
+        assert!(!no_hotkeys(
+            HotkeyListenerBuilder::new()
+                .require_hotkeys()
+                .add_hotkey(crate::parse_hotkey("F8").unwrap())
+        ));
+        assert!(!no_hotkeys(
+            HotkeyListenerBuilder::new()
+                .require_hotkeys()
+                .add_chord(Chord::new(
+                    [
+                        crate::parse_hotkey("Ctrl+F1").unwrap(),
+                        crate::parse_hotkey("F2").unwrap(),
+                    ],
+                    Duration::from_secs(1),
+                ))
+        ));
+        // Off by default
+        assert!(!no_hotkeys(HotkeyListenerBuilder::new()));
+    }
+
+    #[test]
+    fn test_modifier_timeout_propagates_to_config() {
+        let builder = HotkeyListenerBuilder::new().modifier_timeout(Duration::from_millis(750));
+        assert_eq!(
+            builder.config.modifier_timeout,
+            Some(Duration::from_millis(750))
+        );
+    }
+
+    #[test]
+    fn test_reconnect_options_propagate_to_config() {
+        let config = HotkeyListenerBuilder::new().config;
+        assert_eq!(config.rescan_interval, None);
+        assert_eq!(config.reconnect_settle, None);
+        assert!(!config.no_rescan);
+
+        let builder = HotkeyListenerBuilder::new()
+            .rescan_interval(Duration::from_millis(500))
+            .reconnect_settle(Duration::from_millis(20))
+            .auto_reconnect(false);
+        assert_eq!(
+            builder.config.rescan_interval,
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            builder.config.reconnect_settle,
+            Some(Duration::from_millis(20))
+        );
+        assert!(builder.config.no_rescan);
+        assert!(builder.describe().contains("auto reconnect: off\n"));
+        assert!(HotkeyListenerBuilder::new()
+            .describe()
+            .contains("auto reconnect: every 3s, settle 100ms\n"));
+    }
+
+    #[test]
+    fn test_wait_for_press_skips_other_events() {
+        let (tx, handle) = test_handle();
+        tx.send(HotkeyEvent::Released(0)).unwrap();
+        tx.send(HotkeyEvent::Repeat(0)).unwrap();
+        tx.send(HotkeyEvent::Pressed(2)).unwrap();
+        tx.send(HotkeyEvent::Released(2)).unwrap();
+
+        assert_eq!(handle.wait_for_press(), Ok(2));
+        assert_eq!(
+            handle.wait_for_press_timeout(Duration::from_millis(20)),
+            Err(RecvTimeoutError::Timeout)
+        );
+
+        drop(tx);
+        assert_eq!(handle.wait_for_press(), Err(RecvError));
+        assert_eq!(
+            handle.wait_for_press_timeout(Duration::from_millis(20)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_iter_ends_when_channel_closes() {
+        let (tx, handle) = test_handle();
+        tx.send(HotkeyEvent::Pressed(0)).unwrap();
+        tx.send(HotkeyEvent::Released(0)).unwrap();
+
+        assert_eq!(handle.peek(), Some(HotkeyEvent::Pressed(0)));
+        assert_eq!(
+            handle.try_iter().collect::<Vec<_>>(),
+            vec![HotkeyEvent::Pressed(0), HotkeyEvent::Released(0)]
+        );
+        assert_eq!(handle.try_iter().next(), None);
+
+        tx.send(HotkeyEvent::Pressed(1)).unwrap();
+        drop(tx);
+        assert_eq!(
+            handle.iter().collect::<Vec<_>>(),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+    }
+
+    #[test]
+    fn test_peek_does_not_consume() {
+        let (tx, handle) = test_handle();
+        assert_eq!(handle.peek(), None);
+
+        tx.send(HotkeyEvent::Pressed(0)).unwrap();
+        tx.send(HotkeyEvent::Released(0)).unwrap();
+        assert_eq!(handle.peek(), Some(HotkeyEvent::Pressed(0)));
+        assert_eq!(handle.peek(), Some(HotkeyEvent::Pressed(0)));
+        assert_eq!(handle.recv(), Ok(HotkeyEvent::Pressed(0)));
+        assert_eq!(handle.peek(), Some(HotkeyEvent::Released(0)));
+        assert_eq!(handle.try_recv(), Ok(HotkeyEvent::Released(0)));
+        assert_eq!(handle.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn test_recv_timeout_returns_peeked_event() {
+        let (tx, handle) = test_handle();
+        tx.send(HotkeyEvent::Pressed(1)).unwrap();
+        drop(tx);
+
+        assert_eq!(handle.peek(), Some(HotkeyEvent::Pressed(1)));
+        assert_eq!(
+            handle.recv_timeout(Duration::from_millis(10)),
+            Ok(HotkeyEvent::Pressed(1))
+        );
+        assert_eq!(
+            handle.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[cfg(all(feature = "crossbeam", target_os = "linux"))]
+    #[test]
+    fn test_events_flow_over_crossbeam_channel() {
+        use crate::hotkey::parse_hotkey;
+
+        let path =
+            std::env::temp_dir().join(format!("hotkey-crossbeam-{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "E: 0.000001 0001 0042 0001\nE: 0.000002 0001 0042 0000\n",
+        )
+        .unwrap();
+
+        let handle = HotkeyListener::from_evemu(&path, vec![parse_hotkey("F8").unwrap()])
+            .unwrap()
+            .start()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let (_other_tx, other_rx) = crossbeam_channel::unbounded::<()>();
+        let mut events = Vec::new();
+        while events.len() < 2 {
+            crossbeam_channel::select! {
+                recv(handle.receiver()) -> event => events.push(event.unwrap()),
+                recv(other_rx) -> _ => unreachable!(),
+            }
+        }
+        assert_eq!(
+            events,
+            vec![HotkeyEvent::Pressed(0), HotkeyEvent::Released(0)]
+        );
     }
 }
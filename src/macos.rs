@@ -1,18 +1,29 @@
 //! macOS implementation using rdev.
 
+use crate::channel::{self, Receiver, Sender};
+use crate::error::HotkeyError;
 use crate::event::HotkeyEvent;
-use crate::hotkey::{Hotkey, Modifiers};
+use crate::hotkey::{Hotkey, ModifierSide};
 use crate::key::Key;
+use crate::listener::ListenerConfig;
+use crate::matcher::{HotkeyMatcher, Modifier};
 use anyhow::Result;
-use rdev::{listen, Event, EventType};
+use rdev::{listen, Event, EventType, ListenError};
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{self, Receiver, Sender};
-use std::sync::Arc;
+use std::sync::{mpsc, Arc};
 use std::thread;
+use std::time::{Duration, Instant};
 
-/// Convert our platform-agnostic Key to rdev Key.
-fn to_rdev_key(key: Key) -> rdev::Key {
-    match key {
+/// How long `start` waits for `rdev::listen` to fail before assuming it's running.
+///
+/// `listen` blocks for as long as it runs and only returns on failure, which
+/// happens right away when the event tap can't be created.
+const STARTUP_GRACE: Duration = Duration::from_millis(200);
+
+/// Convert our platform-agnostic Key to rdev Key. Raw evdev keycodes have none.
+fn to_rdev_key(key: Key) -> Option<rdev::Key> {
+    let key = match key {
         Key::F1 => rdev::Key::F1,
         Key::F2 => rdev::Key::F2,
         Key::F3 => rdev::Key::F3,
@@ -25,107 +36,266 @@ fn to_rdev_key(key: Key) -> rdev::Key {
         Key::F10 => rdev::Key::F10,
         Key::F11 => rdev::Key::F11,
         Key::F12 => rdev::Key::F12,
+        Key::Escape => rdev::Key::Escape,
+        Key::PrintScreen => rdev::Key::PrintScreen,
         Key::ScrollLock => rdev::Key::ScrollLock,
         Key::Pause => rdev::Key::Pause,
         Key::Insert => rdev::Key::Insert,
+        // rdev has no variant for the application key, macOS keycode 110
+        Key::Menu => rdev::Key::Unknown(110),
+        Key::Up => rdev::Key::UpArrow,
+        Key::Down => rdev::Key::DownArrow,
+        Key::Left => rdev::Key::LeftArrow,
+        Key::Right => rdev::Key::RightArrow,
+        Key::Home => rdev::Key::Home,
+        Key::End => rdev::Key::End,
+        Key::PageUp => rdev::Key::PageUp,
+        Key::PageDown => rdev::Key::PageDown,
+        Key::Delete => rdev::Key::Delete,
+        Key::Numpad0 => rdev::Key::Kp0,
+        Key::Numpad1 => rdev::Key::Kp1,
+        Key::Numpad2 => rdev::Key::Kp2,
+        Key::Numpad3 => rdev::Key::Kp3,
+        Key::Numpad4 => rdev::Key::Kp4,
+        Key::Numpad5 => rdev::Key::Kp5,
+        Key::Numpad6 => rdev::Key::Kp6,
+        Key::Numpad7 => rdev::Key::Kp7,
+        Key::Numpad8 => rdev::Key::Kp8,
+        Key::Numpad9 => rdev::Key::Kp9,
+        Key::NumpadEnter => rdev::Key::KpReturn,
+        Key::NumpadPlus => rdev::Key::KpPlus,
+        Key::NumpadMinus => rdev::Key::KpMinus,
+        Key::NumpadMultiply => rdev::Key::KpMultiply,
+        Key::NumpadDivide => rdev::Key::KpDivide,
+        Key::A => rdev::Key::KeyA,
+        Key::B => rdev::Key::KeyB,
+        Key::C => rdev::Key::KeyC,
+        Key::D => rdev::Key::KeyD,
+        Key::E => rdev::Key::KeyE,
+        Key::F => rdev::Key::KeyF,
+        Key::G => rdev::Key::KeyG,
+        Key::H => rdev::Key::KeyH,
+        Key::I => rdev::Key::KeyI,
+        Key::J => rdev::Key::KeyJ,
+        Key::K => rdev::Key::KeyK,
+        Key::L => rdev::Key::KeyL,
+        Key::M => rdev::Key::KeyM,
+        Key::N => rdev::Key::KeyN,
+        Key::O => rdev::Key::KeyO,
+        Key::P => rdev::Key::KeyP,
+        Key::Q => rdev::Key::KeyQ,
+        Key::R => rdev::Key::KeyR,
+        Key::S => rdev::Key::KeyS,
+        Key::T => rdev::Key::KeyT,
+        Key::U => rdev::Key::KeyU,
+        Key::V => rdev::Key::KeyV,
+        Key::W => rdev::Key::KeyW,
+        Key::X => rdev::Key::KeyX,
+        Key::Y => rdev::Key::KeyY,
+        Key::Z => rdev::Key::KeyZ,
+        Key::Digit0 => rdev::Key::Num0,
+        Key::Digit1 => rdev::Key::Num1,
+        Key::Digit2 => rdev::Key::Num2,
+        Key::Digit3 => rdev::Key::Num3,
+        Key::Digit4 => rdev::Key::Num4,
+        Key::Digit5 => rdev::Key::Num5,
+        Key::Digit6 => rdev::Key::Num6,
+        Key::Digit7 => rdev::Key::Num7,
+        Key::Digit8 => rdev::Key::Num8,
+        Key::Digit9 => rdev::Key::Num9,
+        Key::Raw(_) => return None,
+    };
+    Some(key)
+}
+
+/// The modifier an rdev key controls and its side. Right Option is `AltGr`.
+fn to_modifier(key: rdev::Key) -> Option<(Modifier, ModifierSide)> {
+    use ModifierSide::{Left, Right};
+    match key {
+        rdev::Key::ShiftLeft => Some((Modifier::Shift, Left)),
+        rdev::Key::ShiftRight => Some((Modifier::Shift, Right)),
+        rdev::Key::ControlLeft => Some((Modifier::Ctrl, Left)),
+        rdev::Key::ControlRight => Some((Modifier::Ctrl, Right)),
+        rdev::Key::Alt => Some((Modifier::Alt, Left)),
+        rdev::Key::AltGr => Some((Modifier::Alt, Right)),
+        rdev::Key::MetaLeft => Some((Modifier::Meta, Left)),
+        rdev::Key::MetaRight => Some((Modifier::Meta, Right)),
+        _ => None,
+    }
+}
+
+/// Our key for an rdev key, if it's one hotkeys can use.
+fn from_rdev_key(key: rdev::Key) -> Option<Key> {
+    Key::all()
+        .iter()
+        .copied()
+        .find(|&k| to_rdev_key(k) == Some(key))
+}
+
+/// Warn about a hotkey macOS can't detect.
+fn warn_unsupported(hotkey: &Hotkey) {
+    if hotkey.key.is_some_and(|key| to_rdev_key(key).is_none()) {
+        log::warn!("Raw keycode hotkey {} is not supported on macOS", hotkey);
+    }
+    if hotkey.num_lock.is_some() || hotkey.caps_lock.is_some() {
+        log::warn!("Lock state of hotkey {} is not known on macOS", hotkey);
+    }
+}
+
+/// Map an rdev listen failure to our typed error.
+fn listen_error(error: ListenError) -> HotkeyError {
+    match error {
+        ListenError::EventTapError => HotkeyError::EventTap,
+        ListenError::LoopSourceError => HotkeyError::LoopSource,
+        other => HotkeyError::Platform(format!("{:?}", other)),
     }
 }
 
 /// macOS hotkey listener using rdev.
 pub struct HotkeyListener {
     hotkeys: Vec<Hotkey>,
+    config: ListenerConfig,
 }
 
 impl HotkeyListener {
-    /// Create a new listener with the given hotkeys.
-    pub fn new(hotkeys: Vec<Hotkey>) -> Self {
-        Self { hotkeys }
+    /// Create a new listener with the given hotkeys and options.
+    pub fn new(hotkeys: Vec<Hotkey>, config: ListenerConfig) -> Self {
+        Self { hotkeys, config }
     }
 
     /// Start listening for hotkeys in a background thread.
-    /// Returns a receiver for hotkey events.
+    /// Returns a receiver for hotkey events, or a [`HotkeyError`] if
+    /// `rdev::listen` fails during startup.
     pub fn start(self, running: Arc<AtomicBool>) -> Result<Receiver<HotkeyEvent>> {
-        let (tx, rx) = mpsc::channel();
-        start_keyboard_listener(self.hotkeys, running, tx);
-        Ok(rx)
+        let (tx, rx) = channel::channel();
+        let failed = start_keyboard_listener(self.hotkeys, self.config, running, tx);
+        match failed.recv_timeout(STARTUP_GRACE) {
+            Ok(error) => Err(error.into()),
+            Err(_) => Ok(rx),
+        }
     }
 }
 
 fn start_keyboard_listener(
     hotkeys: Vec<Hotkey>,
+    config: ListenerConfig,
     running: Arc<AtomicBool>,
     tx: Sender<HotkeyEvent>,
-) {
-    // Convert hotkeys to rdev keys
-    let rdev_hotkeys: Vec<(rdev::Key, Modifiers)> = hotkeys
-        .iter()
-        .map(|h| (to_rdev_key(h.key), h.modifiers))
-        .collect();
+) -> mpsc::Receiver<HotkeyError> {
+    let (failed_tx, failed_rx) = mpsc::channel();
+    hotkeys.iter().for_each(warn_unsupported);
 
     thread::spawn(move || {
-        let mut current_mods = Modifiers::default();
+        let _stopped = config.shared.status.stop_on_drop();
+        let mut matcher = HotkeyMatcher::new(hotkeys, config);
+        // rdev repeats KeyPress while a key is held
+        let mut held_keys: HashSet<rdev::Key> = HashSet::new();
 
         let callback = move |event: Event| {
-            match event.event_type {
-                // Track modifier state
+            matcher.set_event_time(Some(event.time));
+            let events = match event.event_type {
                 EventType::KeyPress(key) => {
-                    match key {
-                        rdev::Key::ShiftLeft | rdev::Key::ShiftRight => {
-                            current_mods.shift = true;
-                        }
-                        rdev::Key::ControlLeft | rdev::Key::ControlRight => {
-                            current_mods.ctrl = true;
+                    let repeat = !held_keys.insert(key);
+                    match (to_modifier(key), from_rdev_key(key)) {
+                        // Repeats leave modifier state untouched
+                        (Some(_), _) if repeat => Vec::new(),
+                        (Some((modifier, side)), _) => {
+                            matcher.on_modifier(modifier, side, true, Instant::now())
                         }
-                        rdev::Key::Alt => {
-                            current_mods.alt = true;
-                        }
-                        _ => {}
-                    }
-
-                    // Check each hotkey
-                    for (idx, (hotkey_key, hotkey_mods)) in rdev_hotkeys.iter().enumerate() {
-                        if key == *hotkey_key {
-                            let mods_match = current_mods.shift == hotkey_mods.shift
-                                && current_mods.ctrl == hotkey_mods.ctrl
-                                && current_mods.alt == hotkey_mods.alt;
-
-                            if mods_match {
-                                let _ = tx.send(HotkeyEvent::Pressed(idx));
+                        (None, Some(key)) if repeat => matcher.on_key_repeat(key),
+                        (None, Some(key)) => matcher.on_key_down(key, Instant::now()),
+                        (None, None) => {
+                            if !repeat {
+                                matcher.on_other_key();
                             }
+                            Vec::new()
                         }
                     }
                 }
                 EventType::KeyRelease(key) => {
-                    match key {
-                        rdev::Key::ShiftLeft | rdev::Key::ShiftRight => {
-                            current_mods.shift = false;
-                        }
-                        rdev::Key::ControlLeft | rdev::Key::ControlRight => {
-                            current_mods.ctrl = false;
-                        }
-                        rdev::Key::Alt => {
-                            current_mods.alt = false;
-                        }
-                        _ => {}
-                    }
-
-                    // Check each hotkey for release
-                    for (idx, (hotkey_key, hotkey_mods)) in rdev_hotkeys.iter().enumerate() {
-                        if key == *hotkey_key {
-                            // For release, we don't check modifiers since they might
-                            // have been released before the key
-                            let _ = tx.send(HotkeyEvent::Released(idx));
-                            let _ = hotkey_mods; // suppress unused warning
+                    held_keys.remove(&key);
+                    match (to_modifier(key), from_rdev_key(key)) {
+                        (Some((modifier, side)), _) => {
+                            matcher.on_modifier(modifier, side, false, Instant::now())
                         }
+                        (None, Some(key)) => matcher.on_key_up(key),
+                        (None, None) => Vec::new(),
                     }
                 }
-                _ => {}
+                _ => Vec::new(),
+            };
+            for event in events {
+                let _ = tx.send(event);
             }
         };
 
         if let Err(e) = listen(callback) {
-            log::error!("Error listening to keyboard events: {:?}", e);
+            let error = listen_error(e);
+            log::error!("{}", error);
             running.store(false, Ordering::SeqCst);
+            // Nobody is waiting once startup has completed
+            let _ = failed_tx.send(error);
         }
     });
+
+    failed_rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_key_maps_to_distinct_rdev_key() {
+        let mut seen = Vec::new();
+        for &key in Key::all() {
+            let rdev_key = to_rdev_key(key).unwrap();
+            assert!(!seen.contains(&rdev_key), "{} shares {:?}", key, rdev_key);
+            seen.push(rdev_key);
+        }
+    }
+
+    #[test]
+    fn test_numpad_rdev_keys() {
+        assert_eq!(to_rdev_key(Key::Numpad5), Some(rdev::Key::Kp5));
+        assert_eq!(to_rdev_key(Key::NumpadEnter), Some(rdev::Key::KpReturn));
+        assert_eq!(to_rdev_key(Key::NumpadPlus), Some(rdev::Key::KpPlus));
+        assert_eq!(to_rdev_key(Key::NumpadMinus), Some(rdev::Key::KpMinus));
+        assert_eq!(
+            to_rdev_key(Key::NumpadMultiply),
+            Some(rdev::Key::KpMultiply)
+        );
+        assert_eq!(to_rdev_key(Key::NumpadDivide), Some(rdev::Key::KpDivide));
+        assert_eq!(from_rdev_key(rdev::Key::Kp5), Some(Key::Numpad5));
+        assert_eq!(from_rdev_key(rdev::Key::Num5), Some(Key::Digit5));
+    }
+
+    #[test]
+    fn test_rdev_keys_translate_back() {
+        for &key in Key::all() {
+            assert_eq!(from_rdev_key(to_rdev_key(key).unwrap()), Some(key));
+        }
+        assert_eq!(from_rdev_key(rdev::Key::CapsLock), None);
+        assert_eq!(
+            to_modifier(rdev::Key::AltGr),
+            Some((Modifier::Alt, ModifierSide::Right))
+        );
+        assert_eq!(to_modifier(rdev::Key::KeyA), None);
+    }
+
+    #[test]
+    fn test_listen_error_maps_to_typed_error() {
+        assert_eq!(
+            listen_error(ListenError::EventTapError),
+            HotkeyError::EventTap
+        );
+        assert_eq!(
+            listen_error(ListenError::LoopSourceError),
+            HotkeyError::LoopSource
+        );
+        assert!(matches!(
+            listen_error(ListenError::KeyHookError(5)),
+            HotkeyError::Platform(_)
+        ));
+    }
 }
@@ -0,0 +1,2034 @@
+//! Platform-agnostic hotkey matching.
+
+use crate::event::HotkeyEvent;
+use crate::hotkey::{Hotkey, ModifierSide, Modifiers};
+use crate::key::Key;
+use crate::listener::{HotkeyUpdate, ListenerConfig, ModifierMatch, MultiDevicePress};
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+
+/// A modifier key reported by a platform backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+    /// Super/Windows/Command.
+    Meta,
+}
+
+/// Drops repeated presses of a key that arrive within the window without a
+/// release in between.
+///
+/// Switch bounce shows up as a second press with no release, while a fast
+/// deliberate double-tap always has a release between its presses, so it's
+/// never suppressed however quick it is.
+#[derive(Debug, Default)]
+pub(crate) struct Debouncer {
+    window: Option<Duration>,
+    /// When each key was last accepted as pressed, until it's released.
+    down: HashMap<Key, Instant>,
+}
+
+impl Debouncer {
+    pub fn new(window: Option<Duration>) -> Self {
+        Self {
+            window,
+            down: HashMap::new(),
+        }
+    }
+
+    /// Whether a press of `key` should be processed.
+    pub fn press(&mut self, key: Key, at: Instant) -> bool {
+        let Some(window) = self.window else {
+            return true;
+        };
+        if let Some(&last) = self.down.get(&key) {
+            if at.saturating_duration_since(last) < window {
+                log::debug!("Debounced repeated press of {}", key);
+                return false;
+            }
+        }
+        self.down.insert(key, at);
+        true
+    }
+
+    pub fn release(&mut self, key: Key) {
+        self.down.remove(&key);
+    }
+
+    pub fn reset(&mut self) {
+        self.down.clear();
+    }
+}
+
+/// Hotkeys whose `Pressed` was emitted and that await their `Released`.
+///
+/// Backends only release hotkeys tracked here, so with both `F8` and
+/// `Shift+F8` registered, releasing F8 releases whichever one fired.
+#[derive(Debug, Default)]
+pub(crate) struct ActiveHotkeys {
+    /// How many presses of each hotkey are outstanding, e.g. across keyboards.
+    counts: Vec<u32>,
+    /// When the first outstanding press of each hotkey was emitted.
+    since: Vec<Option<Instant>>,
+}
+
+impl ActiveHotkeys {
+    pub fn new(len: usize) -> Self {
+        Self {
+            counts: vec![0; len],
+            since: vec![None; len],
+        }
+    }
+
+    /// Note an emitted press, returning how many presses are now outstanding.
+    pub fn press(&mut self, idx: usize, at: Instant) -> u32 {
+        self.counts[idx] += 1;
+        self.since[idx].get_or_insert(at);
+        self.counts[idx]
+    }
+
+    /// Note a release, returning how many presses remain outstanding, or
+    /// `None` if the hotkey wasn't pressed.
+    pub fn release(&mut self, idx: usize) -> Option<u32> {
+        if self.counts[idx] == 0 {
+            return None;
+        }
+        self.counts[idx] -= 1;
+        if self.counts[idx] == 0 {
+            self.since[idx] = None;
+        }
+        Some(self.counts[idx])
+    }
+
+    /// Track one more hotkey, added at the end.
+    pub fn push(&mut self) {
+        self.counts.push(0);
+        self.since.push(None);
+    }
+
+    pub fn is_pressed(&self, idx: usize) -> bool {
+        self.counts[idx] > 0
+    }
+
+    /// When the hotkey's first outstanding press was emitted, if it's pressed.
+    pub fn pressed_since(&self, idx: usize) -> Option<Instant> {
+        self.since[idx]
+    }
+
+    /// Forget every outstanding press of the hotkey, returning how many there were.
+    pub fn clear(&mut self, idx: usize) -> u32 {
+        self.since[idx] = None;
+        std::mem::take(&mut self.counts[idx])
+    }
+}
+
+/// Tracks modifier state and matches key transitions against registered hotkeys.
+///
+/// Backends translate their native key events into [`Key`]s and [`Modifier`]s
+/// and feed them here, so matching behaves identically on every platform.
+pub(crate) struct HotkeyMatcher {
+    hotkeys: Vec<Hotkey>,
+    /// Indices of the plain key hotkeys, grouped by key in index order.
+    by_key: HashMap<Key, Vec<usize>>,
+    /// Hotkeys removed through the handle. Their indices stay taken.
+    removed: Vec<bool>,
+    config: ListenerConfig,
+    current_mods: Modifiers,
+    /// When each held modifier was pressed, indexed by [`Modifier`] and
+    /// [`ModifierSide`].
+    held_since: [[Option<Instant>; 2]; 4],
+    /// Modifier combination being tapped and the time of each consecutive tap.
+    tap_mods: Modifiers,
+    tap_times: Vec<Instant>,
+    /// Modifier-only hotkeys that fired and await their release.
+    tapped: Vec<usize>,
+    /// When each hotkey last fired, for cooldowns.
+    last_fired: Vec<Option<Instant>>,
+    /// The press that may start a double-tap, for hotkeys configured with one.
+    double_tap_start: Vec<Option<Instant>>,
+    /// The press each hotkey last emitted `LongPressed` for.
+    long_pressed: Vec<Option<Instant>>,
+    /// Recent presses counted towards each sequence hotkey.
+    sequence_times: Vec<Vec<Instant>>,
+    /// Steps of each chord pressed so far, and when the last one was.
+    chord_progress: Vec<Option<(usize, Instant)>>,
+    active: ActiveHotkeys,
+    debouncer: Debouncer,
+    /// NumLock state, once a backend has reported it.
+    num_lock: Option<bool>,
+    /// CapsLock state, once a backend has reported it.
+    caps_lock: Option<bool>,
+    /// When the input being matched happened, if the backend knows.
+    event_time: Option<SystemTime>,
+    /// The keyboard the input being matched came from, if tracked.
+    event_device: Option<String>,
+}
+
+impl HotkeyMatcher {
+    /// Create a matcher for the given hotkeys and listener options.
+    pub fn new(hotkeys: Vec<Hotkey>, config: ListenerConfig) -> Self {
+        let mut by_key: HashMap<Key, Vec<usize>> = HashMap::new();
+        for (idx, hotkey) in hotkeys.iter().enumerate() {
+            if let Some(key) = hotkey.key.filter(|_| is_plain_key(hotkey)) {
+                by_key.entry(key).or_default().push(idx);
+            }
+        }
+        Self {
+            by_key,
+            active: ActiveHotkeys::new(hotkeys.len()),
+            removed: vec![false; hotkeys.len()],
+            debouncer: Debouncer::new(config.debounce),
+            num_lock: None,
+            caps_lock: None,
+            event_time: None,
+            event_device: None,
+            sequence_times: vec![Vec::new(); hotkeys.len()],
+            chord_progress: vec![None; config.chords.len()],
+            last_fired: vec![None; hotkeys.len()],
+            double_tap_start: vec![None; hotkeys.len()],
+            long_pressed: vec![None; hotkeys.len()],
+            hotkeys,
+            config,
+            current_mods: Modifiers::default(),
+            held_since: [[None; 2]; 4],
+            tap_mods: Modifiers::default(),
+            tap_times: Vec::new(),
+            tapped: Vec::new(),
+        }
+    }
+
+    /// Update the tracked modifier state, returning events for modifier-only hotkeys.
+    pub fn on_modifier(
+        &mut self,
+        modifier: Modifier,
+        side: ModifierSide,
+        pressed: bool,
+        at: Instant,
+    ) -> Vec<HotkeyEvent> {
+        self.apply_updates();
+        let was_idle = self.current_mods == Modifiers::default();
+        let held_since = std::mem::replace(
+            &mut self.held_since[modifier as usize][side as usize],
+            pressed.then_some(at),
+        );
+        // A modifier stays held until both of its keys are released
+        let held = self.held_since[modifier as usize]
+            .iter()
+            .any(Option::is_some);
+        match modifier {
+            Modifier::Shift => self.current_mods.shift = held,
+            Modifier::Ctrl => self.current_mods.ctrl = held,
+            Modifier::Alt => self.current_mods.alt = held,
+            Modifier::Meta => self.current_mods.meta = held,
+        }
+        self.config.shared.held_modifiers.set(self.current_mods);
+
+        if !pressed {
+            // Holding a modifier past the timeout isn't a tap
+            if held_since.is_some_and(|since| self.timed_out(since, at)) {
+                self.tap_times.clear();
+            }
+            if self.current_mods == Modifiers::default() {
+                let tapped: Vec<usize> = self.tapped.drain(..).collect();
+                return self.emit_released(tapped);
+            }
+            return Vec::new();
+        }
+
+        // A tap starts from no modifiers held; adding a modifier to a held one
+        // extends the current tap instead of counting as a new one
+        if was_idle {
+            if self.current_mods != self.tap_mods {
+                self.tap_times.clear();
+            }
+            self.tap_times.push(at);
+        } else if let Some(last) = self.tap_times.last_mut() {
+            *last = at;
+        }
+        self.tap_mods = self.current_mods;
+
+        self.match_taps(at)
+    }
+
+    /// Timestamp the events emitted from now on with `time`, the moment the
+    /// input being fed in happened, or with the time of emission if `None`.
+    pub fn set_event_time(&mut self, time: Option<SystemTime>) {
+        self.event_time = time;
+    }
+
+    /// Attribute the events emitted from now on to the keyboard at `device`.
+    pub fn set_event_device(&mut self, device: Option<String>) {
+        self.event_device = device;
+    }
+
+    /// Record the NumLock state for hotkeys conditioned on it.
+    pub fn set_num_lock(&mut self, on: bool) {
+        self.num_lock = Some(on);
+    }
+
+    /// Record the CapsLock state for hotkeys conditioned on it.
+    pub fn set_caps_lock(&mut self, on: bool) {
+        self.caps_lock = Some(on);
+    }
+
+    /// Handle a key press, returning the events for any matching hotkeys.
+    pub fn on_key_down(&mut self, key: Key, at: Instant) -> Vec<HotkeyEvent> {
+        self.apply_updates();
+        if !self.debouncer.press(key, at) {
+            return Vec::new();
+        }
+        self.tap_times.clear();
+        let mods = self.effective_mods(at);
+        if self
+            .config
+            .shared
+            .capture
+            .offer(Hotkey::with_modifiers(key, mods))
+        {
+            return Vec::new();
+        }
+        let mut matched = self.matching(key, mods, at);
+        matched.extend(self.match_sequences(key, mods, at));
+        let mut matched = self.throttle(matched, at);
+        let double_tapped = self.match_double_taps(&matched, at);
+        if self.config.suppress_double_tap_presses {
+            matched.retain(|idx| !double_tapped.contains(idx));
+        }
+        // A hotkey already held on another keyboard is one logical press
+        let coalesce = self.config.multi_device_press == MultiDevicePress::Coalesce;
+        matched.retain(|&idx| {
+            let outstanding = self.active.press(idx, at);
+            !coalesce || outstanding == 1
+        });
+        let chords = self.match_chords(key, mods, at);
+        let mut events = self.emit_pressed(matched);
+        events.extend(self.emit(double_tapped, HotkeyEvent::DoubleTapped));
+        events.extend(
+            chords
+                .into_iter()
+                .map(|idx| self.stamp(HotkeyEvent::ChordCompleted(idx), None)),
+        );
+        events
+    }
+
+    /// Advance chords whose next step was pressed, returning those completed.
+    ///
+    /// A step out of order, or after the chord's timeout, starts it over.
+    fn match_chords(&mut self, key: Key, mods: Modifiers, at: Instant) -> Vec<usize> {
+        let active = self.config.is_active(SystemTime::now());
+        let progress: Vec<usize> = self
+            .config
+            .chords
+            .iter()
+            .zip(&self.chord_progress)
+            .map(|(chord, progress)| {
+                let done = match *progress {
+                    Some((done, last)) if at.saturating_duration_since(last) <= chord.timeout => {
+                        done
+                    }
+                    _ => 0,
+                };
+                let step_matches = |step: usize| {
+                    chord.steps.get(step).is_some_and(|hotkey| {
+                        self.combo_matches(hotkey, key, mods, at, self.config.modifier_match)
+                    })
+                };
+                if step_matches(done) {
+                    done + 1
+                } else if done > 0 && step_matches(0) {
+                    1
+                } else {
+                    0
+                }
+            })
+            .collect();
+
+        let mut completed = Vec::new();
+        for (idx, done) in progress.into_iter().enumerate() {
+            self.chord_progress[idx] = if done == self.config.chords[idx].steps.len() {
+                if active {
+                    completed.push(idx);
+                }
+                None
+            } else {
+                (done > 0).then_some((done, at))
+            };
+        }
+        completed
+    }
+
+    /// Count presses of hotkeys configured for double-taps, returning those
+    /// pressed for the second time within their window.
+    fn match_double_taps(&mut self, matched: &[usize], at: Instant) -> Vec<usize> {
+        let mut fired = Vec::new();
+        for &idx in matched {
+            let Some(window) = self.config.double_tap(idx) else {
+                continue;
+            };
+            let start = self.double_tap_start[idx]
+                .filter(|&start| at.saturating_duration_since(start) <= window);
+            if start.is_some() {
+                self.double_tap_start[idx] = None;
+                fired.push(idx);
+            } else {
+                self.double_tap_start[idx] = Some(at);
+            }
+        }
+        fired
+    }
+
+    /// Handle an auto-repeat of a held key, returning `Repeat` events for the
+    /// hotkeys it holds down if repeats are enabled.
+    pub fn on_key_repeat(&mut self, key: Key) -> Vec<HotkeyEvent> {
+        if !self.config.emit_repeats
+            || self.config.tap_mode
+            || !self.config.is_active(SystemTime::now())
+        {
+            return Vec::new();
+        }
+        let repeating: Vec<usize> = self
+            .hotkeys
+            .iter()
+            .enumerate()
+            .filter(|&(idx, hotkey)| {
+                self.active.is_pressed(idx) && self.is_enabled(idx) && hotkey.key == Some(key)
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        self.emit(repeating, HotkeyEvent::Repeat)
+    }
+
+    /// Whether a hotkey or chord step is bound to `key`.
+    pub fn binds(&mut self, key: Key) -> bool {
+        self.apply_updates();
+        self.hotkeys.iter().any(|hotkey| hotkey.key == Some(key))
+            || self
+                .config
+                .chords
+                .iter()
+                .any(|chord| chord.steps.iter().any(|step| step.key == Some(key)))
+    }
+
+    /// Note a press of a key that isn't a modifier or a registered [`Key`].
+    ///
+    /// Any such key interrupts a sequence of modifier taps or key presses.
+    pub fn on_other_key(&mut self) {
+        self.tap_times.clear();
+        self.sequence_times.iter_mut().for_each(Vec::clear);
+        self.chord_progress.fill(None);
+    }
+
+    /// Handle a key release, returning the events for any matching hotkeys.
+    ///
+    /// Only hotkeys whose press was emitted are released, whatever the
+    /// modifiers are now. A release for a key that was already held when
+    /// listening started, or whose press was suppressed, emits nothing.
+    pub fn on_key_up(&mut self, key: Key) -> Vec<HotkeyEvent> {
+        self.debouncer.release(key);
+        let coalesce = self.config.multi_device_press == MultiDevicePress::Coalesce;
+        let mut released = Vec::new();
+        for (idx, hotkey) in self.hotkeys.iter().enumerate() {
+            if hotkey.key != Some(key) {
+                continue;
+            }
+            // A long press isn't a tap
+            let since = self.active.pressed_since(idx);
+            let long_pressed = since.is_some() && self.long_pressed[idx] == since;
+            match self.active.release(idx) {
+                Some(_) if self.config.tap_mode && long_pressed => {}
+                Some(0) => released.push(idx),
+                Some(_) if !coalesce => released.push(idx),
+                _ => {}
+            }
+        }
+        self.emit_released(released)
+    }
+
+    /// Release hotkeys held for longer than the configured maximum.
+    ///
+    /// Backends call this periodically so a stuck key can't hold a hotkey
+    /// forever. The real release that may follow is then an orphan and
+    /// emits nothing.
+    pub fn release_stuck(&mut self, now: Instant) -> Vec<HotkeyEvent> {
+        let Some(max_hold) = self.config.max_hold else {
+            return Vec::new();
+        };
+        let mut released = Vec::new();
+        for idx in 0..self.hotkeys.len() {
+            let pressed_since = self.active.pressed_since(idx);
+            if pressed_since.is_some_and(|at| now.saturating_duration_since(at) > max_hold) {
+                log::warn!(
+                    "Hotkey {} held for more than {:?}, releasing it",
+                    self.hotkeys[idx],
+                    max_hold
+                );
+                // Keep every emitted press paired with a release
+                let outstanding = self.active.clear(idx);
+                let count = match self.config.multi_device_press {
+                    MultiDevicePress::Coalesce => 1,
+                    MultiDevicePress::Separate => outstanding,
+                };
+                released.extend(std::iter::repeat_n(idx, count as usize));
+            }
+        }
+        // Held this long, it wasn't a tap
+        if self.config.tap_mode {
+            return Vec::new();
+        }
+        self.emit(released, HotkeyEvent::Released)
+    }
+
+    /// Emit `LongPressed` for hotkeys held for at least their long-press
+    /// threshold at `now`, once per press.
+    pub fn poll_long_presses(&mut self, now: Instant) -> Vec<HotkeyEvent> {
+        let active = self.config.is_active(SystemTime::now());
+        let mut long_pressed = Vec::new();
+        for idx in 0..self.hotkeys.len() {
+            let Some(threshold) = self.config.long_press(idx) else {
+                continue;
+            };
+            let Some(since) = self.active.pressed_since(idx) else {
+                continue;
+            };
+            if self.long_pressed[idx] != Some(since)
+                && now.saturating_duration_since(since) >= threshold
+            {
+                // A press that reaches the threshold while muted or disabled
+                // is used up, so the listener doesn't keep polling for it
+                self.long_pressed[idx] = Some(since);
+                if active && self.is_enabled(idx) {
+                    long_pressed.push(idx);
+                }
+            }
+        }
+        self.emit(long_pressed, HotkeyEvent::LongPressed)
+    }
+
+    /// When the next `LongPressed` is due, if a hotkey with a long-press
+    /// threshold is held.
+    pub fn next_long_press(&self) -> Option<Instant> {
+        (0..self.hotkeys.len())
+            .filter_map(|idx| {
+                let threshold = self.config.long_press(idx)?;
+                let since = self.active.pressed_since(idx)?;
+                (self.long_pressed[idx] != Some(since)).then_some(since + threshold)
+            })
+            .min()
+    }
+
+    /// Forget all held modifiers, e.g. after the keyboards were reconnected.
+    pub fn reset(&mut self) {
+        self.current_mods = Modifiers::default();
+        self.config.shared.held_modifiers.set(self.current_mods);
+        self.held_since = [[None; 2]; 4];
+        self.tap_times.clear();
+        self.sequence_times.iter_mut().for_each(Vec::clear);
+        self.chord_progress.fill(None);
+        self.tapped.clear();
+        self.debouncer.reset();
+    }
+
+    /// Whether the hotkey at `idx` is registered and not disabled through the handle.
+    fn is_enabled(&self, idx: usize) -> bool {
+        !self.removed[idx] && self.config.shared.disabled.is_enabled(idx)
+    }
+
+    /// Apply the hotkey additions and removals requested through the handle.
+    fn apply_updates(&mut self) {
+        for update in self.config.shared.updates.drain() {
+            match update {
+                HotkeyUpdate::Add(hotkey) => {
+                    log::debug!("Adding hotkey {} at {}", hotkey, self.hotkeys.len());
+                    if let Some(key) = hotkey.key.filter(|_| is_plain_key(&hotkey)) {
+                        self.by_key.entry(key).or_default().push(self.hotkeys.len());
+                    }
+                    self.hotkeys.push(hotkey);
+                    self.removed.push(false);
+                    self.sequence_times.push(Vec::new());
+                    self.last_fired.push(None);
+                    self.double_tap_start.push(None);
+                    self.long_pressed.push(None);
+                    self.active.push();
+                }
+                HotkeyUpdate::Remove(idx) => {
+                    if let Some(removed) = self.removed.get_mut(idx) {
+                        *removed = true;
+                        self.sequence_times[idx].clear();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fire modifier-only hotkeys whose tap pattern was just completed.
+    fn match_taps(&mut self, at: Instant) -> Vec<HotkeyEvent> {
+        if !self.config.is_active(SystemTime::now()) {
+            return Vec::new();
+        }
+
+        let mut fired = Vec::new();
+        for (idx, hotkey) in self.hotkeys.iter().enumerate() {
+            let Some(taps) = hotkey.modifier_taps else {
+                continue;
+            };
+            if !self.is_enabled(idx) {
+                continue;
+            }
+            let count = taps.count.max(1) as usize;
+            if hotkey.modifiers.any_side() != self.tap_mods
+                || !self.sides_held(&hotkey.modifiers, at)
+                || self.tap_times.len() < count
+            {
+                continue;
+            }
+            let first = self.tap_times[self.tap_times.len() - count];
+            if at.duration_since(first) <= taps.window {
+                fired.push(idx);
+            }
+        }
+
+        if !fired.is_empty() {
+            self.tap_times.clear();
+        }
+        let fired = self.throttle(fired, at);
+        self.tapped.extend(&fired);
+        self.emit_pressed(fired)
+    }
+
+    /// Count a key press towards sequence hotkeys, returning those that completed.
+    ///
+    /// A press of any other key or combination restarts a sequence.
+    fn match_sequences(&mut self, key: Key, mods: Modifiers, at: Instant) -> Vec<usize> {
+        let active = self.config.is_active(SystemTime::now());
+        let mut fired = Vec::new();
+        for (idx, hotkey) in self.hotkeys.iter().enumerate() {
+            let Some(sequence) = hotkey.sequence else {
+                continue;
+            };
+            if !self.is_enabled(idx) {
+                continue;
+            }
+            let same_combo = hotkey.key == Some(key)
+                && hotkey.modifiers.any_side() == mods
+                && self.sides_held(&hotkey.modifiers, at);
+            let presses = &mut self.sequence_times[idx];
+            if !same_combo {
+                presses.clear();
+                continue;
+            }
+            presses.retain(|&t| at.saturating_duration_since(t) <= sequence.window);
+            presses.push(at);
+            if presses.len() >= sequence.count.max(1) as usize {
+                presses.clear();
+                if active {
+                    fired.push(idx);
+                }
+            }
+        }
+        fired
+    }
+
+    /// Drop hotkeys still within their cooldown, recording the time of the rest.
+    fn throttle(&mut self, matched: Vec<usize>, at: Instant) -> Vec<usize> {
+        matched
+            .into_iter()
+            .filter(|&idx| {
+                let cooling = self.config.cooldown(idx).is_some_and(|cooldown| {
+                    self.last_fired[idx]
+                        .is_some_and(|last| at.saturating_duration_since(last) < cooldown)
+                });
+                if !cooling {
+                    self.last_fired[idx] = Some(at);
+                }
+                !cooling
+            })
+            .collect()
+    }
+
+    /// Turn matched hotkey indices into events carrying their configured ids.
+    fn emit(&self, matched: Vec<usize>, event: impl Fn(usize) -> HotkeyEvent) -> Vec<HotkeyEvent> {
+        matched
+            .into_iter()
+            .map(|idx| self.stamp(event(self.config.event_id(idx)), self.char_hint(idx)))
+            .collect()
+    }
+
+    /// The character the hotkey at `idx` types with the Shift state held now.
+    ///
+    /// Taken from the keyboard rather than the hotkey, which with
+    /// `ModifierMatch::AtLeast` may match whether Shift is held or not.
+    fn char_hint(&self, idx: usize) -> Option<char> {
+        self.hotkeys[idx].key?.to_char(self.current_mods.shift)
+    }
+
+    /// Emit `Pressed` for `matched`, or nothing in tap mode, where the
+    /// release emits `Tapped` instead.
+    fn emit_pressed(&self, matched: Vec<usize>) -> Vec<HotkeyEvent> {
+        if self.config.tap_mode {
+            return Vec::new();
+        }
+        self.emit(matched, HotkeyEvent::Pressed)
+    }
+
+    /// Emit `Released` for `released`, or `Tapped` in tap mode.
+    fn emit_released(&self, released: Vec<usize>) -> Vec<HotkeyEvent> {
+        if self.config.tap_mode {
+            return self.emit(released, HotkeyEvent::Tapped);
+        }
+        self.emit(released, HotkeyEvent::Released)
+    }
+
+    /// Record `event` with the time of the input being matched and the
+    /// character its key typed.
+    fn stamp(&self, event: HotkeyEvent, char_hint: Option<char>) -> HotkeyEvent {
+        let timestamp = self.event_time.unwrap_or_else(SystemTime::now);
+        self.config
+            .shared
+            .record(event, timestamp, self.event_device.as_deref(), char_hint);
+        event
+    }
+
+    /// Whether a modifier pressed at `since` has been held past the configured timeout.
+    fn timed_out(&self, since: Instant, at: Instant) -> bool {
+        self.config
+            .modifier_timeout
+            .is_some_and(|timeout| at.saturating_duration_since(since) > timeout)
+    }
+
+    /// The held modifiers that still count towards a combo at `at`.
+    fn effective_mods(&self, at: Instant) -> Modifiers {
+        Modifiers {
+            shift: self.counts(Modifier::Shift, at),
+            ctrl: self.counts(Modifier::Ctrl, at),
+            alt: self.counts(Modifier::Alt, at),
+            meta: self.counts(Modifier::Meta, at),
+            ..Modifiers::default()
+        }
+    }
+
+    /// Whether either side of `modifier` is held and not yet past the timeout at `at`.
+    fn counts(&self, modifier: Modifier, at: Instant) -> bool {
+        [ModifierSide::Left, ModifierSide::Right]
+            .into_iter()
+            .any(|side| self.side_counts(modifier, side, at))
+    }
+
+    /// Whether one side of `modifier` is held and not yet past the timeout at `at`.
+    fn side_counts(&self, modifier: Modifier, side: ModifierSide, at: Instant) -> bool {
+        self.held_since[modifier as usize][side as usize]
+            .is_some_and(|since| !self.timed_out(since, at))
+    }
+
+    /// Whether the sides `modifiers` requires, if any, are held at `at`.
+    fn sides_held(&self, modifiers: &Modifiers, at: Instant) -> bool {
+        [
+            (Modifier::Shift, modifiers.shift_side),
+            (Modifier::Ctrl, modifiers.ctrl_side),
+            (Modifier::Alt, modifiers.alt_side),
+            (Modifier::Meta, modifiers.meta_side),
+        ]
+        .into_iter()
+        .all(|(modifier, side)| side.is_none_or(|side| self.side_counts(modifier, side, at)))
+    }
+
+    /// The plain key hotkeys pressing `key` with `mods` held fires.
+    ///
+    /// When several match, e.g. `F8` with [`ModifierMatch::IgnoreModifiers`]
+    /// and `Shift+F8` while Shift is held, only the most specific fire; see
+    /// [`specificity`].
+    fn matching(&self, key: Key, mods: Modifiers, at: Instant) -> Vec<usize> {
+        // Modifier state is tracked regardless; the schedule only gates emission
+        if !self.config.is_active(SystemTime::now()) {
+            return Vec::new();
+        }
+        let candidates = self.by_key.get(&key).map_or(&[][..], Vec::as_slice);
+        let matched: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|&idx| {
+                let modifier_match = self.config.hotkey_modifier_match(idx);
+                self.is_enabled(idx)
+                    && self.combo_matches(&self.hotkeys[idx], key, mods, at, modifier_match)
+            })
+            .collect();
+        let best = matched
+            .iter()
+            .map(|&idx| specificity(&self.hotkeys[idx]))
+            .max();
+        matched
+            .into_iter()
+            .filter(|&idx| Some(specificity(&self.hotkeys[idx])) == best)
+            .collect()
+    }
+
+    /// Whether pressing `key` with `mods` held matches a plain key hotkey,
+    /// comparing modifiers as `modifier_match` says.
+    fn combo_matches(
+        &self,
+        hotkey: &Hotkey,
+        key: Key,
+        mods: Modifiers,
+        at: Instant,
+        modifier_match: ModifierMatch,
+    ) -> bool {
+        let modifiers_match = match modifier_match {
+            ModifierMatch::Exact => {
+                hotkey.modifiers.any_side() == mods && self.sides_held(&hotkey.modifiers, at)
+            }
+            ModifierMatch::AtLeast => {
+                hotkey.modifiers.is_subset_of(mods) && self.sides_held(&hotkey.modifiers, at)
+            }
+            ModifierMatch::IgnoreModifiers => true,
+        };
+        is_plain_key(hotkey)
+            && hotkey.key == Some(key)
+            && modifiers_match
+            && hotkey.num_lock.is_none_or(|on| self.num_lock == Some(on))
+            && hotkey.caps_lock.is_none_or(|on| self.caps_lock == Some(on))
+    }
+}
+
+/// Whether the hotkey fires on a single key press, rather than on modifier
+/// taps or a sequence.
+fn is_plain_key(hotkey: &Hotkey) -> bool {
+    !hotkey.is_modifier_only() && hotkey.sequence.is_none()
+}
+
+/// How specific a hotkey is, for choosing between hotkeys matching the same
+/// press.
+///
+/// More required modifiers rank higher, then more modifiers required on a
+/// given side, then more NumLock and CapsLock conditions. Hotkeys tied on
+/// all three all fire, in the order they were added.
+fn specificity(hotkey: &Hotkey) -> (usize, usize, usize) {
+    let modifiers = &hotkey.modifiers;
+    let held = [
+        modifiers.shift,
+        modifiers.ctrl,
+        modifiers.alt,
+        modifiers.meta,
+    ];
+    let sided = [
+        modifiers.shift_side,
+        modifiers.ctrl_side,
+        modifiers.alt_side,
+        modifiers.meta_side,
+    ];
+    (
+        held.into_iter().filter(|&held| held).count(),
+        sided.into_iter().flatten().count(),
+        hotkey.num_lock.iter().chain(&hotkey.caps_lock).count(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::{parse_hotkey, Chord};
+    use crate::listener::{HotkeyOptions, ModifierMatch};
+
+    #[test]
+    fn test_matches_exact_modifiers() {
+        let mut matcher = HotkeyMatcher::new(
+            vec![
+                parse_hotkey("F8").unwrap(),
+                parse_hotkey("Shift+F8").unwrap(),
+            ],
+            ListenerConfig::default(),
+        );
+
+        assert_eq!(
+            matcher.on_key_down(Key::F8, Instant::now()),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(0)]);
+
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, true, Instant::now());
+        assert_eq!(
+            matcher.on_key_down(Key::F8, Instant::now()),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(1)]);
+    }
+
+    #[test]
+    fn test_most_specific_overlapping_hotkey_wins() {
+        let config = ListenerConfig {
+            hotkey_options: vec![
+                HotkeyOptions {
+                    id: 0,
+                    modifier_match: Some(ModifierMatch::IgnoreModifiers),
+                    ..HotkeyOptions::default()
+                },
+                HotkeyOptions {
+                    id: 1,
+                    modifier_match: Some(ModifierMatch::AtLeast),
+                    ..HotkeyOptions::default()
+                },
+                HotkeyOptions {
+                    id: 2,
+                    ..HotkeyOptions::default()
+                },
+                HotkeyOptions {
+                    id: 3,
+                    ..HotkeyOptions::default()
+                },
+            ],
+            ..ListenerConfig::default()
+        };
+        let mut matcher = HotkeyMatcher::new(
+            vec![
+                parse_hotkey("F8").unwrap(),
+                parse_hotkey("Shift+F8").unwrap(),
+                parse_hotkey("Shift+Ctrl+F8").unwrap(),
+                parse_hotkey("RShift+Ctrl+F8").unwrap(),
+            ],
+            config,
+        );
+        let at = Instant::now();
+
+        // Only the catch-all matches a bare press
+        assert_eq!(
+            matcher.on_key_down(Key::F8, at),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        matcher.on_key_up(Key::F8);
+
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, true, at);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, at),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+        matcher.on_key_up(Key::F8);
+
+        matcher.on_modifier(Modifier::Ctrl, ModifierSide::Left, true, at);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, at),
+            vec![HotkeyEvent::Pressed(2)]
+        );
+        matcher.on_key_up(Key::F8);
+
+        // A required side outranks the same modifiers on either side
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, false, at);
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Right, true, at);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, at),
+            vec![HotkeyEvent::Pressed(3)]
+        );
+        matcher.on_key_up(Key::F8);
+    }
+
+    #[test]
+    fn test_equally_specific_hotkeys_all_fire() {
+        let mut matcher = HotkeyMatcher::new(
+            vec![
+                parse_hotkey("Ctrl+F8").unwrap(),
+                parse_hotkey("F9").unwrap(),
+                parse_hotkey("Ctrl+F8").unwrap(),
+            ],
+            ListenerConfig {
+                hotkey_options: (0..3)
+                    .map(|id| HotkeyOptions {
+                        id,
+                        ..HotkeyOptions::default()
+                    })
+                    .collect(),
+                ..ListenerConfig::default()
+            },
+        );
+        let at = Instant::now();
+        matcher.on_modifier(Modifier::Ctrl, ModifierSide::Left, true, at);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, at),
+            vec![HotkeyEvent::Pressed(0), HotkeyEvent::Pressed(2)]
+        );
+    }
+
+    #[test]
+    fn test_reset_clears_modifiers() {
+        let mut matcher =
+            HotkeyMatcher::new(vec![parse_hotkey("F8").unwrap()], ListenerConfig::default());
+        matcher.on_modifier(Modifier::Ctrl, ModifierSide::Left, true, Instant::now());
+        assert!(matcher.on_key_down(Key::F8, Instant::now()).is_empty());
+
+        matcher.reset();
+        assert_eq!(
+            matcher.on_key_down(Key::F8, Instant::now()),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+    }
+
+    #[test]
+    fn test_active_schedule_gates_emission() {
+        use crate::listener::Schedule;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let active = Arc::new(AtomicBool::new(false));
+        let schedule_active = Arc::clone(&active);
+        let config = ListenerConfig {
+            active_schedule: Some(Schedule::new(move |_| {
+                schedule_active.load(Ordering::SeqCst)
+            })),
+            ..ListenerConfig::default()
+        };
+        let mut matcher = HotkeyMatcher::new(vec![parse_hotkey("Shift+F8").unwrap()], config);
+
+        // Modifiers are tracked while inactive
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, true, Instant::now());
+        assert!(matcher.on_key_down(Key::F8, Instant::now()).is_empty());
+        assert!(matcher.on_key_up(Key::F8).is_empty());
+
+        active.store(true, Ordering::SeqCst);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, Instant::now()),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+    }
+
+    #[test]
+    fn test_events_carry_assigned_ids() {
+        let config = ListenerConfig {
+            hotkey_options: vec![
+                HotkeyOptions {
+                    id: 42,
+                    ..HotkeyOptions::default()
+                },
+                HotkeyOptions {
+                    id: 7,
+                    ..HotkeyOptions::default()
+                },
+            ],
+            ..ListenerConfig::default()
+        };
+        let mut matcher = HotkeyMatcher::new(
+            vec![
+                parse_hotkey("Shift+F8").unwrap(),
+                parse_hotkey("F8").unwrap(),
+            ],
+            config,
+        );
+
+        assert_eq!(
+            matcher.on_key_down(Key::F8, Instant::now()),
+            vec![HotkeyEvent::Pressed(7)]
+        );
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(7)]);
+
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, true, Instant::now());
+        assert_eq!(
+            matcher.on_key_down(Key::F8, Instant::now()),
+            vec![HotkeyEvent::Pressed(42)]
+        );
+    }
+
+    #[test]
+    fn test_remapped_hotkey_emits_new_index() {
+        let config = ListenerConfig::default();
+        let remap = config.shared.remap.clone();
+        let mut matcher = HotkeyMatcher::new(
+            vec![parse_hotkey("F8").unwrap(), parse_hotkey("F9").unwrap()],
+            config,
+        );
+
+        remap.set(0, 1);
+        remap.set(1, 0);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, Instant::now()),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(1)]);
+        assert_eq!(
+            matcher.on_key_down(Key::F9, Instant::now()),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+    }
+
+    fn debounced_matcher() -> HotkeyMatcher {
+        let config = ListenerConfig {
+            debounce: Some(Duration::from_millis(30)),
+            ..ListenerConfig::default()
+        };
+        HotkeyMatcher::new(vec![parse_hotkey("F8").unwrap()], config)
+    }
+
+    #[test]
+    fn test_debounce_suppresses_press_without_release() {
+        let mut matcher = debounced_matcher();
+        let start = Instant::now();
+
+        let mut events = matcher.on_key_down(Key::F8, start);
+        events.extend(matcher.on_key_down(Key::F8, start + Duration::from_millis(5)));
+        events.extend(matcher.on_key_up(Key::F8));
+        assert_eq!(
+            events,
+            vec![HotkeyEvent::Pressed(0), HotkeyEvent::Released(0)]
+        );
+    }
+
+    #[test]
+    fn test_debounce_passes_fast_double_tap() {
+        let mut matcher = debounced_matcher();
+        let start = Instant::now();
+
+        let mut events = matcher.on_key_down(Key::F8, start);
+        events.extend(matcher.on_key_up(Key::F8));
+        events.extend(matcher.on_key_down(Key::F8, start + Duration::from_millis(5)));
+        events.extend(matcher.on_key_up(Key::F8));
+        assert_eq!(
+            events,
+            vec![
+                HotkeyEvent::Pressed(0),
+                HotkeyEvent::Released(0),
+                HotkeyEvent::Pressed(0),
+                HotkeyEvent::Released(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_num_lock_selects_binding() {
+        let mut matcher = HotkeyMatcher::new(
+            vec![
+                Hotkey::new(Key::Numpad1).when_num_lock(true),
+                Hotkey::new(Key::Numpad1).when_num_lock(false),
+            ],
+            ListenerConfig::default(),
+        );
+
+        // Unknown state matches neither
+        assert!(matcher.on_key_down(Key::Numpad1, Instant::now()).is_empty());
+        matcher.on_key_up(Key::Numpad1);
+
+        matcher.set_num_lock(true);
+        assert_eq!(
+            matcher.on_key_down(Key::Numpad1, Instant::now()),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        assert_eq!(
+            matcher.on_key_up(Key::Numpad1),
+            vec![HotkeyEvent::Released(0)]
+        );
+
+        matcher.set_num_lock(false);
+        assert_eq!(
+            matcher.on_key_down(Key::Numpad1, Instant::now()),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+    }
+
+    #[test]
+    fn test_orphan_release_is_suppressed() {
+        let mut matcher =
+            HotkeyMatcher::new(vec![parse_hotkey("F8").unwrap()], ListenerConfig::default());
+
+        assert!(matcher.on_key_up(Key::F8).is_empty());
+        assert_eq!(
+            matcher.on_key_down(Key::F8, Instant::now()),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(0)]);
+        assert!(matcher.on_key_up(Key::F8).is_empty());
+    }
+
+    #[test]
+    fn test_release_pairs_with_press_after_modifier_release() {
+        let mut matcher = HotkeyMatcher::new(
+            vec![parse_hotkey("Ctrl+F8").unwrap()],
+            ListenerConfig::default(),
+        );
+
+        matcher.on_modifier(Modifier::Ctrl, ModifierSide::Left, true, Instant::now());
+        assert_eq!(
+            matcher.on_key_down(Key::F8, Instant::now()),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        matcher.on_modifier(Modifier::Ctrl, ModifierSide::Left, false, Instant::now());
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(0)]);
+    }
+
+    #[test]
+    fn test_capture_returns_next_combo() {
+        let config = ListenerConfig::default();
+        let capture = config.shared.capture.clone();
+        let mut matcher = HotkeyMatcher::new(vec![parse_hotkey("Ctrl+Shift+F8").unwrap()], config);
+
+        let rx = capture.arm();
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, true, Instant::now());
+        matcher.on_modifier(Modifier::Ctrl, ModifierSide::Left, true, Instant::now());
+        assert!(rx.try_recv().is_err());
+
+        // The captured press is swallowed; the next one matches normally
+        assert!(matcher.on_key_down(Key::F8, Instant::now()).is_empty());
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            parse_hotkey("Ctrl+Shift+F8").unwrap()
+        );
+        assert!(matcher.on_key_up(Key::F8).is_empty());
+        assert_eq!(
+            matcher.on_key_down(Key::F8, Instant::now()),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+    }
+
+    #[test]
+    fn test_modifier_timeout_drops_stale_modifier() {
+        let config = ListenerConfig {
+            modifier_timeout: Some(Duration::from_millis(500)),
+            ..ListenerConfig::default()
+        };
+        let mut matcher = HotkeyMatcher::new(
+            vec![
+                parse_hotkey("F8").unwrap(),
+                parse_hotkey("Shift+F8").unwrap(),
+            ],
+            config,
+        );
+        let t0 = Instant::now();
+
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, true, t0);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, t0 + Duration::from_millis(100)),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+        matcher.on_key_up(Key::F8);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, t0 + Duration::from_secs(1)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+    }
+
+    #[test]
+    fn test_max_hold_releases_stuck_hotkey() {
+        let config = ListenerConfig {
+            max_hold: Some(Duration::from_secs(5)),
+            ..ListenerConfig::default()
+        };
+        let mut matcher = HotkeyMatcher::new(vec![parse_hotkey("F8").unwrap()], config);
+        let t0 = Instant::now();
+
+        assert_eq!(
+            matcher.on_key_down(Key::F8, t0),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        assert!(matcher
+            .release_stuck(t0 + Duration::from_secs(4))
+            .is_empty());
+        assert_eq!(
+            matcher.release_stuck(t0 + Duration::from_secs(6)),
+            vec![HotkeyEvent::Released(0)]
+        );
+        assert!(matcher
+            .release_stuck(t0 + Duration::from_secs(7))
+            .is_empty());
+
+        // The late physical release is not reported twice
+        assert!(matcher.on_key_up(Key::F8).is_empty());
+    }
+
+    #[test]
+    fn test_cooldown_suppresses_repeated_presses() {
+        let config = ListenerConfig {
+            hotkey_options: vec![HotkeyOptions {
+                id: 0,
+                cooldown: Some(Duration::from_secs(2)),
+                ..HotkeyOptions::default()
+            }],
+            ..ListenerConfig::default()
+        };
+        let mut matcher = HotkeyMatcher::new(vec![parse_hotkey("F8").unwrap()], config);
+        let t0 = Instant::now();
+
+        assert_eq!(
+            matcher.on_key_down(Key::F8, t0),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(0)]);
+
+        assert!(matcher
+            .on_key_down(Key::F8, t0 + Duration::from_secs(1))
+            .is_empty());
+        assert!(matcher.on_key_up(Key::F8).is_empty());
+
+        assert_eq!(
+            matcher.on_key_down(Key::F8, t0 + Duration::from_secs(3)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+    }
+
+    fn double_tap_config(suppress: bool) -> ListenerConfig {
+        let options = HotkeyOptions {
+            double_tap: Some(Duration::from_millis(300)),
+            ..HotkeyOptions::default()
+        };
+        ListenerConfig {
+            hotkey_options: vec![options, HotkeyOptions { id: 1, ..options }],
+            suppress_double_tap_presses: suppress,
+            ..ListenerConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_double_tap_within_window() {
+        let mut matcher = HotkeyMatcher::new(
+            vec![
+                parse_hotkey("F8").unwrap(),
+                parse_hotkey("Ctrl+F8").unwrap(),
+            ],
+            double_tap_config(false),
+        );
+        let t0 = Instant::now();
+        let tap = |matcher: &mut HotkeyMatcher, at| {
+            let events = matcher.on_key_down(Key::F8, at);
+            matcher.on_key_up(Key::F8);
+            events
+        };
+
+        assert_eq!(tap(&mut matcher, t0), vec![HotkeyEvent::Pressed(0)]);
+        assert_eq!(
+            tap(&mut matcher, t0 + Duration::from_millis(200)),
+            vec![HotkeyEvent::Pressed(0), HotkeyEvent::DoubleTapped(0)]
+        );
+        // A third press starts over
+        assert_eq!(
+            tap(&mut matcher, t0 + Duration::from_millis(400)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+
+        // The window elapsed, so this press starts a new double-tap
+        assert_eq!(
+            tap(&mut matcher, t0 + Duration::from_millis(800)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+
+        // A press with Ctrl is a different hotkey and doesn't count for F8
+        let t1 = t0 + Duration::from_millis(900);
+        matcher.on_modifier(Modifier::Ctrl, ModifierSide::Left, true, t1);
+        assert_eq!(tap(&mut matcher, t1), vec![HotkeyEvent::Pressed(1)]);
+        matcher.on_modifier(Modifier::Ctrl, ModifierSide::Left, false, t1);
+        assert_eq!(
+            tap(&mut matcher, t0 + Duration::from_millis(1000)),
+            vec![HotkeyEvent::Pressed(0), HotkeyEvent::DoubleTapped(0)]
+        );
+    }
+
+    #[test]
+    fn test_double_tap_can_suppress_second_press() {
+        let mut matcher =
+            HotkeyMatcher::new(vec![parse_hotkey("F8").unwrap()], double_tap_config(true));
+        let t0 = Instant::now();
+
+        assert_eq!(
+            matcher.on_key_down(Key::F8, t0),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(0)]);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, t0 + Duration::from_millis(100)),
+            vec![HotkeyEvent::DoubleTapped(0)]
+        );
+        assert!(matcher.on_key_up(Key::F8).is_empty());
+    }
+
+    #[test]
+    fn test_tap_mode_emits_tapped_on_release() {
+        let options = HotkeyOptions {
+            long_press: Some(Duration::from_millis(500)),
+            ..HotkeyOptions::default()
+        };
+        let config = ListenerConfig {
+            tap_mode: true,
+            emit_repeats: true,
+            hotkey_options: vec![options],
+            ..ListenerConfig::default()
+        };
+        let mut matcher = HotkeyMatcher::new(vec![parse_hotkey("F8").unwrap()], config);
+        let t0 = Instant::now();
+
+        // A release without a press seen isn't a tap
+        assert!(matcher.on_key_up(Key::F8).is_empty());
+
+        assert!(matcher.on_key_down(Key::F8, t0).is_empty());
+        assert!(matcher.on_key_repeat(Key::F8).is_empty());
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Tapped(0)]);
+
+        // Held until the long press: no tap
+        let t1 = t0 + Duration::from_secs(1);
+        assert!(matcher.on_key_down(Key::F8, t1).is_empty());
+        assert_eq!(
+            matcher.poll_long_presses(t1 + Duration::from_millis(500)),
+            vec![HotkeyEvent::LongPressed(0)]
+        );
+        assert!(matcher.on_key_up(Key::F8).is_empty());
+    }
+
+    #[test]
+    fn test_long_press_fires_once_at_threshold() {
+        let options = HotkeyOptions {
+            long_press: Some(Duration::from_millis(500)),
+            ..HotkeyOptions::default()
+        };
+        let config = ListenerConfig {
+            hotkey_options: vec![options],
+            ..ListenerConfig::default()
+        };
+        let mut matcher = HotkeyMatcher::new(vec![parse_hotkey("F8").unwrap()], config);
+        let t0 = Instant::now();
+
+        assert_eq!(matcher.next_long_press(), None);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, t0),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        assert_eq!(
+            matcher.next_long_press(),
+            Some(t0 + Duration::from_millis(500))
+        );
+        assert!(matcher
+            .poll_long_presses(t0 + Duration::from_millis(499))
+            .is_empty());
+        assert_eq!(
+            matcher.poll_long_presses(t0 + Duration::from_millis(500)),
+            vec![HotkeyEvent::LongPressed(0)]
+        );
+        // Only once per press
+        assert!(matcher
+            .poll_long_presses(t0 + Duration::from_secs(2))
+            .is_empty());
+        assert_eq!(matcher.next_long_press(), None);
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(0)]);
+
+        // Released before the threshold: just the press and release
+        let t1 = t0 + Duration::from_secs(3);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, t1),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        assert!(matcher
+            .poll_long_presses(t1 + Duration::from_millis(300))
+            .is_empty());
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(0)]);
+        assert!(matcher
+            .poll_long_presses(t1 + Duration::from_secs(1))
+            .is_empty());
+        assert_eq!(matcher.next_long_press(), None);
+    }
+
+    fn chord_matcher() -> HotkeyMatcher {
+        let chord = Chord::new(
+            [
+                parse_hotkey("Ctrl+F1").unwrap(),
+                parse_hotkey("F2").unwrap(),
+            ],
+            Duration::from_millis(500),
+        );
+        let config = ListenerConfig {
+            chords: vec![chord],
+            ..ListenerConfig::default()
+        };
+        HotkeyMatcher::new(vec![parse_hotkey("F2").unwrap()], config)
+    }
+
+    fn press_ctrl_f1(matcher: &mut HotkeyMatcher, at: Instant) -> Vec<HotkeyEvent> {
+        matcher.on_modifier(Modifier::Ctrl, ModifierSide::Left, true, at);
+        let events = matcher.on_key_down(Key::F1, at);
+        matcher.on_key_up(Key::F1);
+        matcher.on_modifier(Modifier::Ctrl, ModifierSide::Left, false, at);
+        events
+    }
+
+    #[test]
+    fn test_chord_completes_on_last_step() {
+        let mut matcher = chord_matcher();
+        let t0 = Instant::now();
+
+        assert!(press_ctrl_f1(&mut matcher, t0).is_empty());
+        // The final step is also a hotkey, so it's reported as well
+        assert_eq!(
+            matcher.on_key_down(Key::F2, t0 + Duration::from_millis(400)),
+            vec![HotkeyEvent::Pressed(0), HotkeyEvent::ChordCompleted(0)]
+        );
+        assert_eq!(matcher.on_key_up(Key::F2), vec![HotkeyEvent::Released(0)]);
+
+        // Completing the chord starts it over
+        assert_eq!(
+            matcher.on_key_down(Key::F2, t0 + Duration::from_millis(600)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+    }
+
+    #[test]
+    fn test_chord_resets_on_timeout_or_other_key() {
+        let mut matcher = chord_matcher();
+        let t0 = Instant::now();
+
+        press_ctrl_f1(&mut matcher, t0);
+        assert_eq!(
+            matcher.on_key_down(Key::F2, t0 + Duration::from_millis(501)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        matcher.on_key_up(Key::F2);
+
+        let t1 = t0 + Duration::from_secs(1);
+        press_ctrl_f1(&mut matcher, t1);
+        matcher.on_key_down(Key::F3, t1);
+        matcher.on_key_up(Key::F3);
+        assert_eq!(
+            matcher.on_key_down(Key::F2, t1 + Duration::from_millis(100)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        matcher.on_key_up(Key::F2);
+
+        // Repeating the first step restarts the timeout from there
+        let t2 = t0 + Duration::from_secs(2);
+        press_ctrl_f1(&mut matcher, t2);
+        press_ctrl_f1(&mut matcher, t2 + Duration::from_millis(400));
+        assert_eq!(
+            matcher.on_key_down(Key::F2, t2 + Duration::from_millis(800)),
+            vec![HotkeyEvent::Pressed(0), HotkeyEvent::ChordCompleted(0)]
+        );
+    }
+
+    #[test]
+    fn test_recorder_logs_emitted_events_in_order() {
+        use crate::listener::{EventRecorder, Shared};
+
+        let recorder = EventRecorder::new(3);
+        let config = ListenerConfig {
+            shared: Shared {
+                recorder: Some(recorder.clone()),
+                ..Shared::default()
+            },
+            ..ListenerConfig::default()
+        };
+        let mut matcher = HotkeyMatcher::new(
+            vec![
+                parse_hotkey("F8").unwrap(),
+                parse_hotkey("Shift+F8").unwrap(),
+            ],
+            config,
+        );
+
+        let mut emitted = Vec::new();
+        emitted.extend(matcher.on_key_down(Key::F8, Instant::now()));
+        emitted.extend(matcher.on_key_up(Key::F8));
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, true, Instant::now());
+        emitted.extend(matcher.on_key_down(Key::F8, Instant::now()));
+        emitted.extend(matcher.on_key_up(Key::F8));
+
+        let recorded = recorder.events();
+        let events: Vec<HotkeyEvent> = recorded.iter().map(|&(_, event)| event).collect();
+        // Bounded to the last three
+        assert_eq!(events, emitted[1..]);
+        assert!(recorded.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+
+    fn pressed_on_two_keyboards(policy: MultiDevicePress) -> Vec<HotkeyEvent> {
+        let config = ListenerConfig {
+            multi_device_press: policy,
+            ..ListenerConfig::default()
+        };
+        let mut matcher = HotkeyMatcher::new(vec![parse_hotkey("F8").unwrap()], config);
+
+        let mut events = Vec::new();
+        events.extend(matcher.on_key_down(Key::F8, Instant::now()));
+        events.extend(matcher.on_key_down(Key::F8, Instant::now()));
+        events.extend(matcher.on_key_up(Key::F8));
+        events.extend(matcher.on_key_up(Key::F8));
+        events
+    }
+
+    #[test]
+    fn test_multi_device_press_coalesce() {
+        assert_eq!(
+            pressed_on_two_keyboards(MultiDevicePress::Coalesce),
+            vec![HotkeyEvent::Pressed(0), HotkeyEvent::Released(0)]
+        );
+    }
+
+    #[test]
+    fn test_multi_device_press_separate() {
+        assert_eq!(
+            pressed_on_two_keyboards(MultiDevicePress::Separate),
+            vec![
+                HotkeyEvent::Pressed(0),
+                HotkeyEvent::Pressed(0),
+                HotkeyEvent::Released(0),
+                HotkeyEvent::Released(0),
+            ]
+        );
+    }
+
+    fn with_meta_held(modifier_match: ModifierMatch) -> Vec<HotkeyEvent> {
+        let config = ListenerConfig {
+            modifier_match,
+            ..ListenerConfig::default()
+        };
+        let mut matcher = HotkeyMatcher::new(
+            vec![
+                parse_hotkey("F8").unwrap(),
+                parse_hotkey("Ctrl+F9").unwrap(),
+            ],
+            config,
+        );
+
+        let mut events = Vec::new();
+        matcher.on_modifier(Modifier::Meta, ModifierSide::Left, true, Instant::now());
+        events.extend(matcher.on_key_down(Key::F8, Instant::now()));
+        matcher.on_modifier(Modifier::Ctrl, ModifierSide::Left, true, Instant::now());
+        events.extend(matcher.on_key_down(Key::F9, Instant::now()));
+        events
+    }
+
+    #[test]
+    fn test_held_meta_blocks_hotkeys_without_it() {
+        assert!(with_meta_held(ModifierMatch::Exact).is_empty());
+    }
+
+    #[test]
+    fn test_char_hint_follows_held_shift() {
+        let config = ListenerConfig {
+            modifier_match: ModifierMatch::AtLeast,
+            ..ListenerConfig::default()
+        };
+        let timed = config.shared.subscribers.subscribe_timed();
+        let mut matcher = HotkeyMatcher::new(vec![parse_hotkey("A").unwrap()], config);
+
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, true, Instant::now());
+        matcher.on_key_down(Key::A, Instant::now());
+        matcher.on_key_up(Key::A);
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, false, Instant::now());
+        matcher.on_key_down(Key::A, Instant::now());
+        let hints: Vec<_> = timed.try_iter().map(|event| event.char_hint).collect();
+        assert_eq!(hints, vec![Some('A'), Some('A'), Some('a')]);
+    }
+
+    #[test]
+    fn test_hotkey_modifier_match_modes() {
+        let options = |id, modifier_match| HotkeyOptions {
+            id,
+            modifier_match: Some(modifier_match),
+            ..HotkeyOptions::default()
+        };
+        let config = ListenerConfig {
+            hotkey_options: vec![
+                HotkeyOptions::default(),
+                options(1, ModifierMatch::AtLeast),
+                options(2, ModifierMatch::IgnoreModifiers),
+            ],
+            ..ListenerConfig::default()
+        };
+        let hotkey = parse_hotkey("Ctrl+F8").unwrap();
+        let mut matcher = HotkeyMatcher::new(vec![hotkey; 3], config);
+        let mut press = |held: &[Modifier]| {
+            for &modifier in held {
+                matcher.on_modifier(modifier, ModifierSide::Left, true, Instant::now());
+            }
+            let events = matcher.on_key_down(Key::F8, Instant::now());
+            matcher.on_key_up(Key::F8);
+            for &modifier in held {
+                matcher.on_modifier(modifier, ModifierSide::Left, false, Instant::now());
+            }
+            events
+        };
+
+        assert_eq!(press(&[]), vec![HotkeyEvent::Pressed(2)]);
+        assert_eq!(
+            press(&[Modifier::Ctrl]),
+            vec![
+                HotkeyEvent::Pressed(0),
+                HotkeyEvent::Pressed(1),
+                HotkeyEvent::Pressed(2)
+            ]
+        );
+        assert_eq!(
+            press(&[Modifier::Ctrl, Modifier::Shift]),
+            vec![HotkeyEvent::Pressed(1), HotkeyEvent::Pressed(2)]
+        );
+        assert_eq!(press(&[Modifier::Alt]), vec![HotkeyEvent::Pressed(2)]);
+    }
+
+    #[test]
+    fn test_only_the_fired_hotkey_is_released() {
+        let mut matcher = HotkeyMatcher::new(
+            vec![
+                parse_hotkey("F8").unwrap(),
+                parse_hotkey("Shift+F8").unwrap(),
+            ],
+            ListenerConfig::default(),
+        );
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, true, Instant::now());
+        assert_eq!(
+            matcher.on_key_down(Key::F8, Instant::now()),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+        // Shift released first, as is common
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, false, Instant::now());
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(1)]);
+        assert!(matcher.on_key_up(Key::F8).is_empty());
+    }
+
+    #[test]
+    fn test_active_hotkeys_pair_presses_with_releases() {
+        let mut active = ActiveHotkeys::new(2);
+        let at = Instant::now();
+        assert_eq!(active.release(0), None);
+        assert_eq!(active.press(0, at), 1);
+        assert_eq!(active.press(0, at + Duration::from_millis(5)), 2);
+        assert_eq!(active.pressed_since(0), Some(at));
+        assert!(!active.is_pressed(1));
+        assert_eq!(active.release(0), Some(1));
+        assert_eq!(active.release(0), Some(0));
+        assert!(!active.is_pressed(0));
+        assert_eq!(active.pressed_since(0), None);
+        assert_eq!(active.release(0), None);
+
+        active.press(1, at);
+        assert_eq!(active.clear(1), 1);
+        assert_eq!(active.release(1), None);
+    }
+
+    #[test]
+    fn test_hotkeys_added_and_removed_at_runtime() {
+        let config = ListenerConfig::default();
+        config.shared.updates.registered(2);
+        let updates = config.shared.updates.clone();
+        let mut matcher = HotkeyMatcher::new(
+            vec![parse_hotkey("F8").unwrap(), parse_hotkey("F9").unwrap()],
+            config,
+        );
+        let now = Instant::now();
+
+        // Removing a held hotkey still releases it
+        assert_eq!(
+            matcher.on_key_down(Key::F8, now),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        updates.remove(0);
+        assert_eq!(updates.add(parse_hotkey("F10").unwrap()), 2);
+        assert_eq!(
+            matcher.on_key_down(Key::F10, now),
+            vec![HotkeyEvent::Pressed(2)]
+        );
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(0)]);
+
+        assert!(matcher.on_key_down(Key::F8, now).is_empty());
+        assert_eq!(
+            matcher.on_key_down(Key::F9, now),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+        assert_eq!(matcher.on_key_up(Key::F10), vec![HotkeyEvent::Released(2)]);
+
+        // The removed index isn't reused
+        assert_eq!(updates.add(parse_hotkey("F8").unwrap()), 3);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, now),
+            vec![HotkeyEvent::Pressed(3)]
+        );
+    }
+
+    #[test]
+    fn test_disabled_hotkey_resumes_when_enabled() {
+        let config = ListenerConfig::default();
+        let disabled = config.shared.disabled.clone();
+        let mut matcher = HotkeyMatcher::new(
+            vec![parse_hotkey("F8").unwrap(), parse_hotkey("F9").unwrap()],
+            config,
+        );
+        let now = Instant::now();
+
+        disabled.set_enabled(0, false);
+        assert!(matcher.on_key_down(Key::F8, now).is_empty());
+        assert!(matcher.on_key_up(Key::F8).is_empty());
+        assert_eq!(
+            matcher.on_key_down(Key::F9, now),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+
+        disabled.set_enabled(0, true);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, now),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        // Disabling while held still pairs the press with its release
+        disabled.set_enabled(0, false);
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(0)]);
+    }
+
+    #[test]
+    fn test_paused_listener_emits_nothing_but_tracks_modifiers() {
+        let config = ListenerConfig {
+            emit_repeats: true,
+            hotkey_options: vec![
+                HotkeyOptions {
+                    id: 0,
+                    long_press: Some(Duration::from_millis(500)),
+                    ..HotkeyOptions::default()
+                },
+                HotkeyOptions {
+                    id: 1,
+                    ..HotkeyOptions::default()
+                },
+            ],
+            ..ListenerConfig::default()
+        };
+        let paused = config.shared.paused.clone();
+        let mut matcher = HotkeyMatcher::new(
+            vec![
+                parse_hotkey("F8").unwrap(),
+                parse_hotkey("Shift+F9").unwrap(),
+            ],
+            config,
+        );
+        let now = Instant::now();
+
+        assert_eq!(
+            matcher.on_key_down(Key::F8, now),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        paused.set(true);
+        // A held hotkey stops repeating and long-pressing once muted
+        assert!(matcher.on_key_repeat(Key::F8).is_empty());
+        assert!(matcher
+            .poll_long_presses(now + Duration::from_secs(1))
+            .is_empty());
+        assert_eq!(matcher.next_long_press(), None);
+        // A press from before muting still gets its release
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(0)]);
+        assert!(matcher.on_key_down(Key::F8, now).is_empty());
+        assert!(matcher.on_key_up(Key::F8).is_empty());
+
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, true, now);
+        assert!(matcher.on_key_down(Key::F9, now).is_empty());
+        assert!(matcher.on_key_up(Key::F9).is_empty());
+
+        paused.set(false);
+        assert_eq!(
+            matcher.on_key_down(Key::F9, now),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+        assert_eq!(matcher.on_key_repeat(Key::F9), vec![HotkeyEvent::Repeat(1)]);
+    }
+
+    #[test]
+    fn test_repeats_are_opt_in() {
+        let hotkeys = vec![parse_hotkey("F8").unwrap(), parse_hotkey("F9").unwrap()];
+        let mut matcher = HotkeyMatcher::new(hotkeys.clone(), ListenerConfig::default());
+        matcher.on_key_down(Key::F8, Instant::now());
+        assert!(matcher.on_key_repeat(Key::F8).is_empty());
+
+        let config = ListenerConfig {
+            emit_repeats: true,
+            ..ListenerConfig::default()
+        };
+        let mut matcher = HotkeyMatcher::new(hotkeys, config);
+        // A repeat without an emitted press, e.g. held before starting
+        assert!(matcher.on_key_repeat(Key::F9).is_empty());
+        matcher.on_key_down(Key::F8, Instant::now());
+        assert_eq!(matcher.on_key_repeat(Key::F8), vec![HotkeyEvent::Repeat(0)]);
+        assert_eq!(matcher.on_key_up(Key::F8), vec![HotkeyEvent::Released(0)]);
+        assert!(matcher.on_key_repeat(Key::F8).is_empty());
+    }
+
+    #[test]
+    fn test_sided_modifiers() {
+        let mut matcher = HotkeyMatcher::new(
+            vec![
+                parse_hotkey("RAlt+F1").unwrap(),
+                parse_hotkey("Alt+F2").unwrap(),
+            ],
+            ListenerConfig::default(),
+        );
+        let now = Instant::now();
+
+        matcher.on_modifier(Modifier::Alt, ModifierSide::Left, true, now);
+        assert!(matcher.on_key_down(Key::F1, now).is_empty());
+        assert_eq!(
+            matcher.on_key_down(Key::F2, now),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+
+        // Releasing left Alt while right Alt is held keeps Alt held
+        matcher.on_modifier(Modifier::Alt, ModifierSide::Right, true, now);
+        matcher.on_modifier(Modifier::Alt, ModifierSide::Left, false, now);
+        assert_eq!(
+            matcher.on_key_down(Key::F1, now),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        assert_eq!(
+            matcher.on_key_down(Key::F2, now),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+    }
+
+    #[test]
+    fn test_meta_hotkey() {
+        let mut matcher = HotkeyMatcher::new(
+            vec![parse_hotkey("Super+F1").unwrap()],
+            ListenerConfig::default(),
+        );
+        assert!(matcher.on_key_down(Key::F1, Instant::now()).is_empty());
+        matcher.on_modifier(Modifier::Meta, ModifierSide::Left, true, Instant::now());
+        assert_eq!(
+            matcher.on_key_down(Key::F1, Instant::now()),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+    }
+
+    fn triple_f12() -> HotkeyMatcher {
+        HotkeyMatcher::new(
+            vec![Hotkey::sequence(Key::F12, 3, Duration::from_millis(600))],
+            ListenerConfig::default(),
+        )
+    }
+
+    #[test]
+    fn test_key_sequence_fires_within_window() {
+        let mut matcher = triple_f12();
+        let t0 = Instant::now();
+
+        for ms in [0, 150] {
+            let at = t0 + Duration::from_millis(ms);
+            assert!(matcher.on_key_down(Key::F12, at).is_empty());
+            assert!(matcher.on_key_up(Key::F12).is_empty());
+        }
+        assert_eq!(
+            matcher.on_key_down(Key::F12, t0 + Duration::from_millis(300)),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        assert_eq!(matcher.on_key_up(Key::F12), vec![HotkeyEvent::Released(0)]);
+
+        // Emits once, then counts again from scratch
+        assert!(matcher
+            .on_key_down(Key::F12, t0 + Duration::from_millis(400))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_slow_key_sequence_resets() {
+        let mut matcher = triple_f12();
+        let t0 = Instant::now();
+
+        for ms in [0, 400, 800, 1200] {
+            assert!(matcher
+                .on_key_down(Key::F12, t0 + Duration::from_millis(ms))
+                .is_empty());
+        }
+
+        // Another key in between also restarts the count
+        let mut matcher = triple_f12();
+        matcher.on_key_down(Key::F12, t0);
+        matcher.on_key_down(Key::F12, t0 + Duration::from_millis(100));
+        matcher.on_other_key();
+        assert!(matcher
+            .on_key_down(Key::F12, t0 + Duration::from_millis(200))
+            .is_empty());
+    }
+
+    fn shift_double_tap() -> HotkeyMatcher {
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::default()
+        };
+        HotkeyMatcher::new(
+            vec![
+                parse_hotkey("Shift+F8").unwrap(),
+                Hotkey::modifier_only(shift, 2, Duration::from_millis(300)),
+            ],
+            ListenerConfig::default(),
+        )
+    }
+
+    #[test]
+    fn test_modifier_only_hotkey_binds_no_key() {
+        let mut matcher = shift_double_tap();
+        assert!(matcher.binds(Key::F8));
+        assert!(!matcher.binds(Key::F1));
+    }
+
+    #[test]
+    fn test_double_tap_shift_fires() {
+        let mut matcher = shift_double_tap();
+        let t0 = Instant::now();
+
+        assert!(matcher
+            .on_modifier(Modifier::Shift, ModifierSide::Left, true, t0)
+            .is_empty());
+        assert!(matcher
+            .on_modifier(
+                Modifier::Shift,
+                ModifierSide::Left,
+                false,
+                t0 + Duration::from_millis(50)
+            )
+            .is_empty());
+        assert_eq!(
+            matcher.on_modifier(
+                Modifier::Shift,
+                ModifierSide::Left,
+                true,
+                t0 + Duration::from_millis(150)
+            ),
+            vec![HotkeyEvent::Pressed(1)]
+        );
+        assert_eq!(
+            matcher.on_modifier(
+                Modifier::Shift,
+                ModifierSide::Left,
+                false,
+                t0 + Duration::from_millis(200)
+            ),
+            vec![HotkeyEvent::Released(1)]
+        );
+    }
+
+    #[test]
+    fn test_slow_double_tap_does_not_fire() {
+        let mut matcher = shift_double_tap();
+        let t0 = Instant::now();
+
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, true, t0);
+        matcher.on_modifier(
+            Modifier::Shift,
+            ModifierSide::Left,
+            false,
+            t0 + Duration::from_millis(50),
+        );
+        assert!(matcher
+            .on_modifier(
+                Modifier::Shift,
+                ModifierSide::Left,
+                true,
+                t0 + Duration::from_millis(500)
+            )
+            .is_empty());
+    }
+
+    #[test]
+    fn test_key_between_taps_interrupts_sequence() {
+        let mut matcher = shift_double_tap();
+        let t0 = Instant::now();
+
+        matcher.on_modifier(Modifier::Shift, ModifierSide::Left, true, t0);
+        assert_eq!(
+            matcher.on_key_down(Key::F8, Instant::now()),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        matcher.on_modifier(
+            Modifier::Shift,
+            ModifierSide::Left,
+            false,
+            t0 + Duration::from_millis(50),
+        );
+        assert!(matcher
+            .on_modifier(
+                Modifier::Shift,
+                ModifierSide::Left,
+                true,
+                t0 + Duration::from_millis(100)
+            )
+            .is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+//! A virtual keyboard for testing hotkey handling without `/dev/input`.
+//!
+//! The mock plugs into the same listener loop as real keyboards, so its key
+//! events go through the full Linux matching, including modifier tracking
+//! and reconnection.
+
+use crate::channel::Sender;
+use crate::event::HotkeyEvent;
+use crate::hotkey::{Hotkey, ModifierSide, Modifiers};
+use crate::key::Key;
+use crate::linux::{
+    run_keyboard_loop, to_evdev_key, EventProcessor, KeyCapabilities, Keyboard, KeyboardScanner,
+    StaleEvents,
+};
+use crate::listener::ListenerConfig;
+use anyhow::Result;
+use std::collections::{HashSet, VecDeque};
+use std::os::fd::RawFd;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// A virtual keyboard feeding a listener built with
+/// [`HotkeyListenerBuilder::build_mock`](crate::HotkeyListenerBuilder::build_mock).
+///
+/// Presses and releases pushed here are read by the listener thread like a
+/// real keyboard's, so tests can assert on the events a key combo produces:
+///
+/// ```
+/// use hotkey_listener::{parse_hotkey, HotkeyEvent, HotkeyListenerBuilder, Key, Modifiers};
+/// use std::time::Duration;
+///
+/// let (listener, keyboard) = HotkeyListenerBuilder::new()
+///     .add_hotkey(parse_hotkey("Shift+F8").unwrap())
+///     .build_mock()
+///     .unwrap();
+/// let handle = listener.start().unwrap();
+///
+/// keyboard.press_modifiers(Modifiers { shift: true, ..Modifiers::default() });
+/// keyboard.press(Key::F8);
+/// let event = handle.recv_timeout(Duration::from_secs(1)).unwrap();
+/// assert_eq!(event, HotkeyEvent::Pressed(0));
+/// ```
+///
+/// Clones push to the same keyboard. Events are not timestamped, so they
+/// carry the time the listener handled them.
+#[derive(Debug, Clone, Default)]
+pub struct MockKeyboard(Arc<Mutex<VecDeque<evdev::InputEvent>>>);
+
+impl MockKeyboard {
+    /// Create a keyboard with no pending input.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Press `key`.
+    pub fn press(&self, key: Key) {
+        self.push(to_evdev_key(key), 1);
+    }
+
+    /// Release `key`.
+    pub fn release(&self, key: Key) {
+        self.push(to_evdev_key(key), 0);
+    }
+
+    /// Press every modifier in `modifiers`, on the side it requires or on
+    /// the left if it accepts either.
+    pub fn press_modifiers(&self, modifiers: Modifiers) {
+        for key in modifier_keys(modifiers) {
+            self.push(key, 1);
+        }
+    }
+
+    /// Release every modifier in `modifiers`, as pressed by
+    /// [`press_modifiers`](Self::press_modifiers).
+    pub fn release_modifiers(&self, modifiers: Modifiers) {
+        for key in modifier_keys(modifiers).into_iter().rev() {
+            self.push(key, 0);
+        }
+    }
+
+    /// Press and release `hotkey`: its modifiers, then its key.
+    pub fn tap(&self, hotkey: &Hotkey) {
+        self.press_modifiers(hotkey.modifiers);
+        if let Some(key) = hotkey.key {
+            self.press(key);
+            self.release(key);
+        }
+        self.release_modifiers(hotkey.modifiers);
+    }
+
+    fn push(&self, key: evdev::Key, value: i32) {
+        let event = evdev::InputEvent::new(evdev::EventType::KEY, key.code(), value);
+        self.0.lock().unwrap().push_back(event);
+    }
+}
+
+/// The evdev keys to press for `modifiers`.
+fn modifier_keys(modifiers: Modifiers) -> Vec<evdev::Key> {
+    use evdev::Key as K;
+    [
+        (
+            modifiers.shift,
+            modifiers.shift_side,
+            K::KEY_LEFTSHIFT,
+            K::KEY_RIGHTSHIFT,
+        ),
+        (
+            modifiers.ctrl,
+            modifiers.ctrl_side,
+            K::KEY_LEFTCTRL,
+            K::KEY_RIGHTCTRL,
+        ),
+        (
+            modifiers.alt,
+            modifiers.alt_side,
+            K::KEY_LEFTALT,
+            K::KEY_RIGHTALT,
+        ),
+        (
+            modifiers.meta,
+            modifiers.meta_side,
+            K::KEY_LEFTMETA,
+            K::KEY_RIGHTMETA,
+        ),
+    ]
+    .into_iter()
+    .filter(|&(held, ..)| held)
+    .map(|(_, side, left, right)| match side {
+        Some(ModifierSide::Right) => right,
+        _ => left,
+    })
+    .collect()
+}
+
+impl StaleEvents for MockKeyboard {
+    fn label(&self) -> Option<String> {
+        Some("mock keyboard".into())
+    }
+
+    // Keep events pushed before a reconnect for the new loop
+    fn discard_batch(&mut self) -> std::io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl KeyCapabilities for MockKeyboard {
+    fn reports_keyboard_keys(&self) -> bool {
+        true
+    }
+}
+
+impl Keyboard for MockKeyboard {
+    fn read_events(&mut self) -> std::io::Result<Vec<evdev::InputEvent>> {
+        Ok(self.0.lock().unwrap().drain(..).collect())
+    }
+
+    fn device_id(&self) -> Option<String> {
+        None
+    }
+
+    fn led(&self, _led: evdev::LedType) -> Option<bool> {
+        None
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        None
+    }
+
+    fn set_grabbed(&mut self, _grabbed: bool) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Finds the mock keyboard again whenever the listener rescans.
+struct MockScanner(MockKeyboard);
+
+impl KeyboardScanner for MockScanner {
+    type Keyboard = MockKeyboard;
+
+    fn scan(&self) -> Result<Vec<MockKeyboard>> {
+        Ok(vec![self.0.clone()])
+    }
+
+    fn scan_new(&self, _known: &HashSet<PathBuf>) -> Vec<(PathBuf, MockKeyboard)> {
+        Vec::new()
+    }
+
+    fn paths(&self) -> HashSet<PathBuf> {
+        HashSet::new()
+    }
+}
+
+/// Spawn the listener loop reading `keyboard`.
+pub(crate) fn start_mock_listener(
+    keyboard: MockKeyboard,
+    hotkeys: Vec<Hotkey>,
+    config: ListenerConfig,
+    running: Arc<AtomicBool>,
+    tx: Sender<HotkeyEvent>,
+) {
+    let scanner = MockScanner(keyboard.clone());
+    let processor = EventProcessor::new(hotkeys, config.clone());
+    let listener_thread = config.shared.thread.clone();
+    listener_thread.set(thread::spawn(move || {
+        run_keyboard_loop(vec![keyboard], scanner, processor, &config, &running, &tx)
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::parse_hotkey;
+    use crate::HotkeyListenerBuilder;
+    use std::time::Duration;
+
+    #[test]
+    fn test_mock_keyboard_drives_the_listener() {
+        let (listener, keyboard) = HotkeyListenerBuilder::new()
+            .add_hotkey(parse_hotkey("F8").unwrap())
+            .add_hotkey(parse_hotkey("RCtrl+F8").unwrap())
+            .build_mock()
+            .unwrap();
+        let handle = listener.start().unwrap();
+        let timeout = Duration::from_secs(2);
+
+        keyboard.tap(&parse_hotkey("F8").unwrap());
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Pressed(0)));
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Released(0)));
+
+        keyboard.tap(&parse_hotkey("RCtrl+F8").unwrap());
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Pressed(1)));
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Released(1)));
+
+        // The left Ctrl doesn't match a hotkey requiring the right one
+        keyboard.tap(&parse_hotkey("LCtrl+F8").unwrap());
+        keyboard.tap(&parse_hotkey("F8").unwrap());
+        assert_eq!(handle.recv_timeout(timeout), Ok(HotkeyEvent::Pressed(0)));
+    }
+
+    #[test]
+    fn test_current_modifiers_follow_the_keyboard() {
+        let (listener, keyboard) = HotkeyListenerBuilder::new()
+            .add_hotkey(parse_hotkey("F8").unwrap())
+            .build_mock()
+            .unwrap();
+        let handle = listener.start().unwrap();
+        let wait_for = |expected: Modifiers| {
+            let deadline = std::time::Instant::now() + Duration::from_secs(2);
+            while handle.current_modifiers() != expected {
+                assert!(std::time::Instant::now() < deadline, "{:?}", expected);
+                thread::sleep(Duration::from_millis(5));
+            }
+        };
+        assert_eq!(handle.current_modifiers(), Modifiers::default());
+
+        let shift_ctrl = Modifiers {
+            shift: true,
+            ctrl: true,
+            ..Modifiers::default()
+        };
+        keyboard.press_modifiers(shift_ctrl);
+        wait_for(shift_ctrl);
+
+        keyboard.release_modifiers(Modifiers {
+            ctrl: true,
+            ..Modifiers::default()
+        });
+        wait_for(Modifiers {
+            shift: true,
+            ..Modifiers::default()
+        });
+
+        keyboard.release_modifiers(shift_ctrl);
+        wait_for(Modifiers::default());
+    }
+}
@@ -0,0 +1,152 @@
+//! Structured summary of the environment a listener starts in.
+
+use crate::capabilities::{capabilities, Capabilities};
+use crate::hotkey::Hotkey;
+use crate::keyboard::KeyboardInfo;
+
+/// The results of the read-only checks run by
+/// [`HotkeyListenerBuilder::build_with_report`], for a diagnostics panel.
+///
+/// [`HotkeyListenerBuilder::build_with_report`]: crate::HotkeyListenerBuilder::build_with_report
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StartupReport {
+    /// Compiled-in features and backend.
+    pub capabilities: Capabilities,
+    /// Keyboards found, see [`list_keyboards`](crate::list_keyboards).
+    pub keyboards: Vec<KeyboardInfo>,
+    /// Whether `/dev/input` has devices that couldn't be opened for lack of
+    /// permissions. Always false on macOS.
+    pub permission_denied: bool,
+    /// Registered hotkeys the platform backend can't detect.
+    pub unsupported_hotkeys: Vec<Hotkey>,
+    /// Human-readable descriptions of anything that may stop hotkeys working.
+    pub warnings: Vec<String>,
+}
+
+impl StartupReport {
+    /// Gather the report for the current system.
+    pub(crate) fn collect(hotkeys: &[Hotkey]) -> Self {
+        #[cfg(target_os = "linux")]
+        let permission_denied = crate::linux::input_permission_denied();
+        #[cfg(not(target_os = "linux"))]
+        let permission_denied = false;
+        Self::new(
+            capabilities(),
+            hotkeys,
+            crate::keyboard::list_keyboards(),
+            permission_denied,
+            crate::layout::detect_layout().as_deref(),
+        )
+    }
+
+    fn new(
+        capabilities: Capabilities,
+        hotkeys: &[Hotkey],
+        keyboards: Vec<KeyboardInfo>,
+        permission_denied: bool,
+        layout: Option<&str>,
+    ) -> Self {
+        let unsupported_hotkeys: Vec<Hotkey> = hotkeys
+            .iter()
+            .filter(|hotkey| !is_supported(hotkey, capabilities.backend))
+            .cloned()
+            .collect();
+
+        let mut warnings = Vec::new();
+        if permission_denied {
+            warnings.push(
+                "Permission denied reading /dev/input; join the 'input' group or run as root"
+                    .to_string(),
+            );
+        }
+        if keyboards.is_empty() && capabilities.backend == "evdev" {
+            warnings.push("No keyboards found".to_string());
+        }
+        for hotkey in &unsupported_hotkeys {
+            warnings.push(format!(
+                "Hotkey {} is not supported by the {} backend",
+                hotkey, capabilities.backend
+            ));
+        }
+        warnings.extend(crate::layout::layout_mismatch(hotkeys, layout));
+
+        Self {
+            capabilities,
+            keyboards,
+            permission_denied,
+            unsupported_hotkeys,
+            warnings,
+        }
+    }
+}
+
+/// Whether the named backend detects `hotkey`.
+fn is_supported(hotkey: &Hotkey, backend: &str) -> bool {
+    match backend {
+        "evdev" => true,
+        // See the warnings logged by the macOS backend
+        "rdev" => {
+            hotkey.num_lock.is_none()
+                && hotkey.caps_lock.is_none()
+                && !matches!(hotkey.key, Some(crate::Key::Raw(_)))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::parse_hotkey;
+    use crate::key::Key;
+    use crate::keyboard::BusType;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[test]
+    fn test_report_reflects_environment() {
+        let keyboard = KeyboardInfo {
+            name: "USB Keyboard".to_string(),
+            path: PathBuf::from("/dev/input/event5"),
+            physical_path: None,
+            bus_type: BusType::Usb,
+            vendor: 0x046d,
+            product: 0xc31c,
+            key_count: 104,
+        };
+        let sequence = Hotkey::sequence(Key::F12, 3, Duration::from_millis(500));
+        let raw = parse_hotkey("raw:190").unwrap();
+        let hotkeys = vec![parse_hotkey("Shift+F8").unwrap(), sequence, raw.clone()];
+        let capabilities = Capabilities {
+            backend: "rdev",
+            ..capabilities()
+        };
+
+        let report = StartupReport::new(
+            capabilities,
+            &hotkeys,
+            vec![keyboard.clone()],
+            false,
+            Some("us"),
+        );
+        assert_eq!(report.keyboards, vec![keyboard]);
+        assert!(!report.permission_denied);
+        assert_eq!(report.unsupported_hotkeys, vec![raw]);
+        assert_eq!(
+            report.warnings,
+            vec!["Hotkey raw:190 is not supported by the rdev backend".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_report_warns_about_permissions() {
+        let capabilities = Capabilities {
+            backend: "evdev",
+            ..capabilities()
+        };
+        let report = StartupReport::new(capabilities, &[], Vec::new(), true, None);
+        assert!(report.permission_denied);
+        assert_eq!(report.warnings.len(), 2);
+    }
+}
@@ -0,0 +1,138 @@
+//! Listener status published to async consumers.
+
+/// Snapshot of the listener state.
+///
+/// With the `tokio` feature, [`HotkeyListenerHandle::status`] returns a watch
+/// receiver that is updated on every transition.
+///
+/// [`HotkeyListenerHandle::status`]: crate::HotkeyListenerHandle
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListenerStatus {
+    /// Whether the listener thread is running.
+    pub running: bool,
+    /// Number of keyboards currently being read. Always 0 on macOS, where
+    /// keyboards aren't tracked individually.
+    pub keyboard_count: usize,
+    /// The most recent device error, cleared once the keyboards reconnect.
+    pub last_error: Option<String>,
+    /// Keyboards another process held an exclusive grab on at startup, so
+    /// their keys never reach the listener. Only checked on Linux when
+    /// `HotkeyListenerBuilder::verify_devices` is set.
+    pub grabbed_keyboards: Vec<String>,
+}
+
+/// Publishes status transitions from the backend.
+///
+/// Without the `tokio` feature there are no subscribers and every update is a no-op.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StatusReporter {
+    #[cfg(feature = "tokio")]
+    tx: std::sync::Arc<tokio::sync::watch::Sender<ListenerStatus>>,
+}
+
+impl StatusReporter {
+    /// A receiver that observes every subsequent update.
+    #[cfg(feature = "tokio")]
+    pub fn subscribe(&self) -> tokio::sync::watch::Receiver<ListenerStatus> {
+        self.tx.subscribe()
+    }
+
+    /// The listener thread started.
+    pub fn started(&self) {
+        self.update(|status| status.running = true);
+    }
+
+    /// The listener thread exited.
+    pub fn stopped(&self) {
+        self.update(|status| status.running = false);
+    }
+
+    /// A guard reporting [`stopped`](Self::stopped) when dropped.
+    ///
+    /// Backends hold one for the life of their listener thread, so every
+    /// way it exits, including a panic, clears `running`.
+    pub fn stop_on_drop(&self) -> StopGuard {
+        StopGuard(self.clone())
+    }
+
+    /// Keyboards were (re)connected, clearing any previous error.
+    pub fn connected(&self, keyboard_count: usize) {
+        self.update(|status| {
+            status.keyboard_count = keyboard_count;
+            status.last_error = None;
+        });
+    }
+
+    /// A keyboard was added or dropped without an error.
+    pub fn keyboard_count(&self, keyboard_count: usize) {
+        self.update(|status| status.keyboard_count = keyboard_count);
+    }
+
+    /// The startup check found keyboards grabbed by another process.
+    pub fn grabbed(&self, keyboards: Vec<String>) {
+        self.update(|status| status.grabbed_keyboards = keyboards);
+    }
+
+    /// Reading the keyboards failed and they will be rescanned.
+    pub fn disconnected(&self, error: impl ToString) {
+        let error = error.to_string();
+        self.update(|status| status.last_error = Some(error));
+    }
+
+    #[cfg(feature = "tokio")]
+    fn update(&self, modify: impl FnOnce(&mut ListenerStatus)) {
+        self.tx.send_modify(modify);
+    }
+
+    #[cfg(not(feature = "tokio"))]
+    fn update(&self, modify: impl FnOnce(&mut ListenerStatus)) {
+        let _ = modify;
+    }
+}
+
+/// Reports the listener stopped when dropped, see
+/// [`StatusReporter::stop_on_drop`].
+pub(crate) struct StopGuard(StatusReporter);
+
+impl Drop for StopGuard {
+    fn drop(&mut self) {
+        self.0.stopped();
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_watch_observes_disconnect_and_reconnect() {
+        let reporter = StatusReporter::default();
+        let mut rx = reporter.subscribe();
+
+        reporter.started();
+        reporter.connected(2);
+        rx.changed().await.unwrap();
+        assert_eq!(
+            *rx.borrow_and_update(),
+            ListenerStatus {
+                running: true,
+                keyboard_count: 2,
+                last_error: None,
+                grabbed_keyboards: Vec::new(),
+            }
+        );
+
+        reporter.disconnected("No such device");
+        rx.changed().await.unwrap();
+        assert_eq!(
+            rx.borrow_and_update().last_error.as_deref(),
+            Some("No such device")
+        );
+
+        reporter.connected(1);
+        rx.changed().await.unwrap();
+        let status = rx.borrow_and_update().clone();
+        assert_eq!(status.keyboard_count, 1);
+        assert_eq!(status.last_error, None);
+    }
+}
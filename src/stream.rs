@@ -0,0 +1,68 @@
+//! Executor-agnostic `futures::Stream` of hotkey events.
+
+use crate::event::HotkeyEvent;
+use crate::listener::HotkeyListenerHandle;
+use futures_channel::mpsc::{unbounded, UnboundedReceiver};
+use futures_core::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Hotkey events as a `futures::Stream`, created with
+/// [`HotkeyListenerHandle::into_stream`].
+///
+/// A forwarding thread owns the handle and passes its events on, so the
+/// stream works with any executor. Dropping the stream stops the listener
+/// shortly after; the stream ends once the listener has stopped.
+pub struct HotkeyEventStream {
+    rx: UnboundedReceiver<HotkeyEvent>,
+}
+
+impl HotkeyEventStream {
+    pub(crate) fn new(handle: HotkeyListenerHandle) -> Self {
+        let (tx, rx) = unbounded();
+        let closed = tx.clone();
+        handle.forward(
+            move || closed.is_closed(),
+            move |event| tx.unbounded_send(event).is_ok(),
+        );
+        Self { rx }
+    }
+}
+
+impl Stream for HotkeyEventStream {
+    type Item = HotkeyEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<HotkeyEvent>> {
+        Pin::new(&mut self.rx).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel;
+    use crate::listener::Shared;
+    use futures::executor::block_on;
+    use futures::StreamExt;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_stream_yields_events_until_listener_stops() {
+        let (tx, rx) = channel::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let handle = HotkeyListenerHandle::new(Arc::clone(&running), rx, Shared::default());
+        let stream = handle.into_stream();
+
+        tx.send(HotkeyEvent::Pressed(0)).unwrap();
+        tx.send(HotkeyEvent::Released(0)).unwrap();
+        drop(tx);
+
+        let events: Vec<HotkeyEvent> = block_on(stream.collect());
+        assert_eq!(
+            events,
+            vec![HotkeyEvent::Pressed(0), HotkeyEvent::Released(0)]
+        );
+        assert!(!running.load(Ordering::SeqCst));
+    }
+}
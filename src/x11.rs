@@ -0,0 +1,510 @@
+//! Alternative Linux backend registering hotkeys with the X server.
+//!
+//! Used when `/dev/input` can't be read but an X11 display is available, or
+//! when selected with [`Backend::X11`](crate::Backend::X11). Each hotkey is
+//! grabbed with `XGrabKey` on the root window, so only registered combos are
+//! seen, and NumLock and CapsLock conditions are checked against the lock
+//! state X reports with each key. Not supported, with a warning when the
+//! listener starts:
+//!
+//! - modifier-only and sequence hotkeys, and raw codes past X's keycodes
+//! - modifier sides, as X doesn't tell left and right modifiers apart
+//! - modifier match modes other than `Exact`, since each combo is grabbed
+//!   exactly
+//! - chords, cooldowns, double taps, long presses, `max_hold`, `debounce`
+//!   and `modifier_timeout`
+//!
+//! Options that need to see every key or the devices themselves, like
+//! `capture_next_combo`, `inject_key`, `report_devices` or
+//! `emit_device_changes`, have no effect.
+
+use crate::channel::Sender;
+use crate::event::HotkeyEvent;
+use crate::hotkey::{Hotkey, Modifiers};
+use crate::key::Key;
+use crate::linux::to_evdev_key;
+use crate::listener::{HotkeyUpdate, ListenerConfig, ModifierMatch};
+use crate::matcher::ActiveHotkeys;
+use anyhow::{Context, Result};
+use nix::errno::Errno;
+use nix::sys::epoll::{Epoll, EpollCreateFlags, EpollEvent, EpollFlags, EpollTimeout};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use x11rb::connection::Connection;
+use x11rb::protocol::xkb::{self, ConnectionExt as _};
+use x11rb::protocol::xproto::{ConnectionExt as _, GrabMode, Keycode, ModMask, Window};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+/// X servers on Linux number keys by their evdev code plus this offset.
+const KEYCODE_OFFSET: u16 = 8;
+
+/// Lock modifiers (CapsLock and Mod2, usually NumLock) that must not stop a
+/// hotkey from matching. Each hotkey is grabbed once per combination of them.
+fn lock_masks() -> [u16; 4] {
+    let (caps, num) = (ModMask::LOCK.bits(), ModMask::M2.bits());
+    [0, caps, num, caps | num]
+}
+
+/// The modifier bits hotkeys are matched on: Shift, Control, Mod1 (Alt) and
+/// Mod4 (Super).
+fn modifier_mask() -> u16 {
+    ModMask::SHIFT.bits() | ModMask::CONTROL.bits() | ModMask::M1.bits() | ModMask::M4.bits()
+}
+
+/// How long the listener thread blocks waiting for X events before it
+/// checks whether it should stop or apply hotkey changes.
+const WAIT_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// The X keycode for `key`, or `None` for raw evdev codes past the 255 X
+/// keycodes can hold.
+fn to_keycode(key: Key) -> Option<Keycode> {
+    let code = to_evdev_key(key).code().checked_add(KEYCODE_OFFSET)?;
+    Keycode::try_from(code).ok()
+}
+
+/// The X modifier mask for `modifiers`, ignoring sides.
+fn to_mod_mask(modifiers: &Modifiers) -> u16 {
+    let mut mask = 0;
+    if modifiers.shift {
+        mask |= ModMask::SHIFT.bits();
+    }
+    if modifiers.ctrl {
+        mask |= ModMask::CONTROL.bits();
+    }
+    if modifiers.alt {
+        mask |= ModMask::M1.bits();
+    }
+    if modifiers.meta {
+        mask |= ModMask::M4.bits();
+    }
+    mask
+}
+
+/// The keycode and modifier mask to grab for `hotkey`, or `None` if X can't
+/// grab it.
+fn to_grab(hotkey: &Hotkey) -> Option<(Keycode, u16)> {
+    if hotkey.is_modifier_only() {
+        log::warn!("Modifier-only hotkey {} is not supported on X11", hotkey);
+        return None;
+    }
+    if hotkey.sequence.is_some() {
+        log::warn!("Key sequence hotkey {} is not supported on X11", hotkey);
+        return None;
+    }
+    let Some(keycode) = hotkey.key.and_then(to_keycode) else {
+        log::warn!("Hotkey {} has no X keycode and can't be grabbed", hotkey);
+        return None;
+    };
+    Some((keycode, to_mod_mask(&hotkey.modifiers)))
+}
+
+/// Warn about the options of `hotkeys` and `config` the X11 backend ignores.
+fn warn_unsupported(hotkeys: &[Hotkey], config: &ListenerConfig) {
+    for (idx, hotkey) in hotkeys.iter().enumerate() {
+        let m = &hotkey.modifiers;
+        if [m.shift_side, m.ctrl_side, m.alt_side, m.meta_side]
+            .iter()
+            .any(Option::is_some)
+        {
+            log::warn!(
+                "X11 can't tell modifier sides apart, {} matches either",
+                hotkey
+            );
+        }
+        let unsupported = [
+            (config.cooldown(idx).is_some(), "Cooldown"),
+            (config.double_tap(idx).is_some(), "Double tap"),
+            (config.long_press(idx).is_some(), "Long press"),
+            (
+                config.hotkey_modifier_match(idx) != ModifierMatch::Exact,
+                "Modifier match mode",
+            ),
+        ];
+        for (_, option) in unsupported.iter().filter(|(set, _)| *set) {
+            log::warn!("{} of hotkey {} is not supported on X11", option, hotkey);
+        }
+    }
+    let unsupported = [
+        (!config.chords.is_empty(), "Chords"),
+        (config.max_hold.is_some(), "max_hold"),
+        (config.debounce.is_some(), "debounce"),
+        (config.modifier_timeout.is_some(), "modifier_timeout"),
+    ];
+    for (_, option) in unsupported.iter().filter(|(set, _)| *set) {
+        log::warn!("{} is not supported on X11", option);
+    }
+}
+
+/// Turns the key events X delivers for grabbed combos into hotkey events.
+struct GrabMatcher {
+    grabs: Vec<Option<(Keycode, u16)>>,
+    /// Required NumLock and CapsLock state of each hotkey.
+    locks: Vec<(Option<bool>, Option<bool>)>,
+    /// Hotkeys removed through the handle. Their indices stay taken.
+    removed: Vec<bool>,
+    /// Keycodes currently held, to tell auto-repeat from a new press.
+    held: HashSet<Keycode>,
+    active: ActiveHotkeys,
+    config: ListenerConfig,
+}
+
+impl GrabMatcher {
+    fn new(hotkeys: &[Hotkey], config: ListenerConfig) -> Self {
+        Self {
+            grabs: hotkeys.iter().map(to_grab).collect(),
+            locks: hotkeys.iter().map(lock_conditions).collect(),
+            removed: vec![false; hotkeys.len()],
+            held: HashSet::new(),
+            active: ActiveHotkeys::new(hotkeys.len()),
+            config,
+        }
+    }
+
+    /// Register `hotkey`, returning what to grab for it.
+    fn add(&mut self, hotkey: &Hotkey) -> Option<(Keycode, u16)> {
+        let grab = to_grab(hotkey);
+        self.grabs.push(grab);
+        self.locks.push(lock_conditions(hotkey));
+        self.removed.push(false);
+        self.active.push();
+        grab
+    }
+
+    /// Unregister the hotkey at `idx`, returning what to ungrab unless
+    /// another hotkey still uses the same combo.
+    fn remove(&mut self, idx: usize) -> Option<(Keycode, u16)> {
+        let removed = self.removed.get_mut(idx)?;
+        if *removed {
+            return None;
+        }
+        *removed = true;
+        let grab = self.grabs[idx]?;
+        let shared = self
+            .grabs
+            .iter()
+            .zip(&self.removed)
+            .any(|(other, removed)| !removed && *other == Some(grab));
+        (!shared).then_some(grab)
+    }
+
+    fn is_enabled(&self, idx: usize) -> bool {
+        !self.removed[idx] && self.config.shared.disabled.is_enabled(idx)
+    }
+
+    fn press(&mut self, keycode: Keycode, state: u16, at: Instant) -> Vec<HotkeyEvent> {
+        let mut events = Vec::new();
+        if !self.held.insert(keycode) {
+            if self.config.emit_repeats
+                && !self.config.tap_mode
+                && self.config.is_active(SystemTime::now())
+            {
+                for idx in 0..self.grabs.len() {
+                    if self.grabs[idx].is_some_and(|(code, _)| code == keycode)
+                        && self.active.is_pressed(idx)
+                        && self.is_enabled(idx)
+                    {
+                        events.push(HotkeyEvent::Repeat(self.config.event_id(idx)));
+                    }
+                }
+            }
+            return events;
+        }
+        if !self.config.is_active(SystemTime::now()) {
+            return events;
+        }
+        let mask = state & modifier_mask();
+        let num_lock = state & ModMask::M2.bits() != 0;
+        let caps_lock = state & ModMask::LOCK.bits() != 0;
+        for idx in 0..self.grabs.len() {
+            let (num, caps) = self.locks[idx];
+            if self.grabs[idx] == Some((keycode, mask))
+                && num.is_none_or(|on| on == num_lock)
+                && caps.is_none_or(|on| on == caps_lock)
+                && self.is_enabled(idx)
+            {
+                self.active.press(idx, at);
+                if !self.config.tap_mode {
+                    events.push(HotkeyEvent::Pressed(self.config.event_id(idx)));
+                }
+            }
+        }
+        events
+    }
+
+    fn release(&mut self, keycode: Keycode) -> Vec<HotkeyEvent> {
+        self.held.remove(&keycode);
+        let mut events = Vec::new();
+        // Release whatever this key pressed, whatever the modifiers are now
+        for idx in 0..self.grabs.len() {
+            if self.grabs[idx].is_some_and(|(code, _)| code == keycode)
+                && self.active.release(idx).is_some()
+            {
+                let id = self.config.event_id(idx);
+                events.push(if self.config.tap_mode {
+                    HotkeyEvent::Tapped(id)
+                } else {
+                    HotkeyEvent::Released(id)
+                });
+            }
+        }
+        events
+    }
+}
+
+/// The NumLock and CapsLock state `hotkey` requires, matched against the
+/// Mod2 (usually NumLock) and Lock bits of each key event.
+fn lock_conditions(hotkey: &Hotkey) -> (Option<bool>, Option<bool>) {
+    (hotkey.num_lock, hotkey.caps_lock)
+}
+
+/// Grab `keycode` with `mask` on `root`, under every lock combination.
+fn grab(conn: &RustConnection, root: Window, (keycode, mask): (Keycode, u16)) -> Result<()> {
+    for lock in lock_masks() {
+        conn.grab_key(
+            false,
+            root,
+            ModMask::from(mask | lock),
+            keycode,
+            GrabMode::ASYNC,
+            GrabMode::ASYNC,
+        )?
+        .check()
+        .context("Key combo is already grabbed by another X client")?;
+    }
+    Ok(())
+}
+
+fn ungrab(conn: &RustConnection, root: Window, (keycode, mask): (Keycode, u16)) {
+    for lock in lock_masks() {
+        if let Ok(cookie) = conn.ungrab_key(keycode, root, ModMask::from(mask | lock)) {
+            cookie.ignore_error();
+        }
+    }
+}
+
+/// Ask the server not to send a release before every auto-repeated press.
+fn enable_detectable_autorepeat(conn: &RustConnection) -> Result<()> {
+    conn.xkb_use_extension(1, 0)?.reply()?;
+    conn.xkb_per_client_flags(
+        xkb::ID::USE_CORE_KBD.into(),
+        xkb::PerClientFlag::DETECTABLE_AUTO_REPEAT,
+        xkb::PerClientFlag::DETECTABLE_AUTO_REPEAT,
+        xkb::BoolCtrl::from(0u32),
+        xkb::BoolCtrl::from(0u32),
+        xkb::BoolCtrl::from(0u32),
+    )?
+    .reply()?;
+    Ok(())
+}
+
+/// Connect to the X display, grab the hotkeys and read their events in a
+/// background thread.
+pub(crate) fn start_x11_listener(
+    hotkeys: Vec<Hotkey>,
+    config: ListenerConfig,
+    running: Arc<AtomicBool>,
+    tx: Sender<HotkeyEvent>,
+) -> Result<()> {
+    let (conn, screen) = x11rb::connect(None).context("Failed to connect to the X display")?;
+    let root = conn.setup().roots[screen].root;
+    let epoll = Epoll::new(EpollCreateFlags::EPOLL_CLOEXEC)
+        .and_then(|epoll| {
+            epoll.add(conn.stream(), EpollEvent::new(EpollFlags::EPOLLIN, 0))?;
+            Ok(epoll)
+        })
+        .context("Failed to wait on the X connection")?;
+    warn_unsupported(&hotkeys, &config);
+    if let Err(e) = enable_detectable_autorepeat(&conn) {
+        log::warn!("Failed to enable detectable auto-repeat: {}", e);
+    }
+
+    let mut matcher = GrabMatcher::new(&hotkeys, config.clone());
+    for (hotkey, grab_combo) in hotkeys.iter().zip(&matcher.grabs) {
+        if let Some(combo) = *grab_combo {
+            if let Err(e) = grab(&conn, root, combo) {
+                log::warn!("Failed to grab {}: {:#}", hotkey, e);
+            }
+        }
+    }
+    conn.flush()?;
+
+    let listener_thread = config.shared.thread.clone();
+    listener_thread.set(thread::spawn(move || {
+        let _stopped = config.shared.status.stop_on_drop();
+        while running.load(Ordering::Relaxed) {
+            for update in config.shared.updates.drain() {
+                match update {
+                    HotkeyUpdate::Add(hotkey) => {
+                        if let Some(combo) = matcher.add(&hotkey) {
+                            if let Err(e) = grab(&conn, root, combo) {
+                                log::warn!("Failed to grab {}: {:#}", hotkey, e);
+                            }
+                        }
+                    }
+                    HotkeyUpdate::Remove(idx) => {
+                        if let Some(combo) = matcher.remove(idx) {
+                            ungrab(&conn, root, combo);
+                        }
+                    }
+                }
+            }
+            let _ = conn.flush();
+
+            loop {
+                let event = match conn.poll_for_event() {
+                    Ok(Some(event)) => event,
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::error!("Lost connection to the X display: {}", e);
+                        running.store(false, Ordering::SeqCst);
+                        return;
+                    }
+                };
+                let hotkey_events = match event {
+                    Event::KeyPress(press) => {
+                        matcher.press(press.detail, press.state.into(), Instant::now())
+                    }
+                    Event::KeyRelease(release) => matcher.release(release.detail),
+                    _ => continue,
+                };
+                for event in hotkey_events {
+                    config.shared.record(event, SystemTime::now(), None, None);
+                    let _ = tx.send(event);
+                }
+            }
+            // Every queued event was handled, so wait for the server to send more
+            let mut events = [EpollEvent::empty()];
+            let timeout = EpollTimeout::try_from(WAIT_TIMEOUT).expect("timeout fits in epoll");
+            match epoll.wait(&mut events, timeout) {
+                Ok(_) | Err(Errno::EINTR) => {}
+                Err(e) => {
+                    log::error!("Failed to wait on the X connection: {}", e);
+                    running.store(false, Ordering::SeqCst);
+                    return;
+                }
+            }
+        }
+    }));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::parse_hotkey;
+
+    #[test]
+    fn test_every_key_maps_to_distinct_keycode() {
+        let mut seen = HashSet::new();
+        for &key in Key::all() {
+            let keycode = to_keycode(key).unwrap();
+            assert!(seen.insert(keycode), "{} shares keycode {}", key, keycode);
+        }
+        assert_eq!(to_keycode(Key::A).unwrap(), 38);
+        assert_eq!(to_keycode(Key::F8).unwrap(), 74);
+    }
+
+    #[test]
+    fn test_raw_keycode_grabs_only_within_x_range() {
+        assert_eq!(to_keycode(Key::Raw(190)), Some(198));
+        assert_eq!(to_keycode(Key::Raw(247)), Some(255));
+        assert_eq!(to_keycode(Key::Raw(248)), None);
+        assert_eq!(to_keycode(Key::Raw(300)), None);
+        assert_eq!(to_keycode(Key::Raw(u16::MAX)), None);
+        assert_eq!(
+            to_grab(&parse_hotkey("Ctrl+raw:190").unwrap()),
+            Some((198, ModMask::CONTROL.bits()))
+        );
+        assert_eq!(to_grab(&parse_hotkey("raw:300").unwrap()), None);
+    }
+
+    #[test]
+    fn test_grab_matcher_matches_exact_modifiers() {
+        let hotkeys = vec![
+            parse_hotkey("Ctrl+F8").unwrap(),
+            parse_hotkey("F8").unwrap(),
+            parse_hotkey("RCtrl+F9").unwrap(),
+        ];
+        let mut matcher = GrabMatcher::new(&hotkeys, ListenerConfig::default());
+        let f8 = to_keycode(Key::F8).unwrap();
+        let ctrl_caps = ModMask::CONTROL.bits() | ModMask::LOCK.bits();
+        let now = Instant::now();
+
+        assert_eq!(
+            matcher.press(f8, ctrl_caps, now),
+            vec![HotkeyEvent::Pressed(0)]
+        );
+        // Auto-repeat doesn't press again
+        assert!(matcher.press(f8, ctrl_caps, now).is_empty());
+        assert_eq!(matcher.release(f8), vec![HotkeyEvent::Released(0)]);
+
+        assert_eq!(matcher.press(f8, 0, now), vec![HotkeyEvent::Pressed(1)]);
+        assert_eq!(matcher.release(f8), vec![HotkeyEvent::Released(1)]);
+
+        // Sides aren't reported, so RCtrl matches either Ctrl
+        let f9 = to_keycode(Key::F9).unwrap();
+        assert_eq!(
+            matcher.press(f9, ModMask::CONTROL.bits(), now),
+            vec![HotkeyEvent::Pressed(2)]
+        );
+    }
+
+    #[test]
+    fn test_grab_matcher_checks_lock_conditions() {
+        let numpad1 = parse_hotkey("Numpad1").unwrap();
+        let hotkeys = vec![
+            numpad1.clone().when_num_lock(true),
+            numpad1.when_num_lock(false),
+            parse_hotkey("F8").unwrap().when_caps_lock(true),
+        ];
+        let mut matcher = GrabMatcher::new(&hotkeys, ListenerConfig::default());
+        let kp1 = to_keycode(Key::Numpad1).unwrap();
+        let f8 = to_keycode(Key::F8).unwrap();
+        let now = Instant::now();
+
+        let num = ModMask::M2.bits();
+        assert_eq!(matcher.press(kp1, num, now), vec![HotkeyEvent::Pressed(0)]);
+        matcher.release(kp1);
+        assert_eq!(matcher.press(kp1, 0, now), vec![HotkeyEvent::Pressed(1)]);
+        matcher.release(kp1);
+
+        assert!(matcher.press(f8, 0, now).is_empty());
+        matcher.release(f8);
+        let caps = ModMask::LOCK.bits();
+        assert_eq!(matcher.press(f8, caps, now), vec![HotkeyEvent::Pressed(2)]);
+    }
+
+    #[test]
+    fn test_remove_keeps_grab_shared_with_another_hotkey() {
+        let hotkeys = vec![parse_hotkey("F8").unwrap(), parse_hotkey("F8").unwrap()];
+        let mut matcher = GrabMatcher::new(&hotkeys, ListenerConfig::default());
+        assert_eq!(matcher.remove(0), None);
+        assert_eq!(matcher.remove(1), Some((to_keycode(Key::F8).unwrap(), 0)));
+        assert_eq!(matcher.remove(1), None);
+        assert!(matcher
+            .press(to_keycode(Key::F8).unwrap(), 0, Instant::now())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_muted_grab_matcher_stops_repeating() {
+        let config = ListenerConfig {
+            emit_repeats: true,
+            ..ListenerConfig::default()
+        };
+        let paused = config.shared.paused.clone();
+        let mut matcher = GrabMatcher::new(&[parse_hotkey("F8").unwrap()], config);
+        let f8 = to_keycode(Key::F8).unwrap();
+        let now = Instant::now();
+
+        assert_eq!(matcher.press(f8, 0, now), vec![HotkeyEvent::Pressed(0)]);
+        assert_eq!(matcher.press(f8, 0, now), vec![HotkeyEvent::Repeat(0)]);
+        paused.set(true);
+        assert!(matcher.press(f8, 0, now).is_empty());
+        assert_eq!(matcher.release(f8), vec![HotkeyEvent::Released(0)]);
+    }
+}